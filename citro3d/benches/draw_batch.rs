@@ -0,0 +1,105 @@
+//! Benchmarks for `DrawBatch::flush` against binding each object's uniforms and
+//! textures and drawing it with `draw_arrays`. Prints the command buffer space
+//! used by one frame of each, and the CPU cost of recording the draw commands.
+//! Run on hardware with `cargo 3ds bench`.
+
+#![feature(allocator_api)]
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use citro3d::batch::DrawBatch;
+use citro3d::macros::include_shader;
+use citro3d::math::Matrix4;
+use citro3d::texture::{Tex, TexFormat};
+use citro3d::{attrib, buffer, shader, Instance};
+
+const ITERATIONS: u32 = 100;
+const OBJECTS: usize = 64;
+/// Consecutive objects share a transform in groups of this size, like parts
+/// of the same model.
+const PARTS_PER_MODEL: usize = 4;
+
+static SHADER_BYTES: &[u8] = include_shader!("../examples/assets/vshader.pica");
+
+fn bench(instance: &mut Instance, name: &str, mut f: impl FnMut(&mut Instance)) {
+    // Warm up caches before timing
+    for _ in 0..ITERATIONS / 10 {
+        instance.render_frame_with(&mut f).unwrap();
+    }
+
+    let mut elapsed = Duration::ZERO;
+    let mut cmdbuf_used = 0;
+    for _ in 0..ITERATIONS {
+        instance
+            .render_frame_with(|instance| {
+                let remaining = instance.cmdbuf_remaining();
+                let start = Instant::now();
+                f(instance);
+                elapsed += start.elapsed();
+                cmdbuf_used = remaining - instance.cmdbuf_remaining();
+            })
+            .unwrap();
+    }
+    println!(
+        "{name:<24} {:?}/iter, {cmdbuf_used} bytes of command buffer",
+        elapsed / ITERATIONS
+    );
+}
+
+fn main() {
+    let mut instance = Instance::new().unwrap();
+
+    let library = shader::Library::from_bytes(SHADER_BYTES).unwrap();
+    let program = Arc::pin(shader::Program::new(library.get(0).unwrap()).unwrap());
+    let projection = program.get_uniform("projection").unwrap();
+    instance.bind_program(program.clone());
+
+    let mut attr_info = attrib::Info::new();
+    attr_info
+        .add_loader(attrib::Register::new(0).unwrap(), attrib::Format::Float, 3)
+        .unwrap();
+    attr_info
+        .add_loader(attrib::Register::new(1).unwrap(), attrib::Format::Float, 3)
+        .unwrap();
+    instance.set_attr_info(&attr_info);
+
+    let vbo = buffer::LinearBuffer::from_slice(&[[0.0f32; 6]; 3]);
+    let mut buf_info = buffer::Info::new();
+    let slice = buf_info.add(vbo.as_ref(), &attr_info).unwrap();
+
+    let textures = [
+        Tex::new_simple(8, 8, TexFormat::Rgba8).unwrap(),
+        Tex::new_simple(8, 8, TexFormat::Rgba8).unwrap(),
+    ];
+    let objects: Vec<_> = (0..OBJECTS)
+        .map(|i| {
+            let mut transform = Matrix4::identity();
+            transform.translate((i / PARTS_PER_MODEL) as f32, 0.0, -3.0);
+            // Objects sorted by texture, as a renderer would to batch them
+            (transform, &textures[i * textures.len() / OBJECTS])
+        })
+        .collect();
+
+    bench(&mut instance, "per-object draw_arrays", |instance| {
+        for (transform, tex) in black_box(&objects) {
+            instance.bind_vertex_uniform(projection, transform).unwrap();
+            tex.bind(0).unwrap();
+            instance
+                .draw_arrays(buffer::Primitive::Triangles, slice)
+                .unwrap();
+        }
+    });
+
+    bench(&mut instance, "DrawBatch::flush", |instance| {
+        let mut batch = DrawBatch::new(&program, &attr_info);
+        for (transform, tex) in black_box(&objects) {
+            batch
+                .draw(buffer::Primitive::Triangles, slice)
+                .uniform(shader::Type::Vertex, projection, transform)
+                .texture(0, tex);
+        }
+        batch.flush(instance).unwrap();
+    });
+}
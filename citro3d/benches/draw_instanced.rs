@@ -0,0 +1,87 @@
+//! Benchmarks for `Instance::draw_instanced` against drawing each instance with
+//! its own uniform upload and `draw_arrays` call. This only measures the CPU
+//! cost of recording the draw commands. Run on hardware with `cargo 3ds bench`.
+
+#![feature(allocator_api)]
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use citro3d::macros::include_shader;
+use citro3d::math::Matrix4;
+use citro3d::{attrib, buffer, shader, Instance};
+
+const ITERATIONS: u32 = 100;
+const INSTANCES: usize = 64;
+
+static SHADER_BYTES: &[u8] = include_shader!("../examples/assets/vshader.pica");
+
+fn bench(instance: &mut Instance, name: &str, mut f: impl FnMut(&mut Instance)) {
+    // Warm up caches before timing
+    for _ in 0..ITERATIONS / 10 {
+        instance.render_frame_with(&mut f).unwrap();
+    }
+
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        instance
+            .render_frame_with(|instance| {
+                let start = Instant::now();
+                f(instance);
+                elapsed += start.elapsed();
+            })
+            .unwrap();
+    }
+    println!("{name:<24} {:?}/iter", elapsed / ITERATIONS);
+}
+
+fn main() {
+    let mut instance = Instance::new().unwrap();
+
+    let library = shader::Library::from_bytes(SHADER_BYTES).unwrap();
+    let program = shader::Program::new(library.get(0).unwrap()).unwrap();
+    let projection = program.get_uniform("projection").unwrap();
+    instance.bind_program(Arc::pin(program));
+
+    let mut attr_info = attrib::Info::new();
+    attr_info
+        .add_loader(attrib::Register::new(0).unwrap(), attrib::Format::Float, 3)
+        .unwrap();
+    attr_info
+        .add_loader(attrib::Register::new(1).unwrap(), attrib::Format::Float, 3)
+        .unwrap();
+    instance.set_attr_info(&attr_info);
+
+    let vbo = buffer::LinearBuffer::from_slice(&[[0.0f32; 6]; 3]);
+    let mut buf_info = buffer::Info::new();
+    let slice = buf_info.add(vbo.as_ref(), &attr_info).unwrap();
+
+    let transforms: Vec<_> = (0..INSTANCES)
+        .map(|i| {
+            let mut m = Matrix4::identity();
+            m.translate(i as f32, 0.0, -3.0);
+            m
+        })
+        .collect();
+
+    bench(&mut instance, "manual loop", |instance| {
+        for transform in black_box(&transforms) {
+            instance.bind_vertex_uniform(projection, transform).unwrap();
+            instance
+                .draw_arrays(buffer::Primitive::Triangles, slice)
+                .unwrap();
+        }
+    });
+
+    bench(&mut instance, "draw_instanced", |instance| {
+        instance
+            .draw_instanced(
+                buffer::Primitive::Triangles,
+                slice,
+                black_box(&transforms),
+                projection,
+            )
+            .unwrap();
+    });
+}
@@ -0,0 +1,44 @@
+//! Benchmarks for building light LUTs, e.g. for LUTs which are animated every
+//! frame. Run on hardware with `cargo 3ds bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use citro3d::light::LightLut;
+
+const ITERATIONS: u32 = 1000;
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    // Warm up caches before timing
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iter: Duration = start.elapsed() / ITERATIONS;
+    println!("{name:<24} {per_iter:?}/iter");
+}
+
+fn main() {
+    let mut exponent = 1.0f32;
+
+    bench("from_fn", || {
+        exponent += 0.01;
+        black_box(LightLut::from_fn(|x| x.powf(black_box(exponent)), false));
+    });
+
+    let mut lut = LightLut::from_fn(|x| x, false);
+    bench("from_fn_into", || {
+        exponent += 0.01;
+        lut.from_fn_into(|x| x.powf(black_box(exponent)), false);
+        black_box(&lut);
+    });
+
+    bench("from_fn (negative)", || {
+        exponent += 0.01;
+        black_box(LightLut::from_fn(|x| x.abs().powf(black_box(exponent)), true));
+    });
+}
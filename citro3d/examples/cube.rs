@@ -3,6 +3,7 @@
 
 #![feature(allocator_api)]
 
+use citro3d::color::Color;
 use citro3d::macros::include_shader;
 use citro3d::math::{
     AspectRatio, ClipPlanes, CoordinateOrientation, FVec3, Matrix4, Projection, StereoDisplacement,
@@ -152,41 +153,47 @@ fn main() {
             break;
         }
 
-        instance.render_frame_with(|instance| {
-            let mut render_to = |target: &mut render::Target, projection| {
-                target.clear(ClearFlags::ALL, CLEAR_COLOR, 0);
-
-                instance
-                    .select_render_target(target)
-                    .expect("failed to set render target");
-
-                instance.bind_vertex_uniform(
-                    projection_uniform_idx,
-                    &(projection * camera_transform.clone()),
-                );
-
-                instance.set_attr_info(&attr_info);
-                unsafe {
-                    instance.draw_elements(
-                        buffer::Primitive::Triangles,
-                        &buf_info,
-                        IndexType::U16(&indecies),
-                    );
-                }
-
-                //instance.draw_arrays(buffer::Primitive::Triangles, vbo_data);
-            };
-
-            let Projections {
-                left_eye,
-                right_eye,
-                center,
-            } = calculate_projections();
-
-            render_to(&mut top_left_target, &left_eye);
-            render_to(&mut top_right_target, &right_eye);
-            render_to(&mut bottom_target, &center);
-        });
+        instance
+            .render_frame_with(|instance| {
+                let mut render_to = |target: &mut render::Target, projection| {
+                    target.clear(ClearFlags::ALL, Color::from_packed(CLEAR_COLOR), 0.0, 0);
+
+                    instance
+                        .select_render_target(target)
+                        .expect("failed to set render target");
+
+                    instance
+                        .bind_vertex_uniform(
+                            projection_uniform_idx,
+                            &(projection * camera_transform.clone()),
+                        )
+                        .expect("failed to bind projection");
+
+                    instance.set_attr_info(&attr_info);
+                    unsafe {
+                        instance
+                            .draw_elements(
+                                buffer::Primitive::Triangles,
+                                &buf_info,
+                                IndexType::U16(&indecies),
+                            )
+                            .expect("failed to draw");
+                    }
+
+                    //instance.draw_arrays(buffer::Primitive::Triangles, vbo_data);
+                };
+
+                let Projections {
+                    left_eye,
+                    right_eye,
+                    center,
+                } = calculate_projections();
+
+                render_to(&mut top_left_target, &left_eye);
+                render_to(&mut top_right_target, &right_eye);
+                render_to(&mut bottom_target, &center);
+            })
+            .expect("failed to render frame");
     }
 }
 
@@ -302,11 +302,14 @@ fn main() {
     let mut buf_info = buffer::Info::new();
     let (attr_info, vbo_data) = prepare_vbos(&mut buf_info, &vbo_data);
     let mut light_env = instance.light_env_mut();
-    light_env.as_mut().connect_lut(
-        LightLutId::D0,
-        LutInput::LightNormal,
-        LightLut::from_fn(|v| v.powf(10.0), false),
-    );
+    light_env
+        .as_mut()
+        .connect_lut(
+            LightLutId::D0,
+            LutInput::LightNormal,
+            LightLut::from_fn(|v| v.powf(10.0), false),
+        )
+        .expect("failed to connect LUT");
     light_env.as_mut().set_material(Material {
         ambient: Some(Color::new(0.2, 0.2, 0.2)),
         diffuse: Some(Color::new(1.0, 0.4, 1.0)),
@@ -325,20 +328,12 @@ fn main() {
     let mut c = Matrix4::identity();
     let model_idx = program.get_uniform("modelView").unwrap();
     c.translate(0.0, 0.0, -2.0);
-    instance.bind_vertex_uniform(model_idx, &c);
+    instance.bind_vertex_uniform(model_idx, &c).unwrap();
 
-    // Configure the first fragment shading substage to just pass through the vertex color
+    // Configure the first fragment shading substage to output the lighting result
     // See https://www.opengl.org/sdk/docs/man2/xhtml/glTexEnv.xml for more insight
     let stage0 = texenv::Stage::new(0).unwrap();
-    instance
-        .texenv(stage0)
-        .src(
-            texenv::Mode::BOTH,
-            texenv::Source::FragmentPrimaryColor,
-            Some(texenv::Source::FragmentSecondaryColor),
-            None,
-        )
-        .func(texenv::Mode::BOTH, texenv::CombineFunc::Add);
+    instance.texenv(stage0).lighting_passthrough();
 
     let projection_uniform_idx = program.get_uniform("projection").unwrap();
 
@@ -349,32 +344,40 @@ fn main() {
             break;
         }
 
-        instance.render_frame_with(|instance| {
-            let mut render_to = |target: &mut render::Target, projection| {
-                target.clear(ClearFlags::ALL, 0, 0);
-
-                instance
-                    .select_render_target(target)
-                    .expect("failed to set render target");
-
-                instance.bind_vertex_uniform(projection_uniform_idx, projection);
-                instance.bind_vertex_uniform(model_idx, &c);
-
-                instance.set_attr_info(&attr_info);
-
-                instance.draw_arrays(buffer::Primitive::Triangles, vbo_data);
-            };
-
-            let Projections {
-                left_eye,
-                right_eye,
-                center,
-            } = calculate_projections();
-
-            render_to(&mut top_left_target, &left_eye);
-            render_to(&mut top_right_target, &right_eye);
-            render_to(&mut bottom_target, &center);
-        });
+        instance
+            .render_frame_with(|instance| {
+                let mut render_to = |target: &mut render::Target, projection| {
+                    target.clear(ClearFlags::ALL, Color::default(), 0.0, 0);
+
+                    instance
+                        .select_render_target(target)
+                        .expect("failed to set render target");
+
+                    instance
+                        .bind_vertex_uniform(projection_uniform_idx, projection)
+                        .expect("failed to bind projection");
+                    instance
+                        .bind_vertex_uniform(model_idx, &c)
+                        .expect("failed to bind model view");
+
+                    instance.set_attr_info(&attr_info);
+
+                    instance
+                        .draw_arrays(buffer::Primitive::Triangles, vbo_data)
+                        .expect("failed to draw");
+                };
+
+                let Projections {
+                    left_eye,
+                    right_eye,
+                    center,
+                } = calculate_projections();
+
+                render_to(&mut top_left_target, &left_eye);
+                render_to(&mut top_right_target, &right_eye);
+                render_to(&mut bottom_target, &center);
+            })
+            .expect("failed to render frame");
         c.translate(0.0, 0.0, 2.0);
         c.rotate_y(1.0f32.to_radians());
         c.translate(0.0, 0.0, -2.0);
@@ -3,8 +3,12 @@
 
 #![feature(allocator_api)]
 
+use std::sync::Arc;
+
+use citro3d::color::Color;
 use citro3d::macros::include_shader;
 use citro3d::math::{AspectRatio, ClipPlanes, Matrix4, Projection, StereoDisplacement};
+use citro3d::pipeline::Pipeline;
 use citro3d::render::ClearFlags;
 use citro3d::texenv;
 use citro3d::{attrib, buffer, render, shader};
@@ -81,8 +85,7 @@ fn main() {
     let shader = shader::Library::from_bytes(SHADER_BYTES).unwrap();
     let vertex_shader = shader.get(0).unwrap();
 
-    let program = shader::Program::new(vertex_shader).unwrap();
-    instance.bind_program(&program);
+    let program = Arc::pin(shader::Program::new(vertex_shader).unwrap());
 
     let mut vbo_data = Vec::with_capacity_in(VERTICES.len(), ctru::linear::LinearAllocator);
     vbo_data.extend_from_slice(VERTICES);
@@ -90,15 +93,15 @@ fn main() {
     let mut buf_info = buffer::Info::new();
     let (attr_info, vbo_data) = prepare_vbos(&mut buf_info, &vbo_data);
 
+    let projection_uniform_idx = program.get_uniform("projection").unwrap();
+
     // Configure the first fragment shading substage to just pass through the vertex color
     // See https://www.opengl.org/sdk/docs/man2/xhtml/glTexEnv.xml for more insight
-    let stage0 = texenv::Stage::new(0).unwrap();
-    instance
-        .texenv(stage0)
-        .src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
-        .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
-
-    let projection_uniform_idx = program.get_uniform("projection").unwrap();
+    let pipeline = Pipeline::new(program, attr_info).stage(texenv::Stage::new(0).unwrap(), |env| {
+        env.src(texenv::Mode::BOTH, texenv::Source::PrimaryColor, None, None)
+            .func(texenv::Mode::BOTH, texenv::CombineFunc::Replace);
+    });
+    pipeline.bind(&mut instance);
 
     while apt.main_loop() {
         hid.scan_input();
@@ -107,31 +110,35 @@ fn main() {
             break;
         }
 
-        instance.render_frame_with(|instance| {
-            let mut render_to = |target: &mut render::Target, projection| {
-                target.clear(ClearFlags::ALL, CLEAR_COLOR, 0);
-
-                instance
-                    .select_render_target(target)
-                    .expect("failed to set render target");
-
-                instance.bind_vertex_uniform(projection_uniform_idx, projection);
-
-                instance.set_attr_info(&attr_info);
-
-                instance.draw_arrays(buffer::Primitive::Triangles, vbo_data);
-            };
-
-            let Projections {
-                left_eye,
-                right_eye,
-                center,
-            } = calculate_projections();
-
-            render_to(&mut top_left_target, &left_eye);
-            render_to(&mut top_right_target, &right_eye);
-            render_to(&mut bottom_target, &center);
-        });
+        instance
+            .render_frame_with(|instance| {
+                let mut render_to = |target: &mut render::Target, projection| {
+                    target.clear(ClearFlags::ALL, Color::from_packed(CLEAR_COLOR), 0.0, 0);
+
+                    instance
+                        .select_render_target(target)
+                        .expect("failed to set render target");
+
+                    instance
+                        .bind_vertex_uniform(projection_uniform_idx, projection)
+                        .expect("failed to bind projection");
+
+                    instance
+                        .draw_arrays(buffer::Primitive::Triangles, vbo_data)
+                        .expect("failed to draw");
+                };
+
+                let Projections {
+                    left_eye,
+                    right_eye,
+                    center,
+                } = calculate_projections();
+
+                render_to(&mut top_left_target, &left_eye);
+                render_to(&mut top_right_target, &right_eye);
+                render_to(&mut bottom_target, &center);
+            })
+            .expect("failed to render frame");
     }
 }
 
@@ -7,6 +7,9 @@
 
 use std::mem::MaybeUninit;
 
+use crate::error::CodeSource;
+use crate::shader;
+
 /// Vertex attribute info. This struct describes how vertex buffers are
 /// layed out and used (i.e. the shape of the vertex data).
 #[derive(Debug, Clone, Copy)]
@@ -33,16 +36,27 @@ impl Register {
             Err(crate::Error::TooManyAttributes)
         }
     }
+
+    /// Get the index of this register, i.e. `n` for `vn`.
+    pub fn index(&self) -> u8 {
+        self.0 as u8
+    }
 }
 
 /// An attribute index. This is the attribute's actual index in the input buffer,
 /// and may correspond to any [`Register`] (or multiple) as input in the shader
 /// program.
-#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
-pub struct Index(u8);
+pub struct Index(pub(crate) u8);
 
 /// The data format of an attribute.
+///
+/// These are the only formats the PICA200 supports: the format of each
+/// attribute is stored in a 2-bit field, and `GPU_FORMATS` has no other
+/// values. There is no unsigned short or 32-bit integer format, so such data
+/// has to be converted to [`Format::Short`] (if it fits) or [`Format::Float`].
+/// All formats are converted to floats when loaded into the shader's input
+/// registers, without any normalization.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy)]
 #[doc(alias = "GPU_FORMATS")]
@@ -96,6 +110,36 @@ impl Info {
         }
     }
 
+    /// Create an attribute info with a loader for each `(register, format, count)`
+    /// in `loaders`, added in order as with [`Info::add_loader`]. The returned
+    /// indices correspond to the entries of `loaders`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first entry which could not be added, see
+    /// [`Info::add_loader`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _runner = test_runner::GdbRunner::default();
+    /// # use citro3d::attrib::{Format, Info, Register};
+    /// let (attr_info, indices) = Info::from_loaders(&[
+    ///     (Register::new(0).unwrap(), Format::Float, 3),
+    ///     (Register::new(1).unwrap(), Format::Float, 2),
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(indices.len(), 2);
+    /// ```
+    pub fn from_loaders(loaders: &[(Register, Format, u8)]) -> crate::Result<(Self, Vec<Index>)> {
+        let mut info = Self::new();
+        let indices = loaders
+            .iter()
+            .map(|&(register, format, count)| info.add_loader(register, format, count))
+            .collect::<crate::Result<_>>()?;
+        Ok((info, indices))
+    }
+
     /// Add an attribute loader to the attribute info. The resulting attribute index
     /// indicates the registration order of the attributes.
     ///
@@ -128,18 +172,161 @@ impl Info {
         };
 
         let Ok(idx) = ret.try_into() else {
-            return Err(crate::Error::TooManyAttributes);
+            return Err(crate::Error::from_c3d_code(
+                CodeSource::AttrInfoAddLoader,
+                ret,
+            ));
         };
 
         Ok(Index(idx))
     }
 
+    /// Add a fixed attribute to the attribute info. Instead of being loaded
+    /// from a vertex buffer, a fixed attribute has the same value for every
+    /// vertex, which is set with
+    /// [`Instance::set_fixed_attribute`](crate::Instance::set_fixed_attribute).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this attribute info already has the maximum number
+    /// of attributes.
+    #[doc(alias = "AttrInfo_AddFixed")]
+    pub fn add_fixed(&mut self, register: Register) -> crate::Result<Index> {
+        // SAFETY: see `add_loader`
+        let ret = unsafe { citro3d_sys::AttrInfo_AddFixed(&mut self.0, register.0) };
+
+        let Ok(idx) = ret.try_into() else {
+            return Err(crate::Error::from_c3d_code(
+                CodeSource::AttrInfoAddLoader,
+                ret,
+            ));
+        };
+
+        Ok(Index(idx))
+    }
+
+    /// Whether the attribute at index `i` was added with [`Info::add_fixed`].
+    fn is_fixed(&self, i: usize) -> bool {
+        // The fixed attribute mask is stored in bits 16..28 of the high flags
+        self.0.flags[1] & (1 << (16 + i)) != 0
+    }
+
+    /// Get the attribute index of the fixed attribute for `register`, if there
+    /// is one.
+    pub(crate) fn fixed_index(&self, register: Register) -> Option<Index> {
+        (0..self.attr_count() as usize)
+            .filter(|&i| self.is_fixed(i))
+            .find(|&i| (self.permutation() >> (i * 4)) & 0xF == register.0 as u64)
+            .map(|i| Index(i as u8))
+    }
+
+    /// Check that the registers used by this attribute info match the input
+    /// registers declared by the vertex shader of `program`. This catches
+    /// loaders registered for the wrong register before any garbage geometry
+    /// is drawn.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AttributeMismatch`](crate::Error::AttributeMismatch) for
+    /// the first register that is used by only one of the shader or the attributes.
+    pub fn validate(&self, program: &shader::Program) -> crate::Result<()> {
+        let attr_mask = self
+            .permutation_decoded()
+            .iter()
+            .fold(0u16, |mask, reg| mask | (1 << reg.index()));
+        let diff = attr_mask ^ program.input_register_mask();
+
+        if diff == 0 {
+            Ok(())
+        } else {
+            Err(crate::Error::AttributeMismatch(diff.trailing_zeros() as u8))
+        }
+    }
+
+    /// Get the packed attribute permutation: the [`Register`] index each
+    /// attribute is loaded into, 4 bits per attribute starting from the least
+    /// significant bits. See [`Info::permutation_decoded`] for a readable form.
     pub fn permutation(&self) -> u64 {
         self.0.permutation
     }
 
+    /// Get the [`Register`] each attribute is loaded into, in attribute index
+    /// order. Comparing this against the shader's input registers helps track
+    /// down attributes that were registered in the wrong order.
+    pub fn permutation_decoded(&self) -> Vec<Register> {
+        (0..self.attr_count())
+            .map(|i| Register(((self.permutation() >> (i * 4)) & 0xF) as libc::c_int))
+            .collect()
+    }
+
     /// Get the number of registered attributes.
     pub fn attr_count(&self) -> libc::c_int {
         self.0.attrCount
     }
+
+    /// The number of attributes loaded from vertex buffers, i.e. not added
+    /// with [`Info::add_fixed`].
+    pub(crate) fn loader_count(&self) -> usize {
+        (0..self.attr_count() as usize)
+            .filter(|&i| !self.is_fixed(i))
+            .count()
+    }
+
+    /// The number of bytes each vertex takes up if all the attributes are
+    /// stored interleaved in a single buffer.
+    pub(crate) fn vertex_stride(&self) -> u32 {
+        (0..self.attr_count() as usize)
+            .filter(|&i| !self.is_fixed(i))
+            .map(|i| {
+                // Each attribute is stored as `(count - 1) << 2 | format` in 4 bits
+                let bits = (self.0.flags[i / 8] >> ((i % 8) * 4)) & 0xF;
+                let size = match bits & 0x3 {
+                    ctru_sys::GPU_BYTE | ctru_sys::GPU_UNSIGNED_BYTE => 1,
+                    ctru_sys::GPU_SHORT => 2,
+                    _ => 4,
+                };
+                size * ((bits >> 2) + 1)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_permutation() {
+        let mut info = Info::new();
+        info.add_loader(Register::new(2).unwrap(), Format::Float, 3)
+            .unwrap();
+        info.add_loader(Register::new(0).unwrap(), Format::Float, 2)
+            .unwrap();
+        info.add_fixed(Register::new(7).unwrap()).unwrap();
+
+        let registers: Vec<_> = info
+            .permutation_decoded()
+            .iter()
+            .map(Register::index)
+            .collect();
+        assert_eq!(registers, [2, 0, 7]);
+        assert_eq!(info.permutation(), 0x702);
+    }
+
+    #[test]
+    fn from_loaders() {
+        let (info, indices) = Info::from_loaders(&[
+            (Register::new(2).unwrap(), Format::Float, 3),
+            (Register::new(0).unwrap(), Format::UnsignedByte, 4),
+        ])
+        .unwrap();
+        assert_eq!(indices.iter().map(|idx| idx.0).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(info.permutation(), 0x02);
+
+        let too_long = Info::from_loaders(&[
+            (Register::new(0).unwrap(), Format::Float, 3),
+            (Register::new(1).unwrap(), Format::Float, 5),
+        ]);
+        assert!(matches!(too_long, Err(crate::Error::InvalidSize)));
+    }
 }
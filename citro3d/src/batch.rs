@@ -0,0 +1,342 @@
+//! Batched drawing of many objects sharing the same shader program and vertex
+//! attributes.
+//!
+//! A [`DrawBatch`] records draw calls along with the uniforms and textures each
+//! one needs, then [flushes](DrawBatch::flush) them all at once. State which is
+//! the same as the previous draw in the batch (buffer info, uniform values and
+//! bound textures) is not sent to the GPU again, which saves command buffer space
+//! compared to rebinding everything for every object.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::texture::Tex;
+use crate::uniform::{self, Uniform, UniformCache};
+use crate::{attrib, buffer, shader, Instance};
+
+/// A batch of draw calls sharing a [`shader::Program`] and [`attrib::Info`].
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use std::sync::Arc;
+/// # use citro3d::batch::DrawBatch;
+/// # use citro3d::{attrib, buffer, shader, uniform};
+/// # use citro3d::math::Matrix4;
+/// # fn draw(
+/// #     instance: &mut citro3d::Instance,
+/// #     program: &std::pin::Pin<Arc<shader::Program>>,
+/// #     attr_info: &attrib::Info,
+/// #     meshes: &[(buffer::Slice, Matrix4)],
+/// # ) {
+/// let model_idx = uniform::Index::from(4);
+///
+/// let mut batch = DrawBatch::new(program, attr_info);
+/// for (slice, transform) in meshes {
+///     batch
+///         .draw(buffer::Primitive::Triangles, *slice)
+///         .uniform(shader::Type::Vertex, model_idx, transform);
+/// }
+/// batch.flush(instance).expect("invalid vertex count");
+/// # }
+/// ```
+#[must_use]
+pub struct DrawBatch<'a> {
+    program: Pin<Arc<shader::Program>>,
+    attr_info: &'a attrib::Info,
+    draws: Vec<Draw<'a>>,
+}
+
+/// How many state changes a [`DrawBatch::flush`] sent to the GPU. Anything
+/// which was redundant with the previous draw in the batch is not counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlushStats {
+    /// The number of draw calls issued.
+    pub draws: usize,
+    /// The number of times the buffer info was bound.
+    pub buffer_binds: usize,
+    /// The number of uniforms uploaded.
+    pub uniform_uploads: usize,
+    /// The number of textures bound.
+    pub texture_binds: usize,
+}
+
+/// A single draw call recorded in a [`DrawBatch`].
+pub struct Draw<'a> {
+    primitive: buffer::Primitive,
+    slice: buffer::Slice<'a>,
+    uniforms: Vec<(shader::Type, uniform::Index, Uniform)>,
+    textures: Vec<(i32, &'a Tex)>,
+}
+
+impl<'a> DrawBatch<'a> {
+    /// Create an empty batch which will draw using the given program and
+    /// vertex attributes.
+    pub fn new(program: &Pin<Arc<shader::Program>>, attr_info: &'a attrib::Info) -> Self {
+        Self {
+            program: program.clone(),
+            attr_info,
+            draws: Vec::new(),
+        }
+    }
+
+    /// Record a draw of `slice`. Uniforms and textures for this draw can be
+    /// set on the returned [`Draw`].
+    pub fn draw(
+        &mut self,
+        primitive: buffer::Primitive,
+        slice: buffer::Slice<'a>,
+    ) -> &mut Draw<'a> {
+        self.draws.push(Draw {
+            primitive,
+            slice,
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+        });
+        self.draws.last_mut().unwrap()
+    }
+
+    /// Get the number of draws recorded in the batch.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// Whether the batch has any draws recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    /// Issue all recorded draws, skipping any state changes that would be
+    /// redundant with the previous draw. The batch is emptied afterwards so it
+    /// can be reused. Returns how many state changes were actually sent.
+    ///
+    /// Uniforms and textures set for one draw stay bound for later draws in the
+    /// batch unless they are overwritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidVertexCount`](crate::Error::InvalidVertexCount)
+    /// if any draw's slice length is not valid for its primitive and the batch's
+    /// program (see [`buffer::Primitive::validate_count`]), or
+    /// [`Error::UniformKindMismatch`](crate::Error::UniformKindMismatch) or
+    /// [`Error::UniformOverflow`](crate::Error::UniformOverflow) if any of its
+    /// uniforms can't be bound to their index, or
+    /// [`Error::InvalidTextureUnit`](crate::Error::InvalidTextureUnit) if any of
+    /// its textures is bound to a unit outside `0..=3`. Nothing is drawn in that case,
+    /// and the batch is left unchanged.
+    ///
+    /// Returns [`Error::SamplingRenderTarget`](crate::Error::SamplingRenderTarget)
+    /// if a draw would sample the texture of the selected render target, or
+    /// [`Error::CommandBufferFull`](crate::Error::CommandBufferFull) if there
+    /// isn't enough command buffer space left for a draw. The draws before it
+    /// have been issued in that case, and the batch is emptied.
+    #[doc(alias = "C3D_DrawArrays")]
+    pub fn flush(&mut self, instance: &mut Instance) -> crate::Result<FlushStats> {
+        for draw in &self.draws {
+            draw.primitive
+                .validate_draw(Some(&*self.program), draw.slice.len().try_into()?)?;
+            for (_, index, uniform) in &draw.uniforms {
+                uniform.check_index(*index)?;
+            }
+            if let Some((unit, _)) = draw
+                .textures
+                .iter()
+                .find(|(unit, _)| !(0..4).contains(unit))
+            {
+                return Err(crate::Error::InvalidTextureUnit(*unit));
+            }
+        }
+
+        instance.bind_program(self.program.clone());
+        instance.set_attr_info(self.attr_info);
+
+        let mut bound_buf: Option<*const buffer::Info> = None;
+        let mut uniforms = UniformCache::new();
+        let mut bound_textures: [Option<*const Tex>; 4] = [None; 4];
+        let mut stats = FlushStats::default();
+
+        for draw in self.draws.drain(..) {
+            let buf: *const _ = draw.slice.info();
+            if bound_buf != Some(buf) {
+                instance.set_buffer_info(draw.slice.info());
+                bound_buf = Some(buf);
+                stats.buffer_binds += 1;
+            }
+
+            for (ty, index, uniform) in draw.uniforms {
+                if uniforms.set_uniform_cached(instance, ty, index, uniform)? {
+                    stats.uniform_uploads += 1;
+                }
+            }
+
+            for (unit, tex) in draw.textures {
+                let slot = &mut bound_textures[unit as usize];
+                if *slot != Some(tex as *const _) {
+                    tex.bind(unit)?;
+                    *slot = Some(tex);
+                    stats.texture_binds += 1;
+                }
+            }
+
+            instance.prepare_draw()?;
+            unsafe {
+                citro3d_sys::C3D_DrawArrays(
+                    draw.primitive as ctru_sys::GPU_Primitive_t,
+                    draw.slice.index(),
+                    draw.slice.len(),
+                );
+            }
+            stats.draws += 1;
+        }
+        Ok(stats)
+    }
+}
+
+impl<'a> Draw<'a> {
+    /// Set a uniform for this draw.
+    pub fn uniform(
+        &mut self,
+        ty: shader::Type,
+        index: uniform::Index,
+        uniform: impl Into<Uniform>,
+    ) -> &mut Self {
+        self.uniforms.push((ty, index, uniform.into()));
+        self
+    }
+
+    /// Bind a texture to the given texture unit (`0..=3`) for this draw. An
+    /// invalid unit makes [`DrawBatch::flush`] fail.
+    pub fn texture(&mut self, unit: i32, tex: &'a Tex) -> &mut Self {
+        self.textures.push((unit, tex));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::include_shader;
+    use crate::math::Matrix4;
+    use crate::texture::TexFormat;
+
+    static SHADER_BYTES: &[u8] = include_shader!("../examples/assets/vshader.pica");
+
+    fn program() -> (Pin<Arc<shader::Program>>, uniform::Index) {
+        let library = shader::Library::from_bytes(SHADER_BYTES).unwrap();
+        let program = shader::Program::new(library.get(0).unwrap()).unwrap();
+        let projection = program.get_uniform("projection").unwrap();
+        (Arc::pin(program), projection)
+    }
+
+    fn attr_info() -> attrib::Info {
+        let mut attr_info = attrib::Info::new();
+        attr_info
+            .add_loader(attrib::Register::new(0).unwrap(), attrib::Format::Float, 3)
+            .unwrap();
+        attr_info
+            .add_loader(attrib::Register::new(1).unwrap(), attrib::Format::Float, 3)
+            .unwrap();
+        attr_info
+    }
+
+    #[test]
+    fn flush_rejects_invalid_draws() {
+        let mut instance = Instance::new().unwrap();
+        let (program, projection) = program();
+        let attr_info = attr_info();
+
+        let vbo = buffer::LinearBuffer::from_slice(&[[0.0f32; 6]; 4]);
+        let mut buf_info = buffer::Info::new();
+        let slice = buf_info.add(vbo.as_ref(), &attr_info).unwrap();
+        let tex = Tex::new_simple(8, 8, TexFormat::L8).unwrap();
+
+        let mut batch = DrawBatch::new(&program, &attr_info);
+        batch.draw(buffer::Primitive::TriangleStrip, slice);
+        batch.draw(buffer::Primitive::Triangles, slice);
+        assert!(matches!(
+            batch.flush(&mut instance),
+            Err(crate::Error::InvalidVertexCount { count: 4, .. })
+        ));
+        assert_eq!(batch.len(), 2);
+
+        let mut batch = DrawBatch::new(&program, &attr_info);
+        batch.draw(buffer::Primitive::TriangleStrip, slice).uniform(
+            shader::Type::Vertex,
+            uniform::Index::from(94),
+            Matrix4::identity(),
+        );
+        assert!(matches!(
+            batch.flush(&mut instance),
+            Err(crate::Error::UniformOverflow { start: 94, len: 4 })
+        ));
+        assert_eq!(batch.len(), 1);
+
+        let mut batch = DrawBatch::new(&program, &attr_info);
+        batch.draw(buffer::Primitive::TriangleStrip, slice).uniform(
+            shader::Type::Vertex,
+            projection,
+            true,
+        );
+        assert!(matches!(
+            batch.flush(&mut instance),
+            Err(crate::Error::UniformKindMismatch { .. })
+        ));
+        assert_eq!(batch.len(), 1);
+
+        let mut batch = DrawBatch::new(&program, &attr_info);
+        batch
+            .draw(buffer::Primitive::TriangleStrip, slice)
+            .texture(4, &tex);
+        assert!(matches!(
+            batch.flush(&mut instance),
+            Err(crate::Error::InvalidTextureUnit(4))
+        ));
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    fn flush_skips_redundant_state() {
+        let mut instance = Instance::new().unwrap();
+        let (program, projection) = program();
+        let attr_info = attr_info();
+
+        let vbo = buffer::LinearBuffer::from_slice(&[[0.0f32; 6]; 3]);
+        let mut buf_info = buffer::Info::new();
+        let slice = buf_info.add(vbo.as_ref(), &attr_info).unwrap();
+        let mut other_buf_info = buffer::Info::new();
+        let other_slice = other_buf_info.add(vbo.as_ref(), &attr_info).unwrap();
+
+        let tex = Tex::new_simple(8, 8, TexFormat::L8).unwrap();
+        let other_tex = Tex::new_simple(8, 8, TexFormat::L8).unwrap();
+        let mut translated = Matrix4::identity();
+        translated.translate(1.0, 0.0, 0.0);
+
+        let mut batch = DrawBatch::new(&program, &attr_info);
+        for _ in 0..3 {
+            batch
+                .draw(buffer::Primitive::Triangles, slice)
+                .uniform(shader::Type::Vertex, projection, Matrix4::identity())
+                .texture(0, &tex);
+        }
+        batch
+            .draw(buffer::Primitive::Triangles, other_slice)
+            .uniform(shader::Type::Vertex, projection, translated)
+            .texture(0, &other_tex)
+            .texture(1, &tex);
+
+        let stats = batch.flush(&mut instance).unwrap();
+        assert_eq!(
+            stats,
+            FlushStats {
+                draws: 4,
+                buffer_binds: 2,
+                uniform_uploads: 2,
+                texture_binds: 3,
+            }
+        );
+        assert!(batch.is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Color blending, i.e. how the output of the [texture combiners](crate::texenv)
+//! is combined with the color already in the framebuffer.
+//!
+//! Blending is configured with [`Instance::set_blend`](crate::Instance::set_blend),
+//! either from one of the common [`BlendPreset`]s or a custom [`Blend`]:
+//!
+//! ```
+//! # let _runner = test_runner::GdbRunner::default();
+//! # use citro3d::blend::{Blend, BlendPreset, Equation, Factor};
+//! # let mut instance = citro3d::Instance::new().unwrap();
+//! instance.set_blend(BlendPreset::Additive);
+//!
+//! // Same as `BlendPreset::Premultiplied`
+//! instance.set_blend(Blend::new(Equation::Add, Factor::One, Factor::OneMinusSrcAlpha));
+//! ```
+
+/// How the weighted source (fragment) and destination (framebuffer) values are
+/// combined.
+#[doc(alias = "GPU_BLENDEQUATION")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Equation {
+    /// `src * src_factor + dst * dst_factor`
+    Add = ctru_sys::GPU_BLEND_ADD,
+    /// `src * src_factor - dst * dst_factor`
+    Subtract = ctru_sys::GPU_BLEND_SUBTRACT,
+    /// `dst * dst_factor - src * src_factor`
+    ReverseSubtract = ctru_sys::GPU_BLEND_REVERSE_SUBTRACT,
+    /// `min(src, dst)`. The factors are ignored.
+    Min = ctru_sys::GPU_BLEND_MIN,
+    /// `max(src, dst)`. The factors are ignored.
+    Max = ctru_sys::GPU_BLEND_MAX,
+}
+
+/// A factor the source or destination value is multiplied with before being
+/// combined by an [`Equation`]. The `Constant*` factors use the color set with
+/// [`Instance::set_blend_color`](crate::Instance::set_blend_color).
+#[doc(alias = "GPU_BLENDFACTOR")]
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Factor {
+    Zero = ctru_sys::GPU_ZERO,
+    One = ctru_sys::GPU_ONE,
+    SrcColor = ctru_sys::GPU_SRC_COLOR,
+    OneMinusSrcColor = ctru_sys::GPU_ONE_MINUS_SRC_COLOR,
+    DstColor = ctru_sys::GPU_DST_COLOR,
+    OneMinusDstColor = ctru_sys::GPU_ONE_MINUS_DST_COLOR,
+    SrcAlpha = ctru_sys::GPU_SRC_ALPHA,
+    OneMinusSrcAlpha = ctru_sys::GPU_ONE_MINUS_SRC_ALPHA,
+    DstAlpha = ctru_sys::GPU_DST_ALPHA,
+    OneMinusDstAlpha = ctru_sys::GPU_ONE_MINUS_DST_ALPHA,
+    ConstantColor = ctru_sys::GPU_CONSTANT_COLOR,
+    OneMinusConstantColor = ctru_sys::GPU_ONE_MINUS_CONSTANT_COLOR,
+    ConstantAlpha = ctru_sys::GPU_CONSTANT_ALPHA,
+    OneMinusConstantAlpha = ctru_sys::GPU_ONE_MINUS_CONSTANT_ALPHA,
+    SrcAlphaSaturate = ctru_sys::GPU_SRC_ALPHA_SATURATE,
+}
+
+/// A full blending configuration, with separate equations and factors for the
+/// color (RGB) and alpha channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blend {
+    pub color_eq: Equation,
+    pub alpha_eq: Equation,
+    pub src_color: Factor,
+    pub dst_color: Factor,
+    pub src_alpha: Factor,
+    pub dst_alpha: Factor,
+}
+
+impl Blend {
+    /// Use the same equation and factors for both color and alpha.
+    pub fn new(eq: Equation, src: Factor, dst: Factor) -> Self {
+        Self {
+            color_eq: eq,
+            alpha_eq: eq,
+            src_color: src,
+            dst_color: dst,
+            src_alpha: src,
+            dst_alpha: dst,
+        }
+    }
+}
+
+impl Default for Blend {
+    /// The blending `citro3d` is initialized with, [`BlendPreset::Alpha`].
+    fn default() -> Self {
+        BlendPreset::Alpha.into()
+    }
+}
+
+/// Commonly used [`Blend`] configurations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BlendPreset {
+    /// No blending, the fragment replaces the framebuffer contents.
+    Opaque,
+    /// Standard alpha blending for straight (non-premultiplied) alpha:
+    /// `src * src_alpha + dst * (1 - src_alpha)`. This is the default.
+    Alpha,
+    /// Alpha blending for colors which are already multiplied by their alpha:
+    /// `src + dst * (1 - src_alpha)`.
+    Premultiplied,
+    /// Add the fragment, weighted by its alpha, to the framebuffer:
+    /// `src * src_alpha + dst`, e.g. for glows and particles. The framebuffer's
+    /// alpha is kept.
+    Additive,
+    /// Multiply the framebuffer by the fragment: `src * dst`, e.g. for tinting
+    /// or baked shadows.
+    Multiply,
+}
+
+impl From<BlendPreset> for Blend {
+    fn from(preset: BlendPreset) -> Self {
+        match preset {
+            BlendPreset::Opaque => Self::new(Equation::Add, Factor::One, Factor::Zero),
+            BlendPreset::Alpha => {
+                Self::new(Equation::Add, Factor::SrcAlpha, Factor::OneMinusSrcAlpha)
+            }
+            BlendPreset::Premultiplied => {
+                Self::new(Equation::Add, Factor::One, Factor::OneMinusSrcAlpha)
+            }
+            BlendPreset::Additive => Self {
+                src_alpha: Factor::Zero,
+                dst_alpha: Factor::One,
+                ..Self::new(Equation::Add, Factor::SrcAlpha, Factor::One)
+            },
+            BlendPreset::Multiply => Self {
+                src_alpha: Factor::DstAlpha,
+                ..Self::new(Equation::Add, Factor::DstColor, Factor::Zero)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        assert_eq!(Blend::default(), BlendPreset::Alpha.into());
+
+        let additive = Blend::from(BlendPreset::Additive);
+        assert_eq!(
+            (additive.src_color, additive.dst_color),
+            (Factor::SrcAlpha, Factor::One)
+        );
+        assert_eq!(
+            (additive.src_alpha, additive.dst_alpha),
+            (Factor::Zero, Factor::One)
+        );
+
+        let multiply = Blend::from(BlendPreset::Multiply);
+        assert_eq!(
+            (multiply.src_alpha, multiply.dst_alpha),
+            (Factor::DstAlpha, Factor::Zero)
+        );
+    }
+}
@@ -2,19 +2,43 @@
 //!
 //! See the [`attrib`] module for details on how to describe the shape and type
 //! of the VBO data.
+//!
+//! # Byte order
+//!
+//! The 3DS is little-endian, and the GPU reads multi-byte vertex attributes
+//! ([`attrib::Format::Short`] and [`attrib::Format::Float`]) and 16-bit indices
+//! in little-endian byte order. Data from Rust values is already in that order,
+//! but raw bytes baked by big-endian tools must be converted first, e.g. with
+//! [`swap_indices_u16`].
 
 use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+
+use ctru::linear::LinearAllocator;
 
-use crate::attrib;
+use crate::error::CodeSource;
+use crate::{attrib, shader, IndexType};
 
 /// Vertex buffer info. This struct is used to describe the shape of the buffer
 /// data to be sent to the GPU for rendering.
-#[derive(Debug, Clone, Copy)]
+///
+/// This type is intentionally not [`Clone`] or [`Copy`]: it holds pointers to
+/// the VBO data registered with [`Info::add`], which is only guaranteed to live
+/// as long as the [`Slice`]s borrowing this [`Info`]. Use [`Info::snapshot`] if
+/// a duplicate is really needed.
+#[derive(Debug)]
 #[doc(alias = "C3D_BufInfo")]
 pub struct Info(pub(crate) citro3d_sys::C3D_BufInfo);
 
 /// A slice of buffer data. This borrows the buffer data and can be thought of
 /// as similar to `&[T]` obtained by slicing a `Vec<T>`.
+///
+/// A slice can't become stale: its [`Info`] stays borrowed (and so can't be
+/// changed) for as long as the slice exists, and
+/// [`Instance::draw_arrays`](crate::Instance::draw_arrays) binds the slice's own
+/// [`Info`] before drawing. Binding a different info with
+/// [`Instance::set_buffer_info`](crate::Instance::set_buffer_info) in between
+/// therefore has no effect on what the slice draws.
 #[derive(Debug, Clone, Copy)]
 pub struct Slice<'buf> {
     index: libc::c_int,
@@ -48,9 +72,175 @@ impl Slice<'_> {
     }
 }
 
+/// Check whether `ptr` can be shared with the GPU, i.e. whether it points into
+/// linear memory (as allocated by [`ctru::linear::LinearAllocator`]) or VRAM.
+///
+/// This is the same check [`Info::add`] performs, so it can be used to catch
+/// [`Error::InvalidMemoryLocation`](crate::Error::InvalidMemoryLocation) ahead of time.
+#[doc(alias = "osConvertVirtToPhys")]
+pub fn is_linear<T>(ptr: *const T) -> bool {
+    unsafe { ctru_sys::osConvertVirtToPhys(ptr.cast()) != 0 }
+}
+
+/// Swap the byte order of each 16-bit index in `bytes`, e.g. to convert index
+/// data baked as big-endian into the little-endian order the GPU reads.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidSize`](crate::Error::InvalidSize) if `bytes` has an
+/// odd length. Nothing is swapped in that case.
+pub fn swap_indices_u16(bytes: &mut [u8]) -> crate::Result<()> {
+    if bytes.len() % 2 != 0 {
+        return Err(crate::Error::InvalidSize);
+    }
+    for index in bytes.chunks_exact_mut(2) {
+        index.swap(0, 1);
+    }
+    Ok(())
+}
+
+/// Join several triangle strips into the indices of a single strip, so they can
+/// be drawn with one [`Primitive::TriangleStrip`] draw call.
+///
+/// The PICA200 has no primitive restart index, so the strips are connected with
+/// degenerate (zero-area) triangles instead, which the GPU discards. Each strip
+/// after the first costs two or three extra indices: one more is added where
+/// needed to keep every strip's triangles facing the same way, for culling.
+/// Strips with fewer than 3 indices have no triangles and are skipped.
+///
+/// The result can be uploaded with [`Info::add_indices_auto`].
+///
+/// ```
+/// # use citro3d::buffer::stitch_strips;
+/// let indices = stitch_strips(&[&[0, 1, 2, 3], &[4, 5, 6]]);
+/// assert_eq!(indices, [0, 1, 2, 3, 3, 4, 4, 5, 6]);
+/// ```
+pub fn stitch_strips(strips: &[&[u32]]) -> Vec<u32> {
+    let mut indices = Vec::new();
+
+    for strip in strips.iter().filter(|strip| strip.len() >= 3) {
+        if let Some(&last) = indices.last() {
+            indices.extend([last, strip[0]]);
+            // Strips alternate winding with each vertex, so each one has to
+            // start at an even position to keep its original facing
+            if indices.len() % 2 != 0 {
+                indices.push(strip[0]);
+            }
+        }
+        indices.extend_from_slice(strip);
+    }
+    indices
+}
+
+/// A buffer of `T` allocated in linear memory, suitable for use as VBO data
+/// with [`Info::add`].
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use citro3d::{attrib, buffer};
+/// let vbo_data = buffer::LinearBuffer::from_slice(&[[0.0f32; 3]; 3]);
+///
+/// let mut attr_info = attrib::Info::new();
+/// let reg0 = attrib::Register::new(0).unwrap();
+/// attr_info.add_loader(reg0, attrib::Format::Float, 3).unwrap();
+///
+/// let mut buf_info = buffer::Info::new();
+/// let slice = buf_info.add(vbo_data.as_ref(), &attr_info).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct LinearBuffer<T: Copy>(Vec<T, LinearAllocator>);
+
+impl<T: Copy> LinearBuffer<T> {
+    /// Copy `data` into a new linearly allocated buffer.
+    pub fn from_slice(data: &[T]) -> Self {
+        let mut buf = Vec::with_capacity_in(data.len(), LinearAllocator);
+        buf.extend_from_slice(data);
+        Self(buf)
+    }
+}
+
+impl<T: Copy> AsRef<[T]> for LinearBuffer<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T: Copy> AsMut<[T]> for LinearBuffer<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+impl<T: Copy> Deref for LinearBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Copy> DerefMut for LinearBuffer<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Copy> From<&[T]> for LinearBuffer<T> {
+    fn from(data: &[T]) -> Self {
+        Self::from_slice(data)
+    }
+}
+
+/// Index data for indexed drawing, allocated in linear memory and stored using
+/// the smallest index type that fits. Obtained from [`Info::add_indices_auto`],
+/// and passed to [`Instance::draw_elements`](crate::Instance::draw_elements).
+#[derive(Debug, Clone)]
+pub struct Indices<'buf> {
+    data: IndexData,
+    buf_info: &'buf Info,
+}
+
+#[derive(Debug, Clone)]
+enum IndexData {
+    U8(Vec<u8, LinearAllocator>),
+    U16(Vec<u16, LinearAllocator>),
+}
+
+impl Indices<'_> {
+    /// Get the number of indices.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match &self.data {
+            IndexData::U8(v) => v.len(),
+            IndexData::U16(v) => v.len(),
+        }
+    }
+
+    /// Return whether or not there are any indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the buffer info these indices are associated with.
+    pub fn info(&self) -> &Info {
+        self.buf_info
+    }
+}
+
+impl<'a> From<&'a Indices<'_>> for IndexType<'a> {
+    fn from(indices: &'a Indices<'_>) -> Self {
+        match &indices.data {
+            IndexData::U8(v) => IndexType::U8(v),
+            IndexData::U16(v) => IndexType::U16(v),
+        }
+    }
+}
+
 /// The geometric primitive to draw (i.e. what shapes the buffer data describes).
 #[repr(u16)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[doc(alias = "GPU_Primitive_t")]
 pub enum Primitive {
     /// Draw triangles (3 vertices per triangle).
@@ -64,6 +254,73 @@ pub enum Primitive {
     GeometryPrim = ctru_sys::GPU_GEOMETRY_PRIM,
 }
 
+impl Primitive {
+    /// Check whether `count` vertices form a whole number of primitives:
+    ///
+    /// * [`Triangles`](Self::Triangles) need a multiple of 3 vertices.
+    /// * [`TriangleStrip`](Self::TriangleStrip) and [`TriangleFan`](Self::TriangleFan)
+    ///   need at least 3 vertices.
+    /// * [`GeometryPrim`](Self::GeometryPrim) depends on the geometry shader, so
+    ///   any count is accepted. When drawing, the count must also be a multiple
+    ///   of the program's [vertices per primitive](crate::shader::Program::geometry_vertices_per_primitive),
+    ///   if known.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidVertexCount`](crate::Error::InvalidVertexCount) if
+    /// the count is not valid for this primitive.
+    pub fn validate_count(self, count: usize) -> crate::Result<()> {
+        let valid = match self {
+            Self::Triangles => count % 3 == 0,
+            Self::TriangleStrip | Self::TriangleFan => count >= 3,
+            Self::GeometryPrim => true,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(crate::Error::InvalidVertexCount {
+                primitive: self,
+                count,
+            })
+        }
+    }
+
+    /// Like [`Primitive::validate_count`], but also checks geometry primitives
+    /// against the vertices per primitive of the program being drawn with.
+    pub(crate) fn validate_draw(
+        self,
+        program: Option<&shader::Program>,
+        count: usize,
+    ) -> crate::Result<()> {
+        self.validate_count(count)?;
+
+        let vertices = program.and_then(shader::Program::geometry_vertices_per_primitive);
+        match vertices {
+            Some(n) if self == Self::GeometryPrim && n != 0 && count % usize::from(n) != 0 => {
+                Err(crate::Error::InvalidVertexCount {
+                    primitive: self,
+                    count,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A buffer registered with an [`Info`], as returned by [`Info::describe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferDesc {
+    /// The offset of the buffer data from the info's base address, in bytes.
+    pub offset: u32,
+    /// The number of bytes between consecutive vertices.
+    pub stride: u16,
+    /// The number of attributes loaded from this buffer.
+    pub attr_count: u8,
+    /// The attribute index loaded from each component of the buffer, one per
+    /// 4 bits, starting with the lowest bits.
+    pub permutation: u64,
+}
+
 impl Default for Info {
     #[doc(alias = "BufInfo_Init")]
     fn default() -> Self {
@@ -125,6 +382,152 @@ impl Info {
         }
     }
 
+    /// Describe each buffer registered with this info, in registration order,
+    /// e.g. to check the strides when a mesh draws scrambled.
+    pub fn describe(&self) -> Vec<BufferDesc> {
+        let count = self.0.bufCount.clamp(0, self.0.buffers.len() as i32) as usize;
+        self.0.buffers[..count]
+            .iter()
+            .map(|buf| BufferDesc {
+                offset: buf.offset,
+                // See `BufInfo_Add`: the high flags hold the last 4 attribute
+                // indices, then the stride and the attribute count
+                stride: ((buf.flags[1] >> 16) & 0xFFF) as u16,
+                attr_count: (buf.flags[1] >> 28) as u8,
+                permutation: u64::from(buf.flags[0]) | (u64::from(buf.flags[1] & 0xFFFF) << 32),
+            })
+            .collect()
+    }
+
+    /// Create a copy of this buffer info, including pointers to all registered VBO data.
+    ///
+    /// # Safety
+    ///
+    /// The returned [`Info`] is not tied to the lifetime of the VBO data registered
+    /// with `self`. The caller must ensure that data outlives every use of the
+    /// snapshot, including any draw calls the GPU has not finished yet.
+    pub unsafe fn snapshot(&self) -> Self {
+        Self(self.0)
+    }
+
+    /// Copy `indices` into a new linearly allocated index buffer for use with this
+    /// buffer info. The indices are narrowed to [`u8`] if they all fit, and to
+    /// [`u16`] otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IndexOutOfRange`](crate::Error::IndexOutOfRange) if any
+    /// index is larger than [`u16::MAX`], which is the largest index the PICA200
+    /// supports.
+    ///
+    /// `indices` are Rust values, so they are stored in the little-endian byte
+    /// order the GPU expects. See the [module documentation](self#byte-order)
+    /// when loading raw index bytes instead.
+    pub fn add_indices_auto(&self, indices: &[u32]) -> crate::Result<Indices<'_>> {
+        let max = indices.iter().copied().max().unwrap_or(0);
+
+        let data = if max <= u8::MAX.into() {
+            let mut v = Vec::with_capacity_in(indices.len(), LinearAllocator);
+            v.extend(indices.iter().map(|&i| i as u8));
+            IndexData::U8(v)
+        } else if max <= u16::MAX.into() {
+            let mut v = Vec::with_capacity_in(indices.len(), LinearAllocator);
+            v.extend(indices.iter().map(|&i| i as u16));
+            IndexData::U16(v)
+        } else {
+            return Err(crate::Error::IndexOutOfRange(max));
+        };
+
+        Ok(Indices {
+            data,
+            buf_info: self,
+        })
+    }
+
+    /// Register several VBOs which each store some of the attributes of the same
+    /// vertices, i.e. a "structure of arrays" layout such as one buffer of
+    /// positions and another of colors. Each buffer's data is described by its
+    /// own [`attrib::Info`], and holds the same number of vertices.
+    ///
+    /// The attribute info used when drawing must have the loaders of every
+    /// buffer's info, registered in the same order as the buffers. For example,
+    /// with a `[FVec3]` position buffer using register 0 and a `[[f32; 4]]` color
+    /// buffer using register 1, draw with an attribute info which has a
+    /// 3 float loader for register 0 followed by a 4 float loader for register 1.
+    ///
+    /// Fixed attributes in the buffers' infos are skipped, since they aren't
+    /// loaded from a buffer. Add them to the attribute info used when drawing
+    /// after all the loaders.
+    ///
+    /// The returned [`Slice`] covers all the buffers at once.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if the buffers don't all
+    ///   contain the same whole number of vertices
+    /// * Any error from [`Info::add`], e.g. if a buffer is not in linear memory
+    ///   or too many buffers are registered
+    #[doc(alias = "BufInfo_Add")]
+    pub fn add_multi<'this, 'vbo, 'idx>(
+        &'this mut self,
+        buffers: &[(&'vbo [u8], &attrib::Info)],
+    ) -> crate::Result<Slice<'idx>>
+    where
+        'this: 'idx,
+        'vbo: 'idx,
+    {
+        let mut vertex_count = None;
+        for (data, attrib_info) in buffers {
+            let stride = attrib_info.vertex_stride() as usize;
+            if stride == 0 || data.len() % stride != 0 {
+                return Err(crate::Error::InvalidSize);
+            }
+            if *vertex_count.get_or_insert(data.len() / stride) != data.len() / stride {
+                return Err(crate::Error::InvalidSize);
+            }
+        }
+
+        let mut first_index = None;
+        let mut attr_offset = 0;
+        for (data, attrib_info) in buffers {
+            let attr_count = attrib_info.loader_count();
+            // Each buffer provides the next `attr_count` attributes, in order
+            let permutation = (0..attr_count as u64)
+                .map(|i| (attr_offset + i) << (i * 4))
+                .sum();
+            attr_offset += attr_count as u64;
+
+            // SAFETY: the pointer to &mut self.0 is only used to access values
+            // in the BufInfo, and the data lifetime is tied to the returned slice
+            let res = unsafe {
+                citro3d_sys::BufInfo_Add(
+                    &mut self.0,
+                    data.as_ptr().cast(),
+                    attrib_info.vertex_stride() as isize,
+                    attr_count as _,
+                    permutation,
+                )
+            };
+            match res {
+                -2 => {
+                    return Err(crate::Error::InvalidMemoryLocation {
+                        address: Some(data.as_ptr() as usize),
+                    })
+                }
+                ..=-1 => return Err(crate::Error::from_c3d_code(CodeSource::BufInfoAdd, res)),
+                _ => {
+                    first_index.get_or_insert(res);
+                }
+            }
+        }
+
+        Ok(Slice {
+            index: first_index.unwrap_or(0),
+            size: vertex_count.unwrap_or(0).try_into()?,
+            buf_info: self,
+        })
+    }
+
     /// Add vbo bytes directly
     ///
     /// This is the same as [`Info::add`] except it requires manually specifying the
@@ -158,11 +561,11 @@ impl Info {
             )
         };
 
-        // Error codes from <https://github.com/devkitPro/citro3d/blob/master/source/buffers.c#L11>
         match res {
-            ..=-3 => Err(crate::Error::System(res)),
-            -2 => Err(crate::Error::InvalidMemoryLocation),
-            -1 => Err(crate::Error::TooManyBuffers),
+            -2 => Err(crate::Error::InvalidMemoryLocation {
+                address: Some(vbo_data.as_ptr() as usize),
+            }),
+            ..=-1 => Err(crate::Error::from_c3d_code(CodeSource::BufInfoAdd, res)),
             _ => Ok(Slice {
                 index: res,
                 size: vbo_data.len().try_into()?,
@@ -171,3 +574,131 @@ impl Info {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_vertex_counts() {
+        assert!(Primitive::Triangles.validate_count(6).is_ok());
+        assert!(matches!(
+            Primitive::Triangles.validate_count(4),
+            Err(crate::Error::InvalidVertexCount {
+                primitive: Primitive::Triangles,
+                count: 4
+            })
+        ));
+        assert!(Primitive::TriangleStrip.validate_count(4).is_ok());
+        assert!(Primitive::TriangleFan.validate_count(2).is_err());
+        assert!(Primitive::GeometryPrim.validate_count(1).is_ok());
+    }
+
+    #[test]
+    fn add_multi_interleaves_attributes() {
+        let positions = LinearBuffer::from_slice(&[[0.0f32; 3]; 3]);
+        let colors = LinearBuffer::from_slice(&[[1.0f32; 4]; 3]);
+
+        let mut position_info = attrib::Info::new();
+        position_info
+            .add_loader(attrib::Register::new(0).unwrap(), attrib::Format::Float, 3)
+            .unwrap();
+        let mut color_info = attrib::Info::new();
+        color_info
+            .add_loader(attrib::Register::new(1).unwrap(), attrib::Format::Float, 4)
+            .unwrap();
+
+        let mut info = Info::new();
+        let slice = info
+            .add_multi(&[
+                (bytemuck::cast_slice(&positions[..]), &position_info),
+                (bytemuck::cast_slice(&colors[..]), &color_info),
+            ])
+            .unwrap();
+        assert_eq!(slice.len(), 3);
+
+        let [position_buf, color_buf] = [info.0.buffers[0], info.0.buffers[1]];
+        // The color buffer provides attribute 1, with a stride of 16 bytes
+        assert_eq!(position_buf.flags[0], 0x0);
+        assert_eq!(color_buf.flags[0], 0x1);
+        assert_eq!((position_buf.flags[1] >> 16) & 0xFFF, 12);
+        assert_eq!((color_buf.flags[1] >> 16) & 0xFFF, 16);
+
+        let desc = info.describe();
+        assert_eq!(desc.len(), 2);
+        assert_eq!((desc[0].stride, desc[0].attr_count), (12, 1));
+        assert_eq!((desc[1].stride, desc[1].attr_count), (16, 1));
+        assert_eq!(desc[1].permutation, 0x1);
+
+        // Fixed attributes aren't part of the buffer
+        let mut fixed_color_info = attrib::Info::new();
+        fixed_color_info
+            .add_fixed(attrib::Register::new(2).unwrap())
+            .unwrap();
+        fixed_color_info
+            .add_loader(attrib::Register::new(1).unwrap(), attrib::Format::Float, 4)
+            .unwrap();
+        let mut info = Info::new();
+        info.add_multi(&[
+            (bytemuck::cast_slice(&positions[..]), &position_info),
+            (bytemuck::cast_slice(&colors[..]), &fixed_color_info),
+        ])
+        .unwrap();
+        let desc = info.describe();
+        assert_eq!((desc[1].stride, desc[1].attr_count), (16, 1));
+        assert_eq!(desc[1].permutation, 0x1);
+
+        let too_short = LinearBuffer::from_slice(&[[1.0f32; 4]; 2]);
+        assert!(matches!(
+            info.add_multi(&[
+                (bytemuck::cast_slice(&positions[..]), &position_info),
+                (bytemuck::cast_slice(&too_short[..]), &color_info),
+            ]),
+            Err(crate::Error::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn swap_index_bytes() {
+        let mut bytes = [0x00, 0x01, 0x12, 0x34];
+        swap_indices_u16(&mut bytes).unwrap();
+        assert_eq!(bytes, [0x01, 0x00, 0x34, 0x12]);
+        assert_eq!(u16::from_le_bytes([bytes[0], bytes[1]]), 1);
+
+        let mut odd = [0x00, 0x01, 0x02];
+        assert!(matches!(
+            swap_indices_u16(&mut odd),
+            Err(crate::Error::InvalidSize)
+        ));
+        assert_eq!(odd, [0x00, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn stitched_strips_keep_winding() {
+        assert_eq!(
+            stitch_strips(&[&[0, 1, 2], &[3, 4, 5, 6], &[], &[7, 8, 9]]),
+            [0, 1, 2, 2, 3, 3, 3, 4, 5, 6, 6, 7, 7, 8, 9]
+        );
+        assert_eq!(stitch_strips(&[&[0, 1], &[2, 3, 4]]), [2, 3, 4]);
+        assert!(stitch_strips(&[]).is_empty());
+    }
+
+    #[test]
+    fn indices_auto_picks_smallest_type() {
+        let info = Info::new();
+
+        let small = info.add_indices_auto(&[0, 1, 255]).unwrap();
+        assert!(matches!(IndexType::from(&small), IndexType::U8(_)));
+
+        let large = info.add_indices_auto(&[0, 256, 2]).unwrap();
+        assert!(matches!(
+            IndexType::from(&large),
+            IndexType::U16(&[0, 256, 2])
+        ));
+
+        assert!(matches!(
+            info.add_indices_auto(&[0, 65536]),
+            Err(crate::Error::IndexOutOfRange(65536))
+        ));
+    }
+}
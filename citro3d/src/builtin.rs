@@ -0,0 +1,76 @@
+//! Shared setup for the helpers which draw with a built-in shader, like
+//! [`sprite`](crate::sprite) and [`debug_draw`](crate::debug_draw).
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::buffer::{self, LinearBuffer};
+use crate::math::Matrix4;
+use crate::{attrib, shader, uniform, Error, Instance, Result};
+
+/// A built-in shader program with a `projection` uniform, along with the
+/// vertex data drawn with it this frame.
+pub(crate) struct BuiltinShader<V: Copy> {
+    // The program owns its library, since it may stay bound after this is
+    // dropped
+    program: Pin<Arc<shader::Program>>,
+    projection: uniform::Index,
+    attr_info: attrib::Info,
+    vertices: Vec<LinearBuffer<V>>,
+}
+
+impl<V: Copy> BuiltinShader<V> {
+    /// Load the first entrypoint of `bytes` as a vertex shader, reading
+    /// vertices of type `V` laid out as described by `loaders`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FailedToInitialize`] if the shader could not be loaded.
+    pub fn load(bytes: &[u8], loaders: &[(attrib::Register, attrib::Format, u8)]) -> Result<Self> {
+        let library =
+            shader::Library::from_bytes_copied(bytes).map_err(|_| Error::FailedToInitialize)?;
+        let program = shader::Program::from_library(Arc::new(library), 0)
+            .map_err(|_| Error::FailedToInitialize)?;
+        let projection = program.get_uniform("projection")?;
+        let (attr_info, _) = attrib::Info::from_loaders(loaders)?;
+
+        Ok(Self {
+            program: Arc::pin(program),
+            projection,
+            attr_info,
+            vertices: Vec::new(),
+        })
+    }
+
+    /// Free the vertex data of previous draws.
+    pub fn next_frame(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Draw `vertices` as triangles, after binding the program, attributes and
+    /// `projection` and calling `configure` to set up the rest of the state.
+    /// All of it is restored afterwards, and the vertex data is kept until
+    /// [`BuiltinShader::next_frame`].
+    pub fn draw(
+        &mut self,
+        instance: &mut Instance,
+        projection: &Matrix4,
+        vertices: &[V],
+        configure: impl FnOnce(&mut Instance),
+    ) -> Result<()> {
+        let vbo = LinearBuffer::from_slice(vertices);
+
+        let mut state = instance.save_state();
+        state.bind_program(self.program.clone());
+        state.set_attr_info(&self.attr_info);
+        state.bind_vertex_uniform(self.projection, projection)?;
+        configure(&mut state);
+
+        let mut buf_info = buffer::Info::new();
+        let slice = buf_info.add(vbo.as_ref(), &self.attr_info)?;
+        state.draw_arrays(buffer::Primitive::Triangles, slice)?;
+
+        self.vertices.push(vbo);
+        Ok(())
+    }
+}
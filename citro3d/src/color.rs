@@ -0,0 +1,122 @@
+//! A color type shared by the lighting, texture combiner and render target APIs.
+//!
+//! [`Color`] components are stored as `f32`s in the range `0.0..=1.0`, and can
+//! be converted to and from 8-bit components or the packed `u32` formats the
+//! GPU uses.
+//!
+//! Note that the GPU does no gamma correction: colors are blended and lit as-is,
+//! and written to the framebuffer without conversion. Colors picked in sRGB
+//! (e.g. from an image editor) can be used directly, but lighting calculations
+//! on them will not be physically accurate.
+
+/// An RGBA color, with components in the range `0.0..=1.0`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    /// The alpha (opacity) component. This is ignored by APIs which only use
+    /// RGB, like [`Material`](crate::material::Material).
+    pub a: f32,
+}
+
+impl Color {
+    /// Create an opaque color from RGB components in the range `0.0..=1.0`.
+    pub fn new(r: f32, g: f32, b: f32) -> Self {
+        Self::from_rgba_f32(r, g, b, 1.0)
+    }
+
+    /// Create an opaque color from RGB components in the range `0.0..=1.0`.
+    /// This is the same as [`Color::new`].
+    pub fn from_rgb_f32(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b)
+    }
+
+    /// Create a color from RGBA components in the range `0.0..=1.0`.
+    pub fn from_rgba_f32(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Create an opaque color from 8-bit RGB components in the range `0..=255`.
+    pub fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::from_rgba8(r, g, b, 0xFF)
+    }
+
+    /// Create a color from 8-bit RGBA components in the range `0..=255`.
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let f = |c: u8| f32::from(c) / 255.0;
+        Self::from_rgba_f32(f(r), f(g), f(b), f(a))
+    }
+
+    /// Create a color from a packed `0xRRGGBBAA` value, as returned by
+    /// [`Color::to_packed`].
+    pub fn from_packed(rgba: u32) -> Self {
+        let [r, g, b, a] = rgba.to_be_bytes();
+        Self::from_rgba8(r, g, b, a)
+    }
+
+    /// Convert to 8-bit RGBA components, clamping each component to `0.0..=1.0`.
+    pub fn to_rgba8(self) -> [u8; 4] {
+        let c = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [c(self.r), c(self.g), c(self.b), c(self.a)]
+    }
+
+    /// Pack into a `0xRRGGBBAA` value, as used for clearing a
+    /// [`render::Target`](crate::render::Target).
+    pub fn to_packed(self) -> u32 {
+        u32::from_be_bytes(self.to_rgba8())
+    }
+
+    /// Pack into a `0xAABBGGRR` value, as used for texture combiner and light
+    /// environment constant colors.
+    pub fn to_packed_abgr(self) -> u32 {
+        u32::from_le_bytes(self.to_rgba8())
+    }
+
+    /// Split into BGR ordered parts
+    ///
+    /// # Reason for existence
+    /// The C version of [`Material`](crate::material::Material) expects colours in BGR order (don't ask why it is beyond my comprehension)
+    /// so we have to reorder when converting
+    pub fn to_parts_bgr(self) -> [f32; 3] {
+        [self.b, self.g, self.r]
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::from_rgba_f32(r, g, b, a)
+    }
+}
+
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self::from_rgba8(r, g, b, a)
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> Self {
+        color.to_rgba8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing() {
+        let color = Color::from_rgba8(0x12, 0x34, 0x56, 0x78);
+        assert_eq!(color.to_packed(), 0x1234_5678);
+        assert_eq!(color.to_packed_abgr(), 0x7856_3412);
+        assert_eq!(Color::from_packed(0x1234_5678), color);
+        assert_eq!(Color::new(2.0, -1.0, 0.5).to_rgba8(), [255, 0, 128, 255]);
+    }
+}
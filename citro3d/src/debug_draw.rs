@@ -0,0 +1,204 @@
+//! Simple line and point drawing for debugging, e.g. to visualize collision
+//! shapes or bounding volumes.
+//!
+//! The PICA200 can only draw triangles, so [`DebugDraw`] builds thin quads for
+//! each line and small square quads for each point, and draws them in a solid
+//! color with a built-in shader. Lines are expanded in the XY plane (or along
+//! the X axis for lines parallel to Z), so they are best viewed head on.
+
+use crate::builtin::BuiltinShader;
+use crate::color::Color;
+use crate::macros::include_shader;
+use crate::math::{FVec3, Matrix4};
+use crate::texenv::{CombineFunc, Mode, Source, Stage};
+use crate::{attrib, Instance, Result};
+
+static SHADER_BYTES: &[u8] = include_shader!("debug_draw/shader.pica");
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 4],
+}
+
+/// A helper for drawing lines and points in a solid color.
+///
+/// The vertex data for each draw is kept alive until [`DebugDraw::next_frame`]
+/// is called, since the GPU reads it while rendering the frame.
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use citro3d::color::Color;
+/// # use citro3d::debug_draw::DebugDraw;
+/// # use citro3d::math::{FVec3, Matrix4};
+/// # let mut instance = citro3d::Instance::new().unwrap();
+/// let mut debug = DebugDraw::new().unwrap();
+/// let projection = Matrix4::identity();
+///
+/// instance
+///     .render_frame_with(|instance| {
+///         debug.next_frame();
+///         debug
+///             .draw_lines(
+///                 instance,
+///                 &projection,
+///                 &[FVec3::new(0.0, 0.0, 0.0), FVec3::new(1.0, 1.0, 0.0)],
+///                 Color::new(1.0, 0.0, 0.0),
+///             )
+///             .unwrap();
+///     })
+///     .unwrap();
+/// ```
+pub struct DebugDraw {
+    shader: BuiltinShader<Vertex>,
+    line_width: f32,
+    point_size: f32,
+}
+
+impl DebugDraw {
+    /// Load the built-in shader and create a new debug drawer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FailedToInitialize`](crate::Error::FailedToInitialize)
+    /// if the shader could not be loaded.
+    pub fn new() -> Result<Self> {
+        let shader = BuiltinShader::load(
+            SHADER_BYTES,
+            &[
+                (attrib::Register::new(0)?, attrib::Format::Float, 3),
+                (attrib::Register::new(1)?, attrib::Format::Float, 4),
+            ],
+        )?;
+
+        Ok(Self {
+            shader,
+            line_width: 0.02,
+            point_size: 0.05,
+        })
+    }
+
+    /// Set the width of lines drawn by [`DebugDraw::draw_lines`], in the same
+    /// units as the line positions. Defaults to `0.02`.
+    pub fn set_line_width(&mut self, width: f32) {
+        self.line_width = width;
+    }
+
+    /// Set the size of points drawn by [`DebugDraw::draw_points`], in the same
+    /// units as the point positions. Defaults to `0.05`.
+    pub fn set_point_size(&mut self, size: f32) {
+        self.point_size = size;
+    }
+
+    /// Free the vertex data of previous draws. Call this at the start of each
+    /// frame, once the GPU has finished rendering the previous one.
+    pub fn next_frame(&mut self) {
+        self.shader.next_frame();
+    }
+
+    /// Draw lines between each pair of `points`, like `GL_LINES`. A trailing
+    /// unpaired point is ignored.
+    ///
+    /// The previously bound shader program, vertex attributes and first texture
+    /// combiner stage are restored afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the vertex data could not be sent to the GPU.
+    pub fn draw_lines(
+        &mut self,
+        instance: &mut Instance,
+        projection: &Matrix4,
+        points: &[FVec3],
+        color: Color,
+    ) -> Result<()> {
+        let half_width = self.line_width / 2.0;
+        let vertices: Vec<_> = points
+            .chunks_exact(2)
+            .flat_map(|line| {
+                let (a, b) = (line[0], line[1]);
+                let mut perp = (b - a).cross(FVec3::new(0.0, 0.0, 1.0));
+                if perp.magnitude() < f32::EPSILON {
+                    perp = FVec3::new(1.0, 0.0, 0.0);
+                }
+                let offset = perp.normalize() * half_width;
+
+                [
+                    a - offset,
+                    a + offset,
+                    b + offset,
+                    a - offset,
+                    b + offset,
+                    b - offset,
+                ]
+            })
+            .collect();
+
+        self.draw(instance, projection, &vertices, color)
+    }
+
+    /// Draw a square for each of `points`.
+    ///
+    /// The previously bound shader program, vertex attributes and first texture
+    /// combiner stage are restored afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the vertex data could not be sent to the GPU.
+    pub fn draw_points(
+        &mut self,
+        instance: &mut Instance,
+        projection: &Matrix4,
+        points: &[FVec3],
+        color: Color,
+    ) -> Result<()> {
+        let half_size = self.point_size / 2.0;
+        let vertices: Vec<_> = points
+            .iter()
+            .flat_map(|&p| {
+                let corner = |x: f32, y: f32| p + FVec3::new(x * half_size, y * half_size, 0.0);
+                let (bl, br, tr, tl) = (
+                    corner(-1.0, -1.0),
+                    corner(1.0, -1.0),
+                    corner(1.0, 1.0),
+                    corner(-1.0, 1.0),
+                );
+                [bl, br, tr, bl, tr, tl]
+            })
+            .collect();
+
+        self.draw(instance, projection, &vertices, color)
+    }
+
+    fn draw(
+        &mut self,
+        instance: &mut Instance,
+        projection: &Matrix4,
+        positions: &[FVec3],
+        color: Color,
+    ) -> Result<()> {
+        if positions.is_empty() {
+            return Ok(());
+        }
+
+        let color = [color.r, color.g, color.b, color.a];
+        let vertices: Vec<_> = positions
+            .iter()
+            .map(|p| Vertex {
+                pos: [p.x(), p.y(), p.z()],
+                color,
+            })
+            .collect();
+
+        self.shader
+            .draw(instance, projection, &vertices, |instance| {
+                instance
+                    .texenv(Stage::new(0).unwrap())
+                    .src(Mode::BOTH, Source::PrimaryColor, None, None)
+                    .func(Mode::BOTH, CombineFunc::Replace);
+            })
+    }
+}
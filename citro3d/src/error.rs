@@ -7,9 +7,6 @@ use std::sync::TryLockError;
 /// The common result type returned by `citro3d` functions.
 pub type Result<T> = std::result::Result<T, Error>;
 
-// TODO probably want a similar type to ctru::Result to make it easier to convert
-// nonzero result codes to errors.
-
 /// The common error type that may be returned by `citro3d` functions.
 #[non_exhaustive]
 #[derive(Debug, thiserror::Error)]
@@ -23,6 +20,20 @@ pub enum Error {
     /// A size parameter was specified that cannot be converted to the proper type.
     #[error("specified size parameter is invalid")]
     InvalidSize,
+    /// The GPU command buffer doesn't have enough space left for a draw call,
+    /// so it was skipped.
+    #[error("GPU command buffer is full, try a larger size with `Instance::with_cmdbuf_size`")]
+    CommandBufferFull,
+    /// A frame could not be started because another one is still being
+    /// rendered, e.g. when rendering a frame from within another.
+    #[error("a frame is already being rendered")]
+    FrameInProgress,
+    /// There is not enough free VRAM for the requested allocation.
+    #[error("not enough free VRAM for allocation")]
+    VramExhausted,
+    /// The texture format is not valid for the requested operation.
+    #[error("texture format is invalid for the requested operation")]
+    InvalidFormat,
     /// Failed to select the given render target for drawing to.
     #[error("render target is invalid")]
     InvalidRenderTarget,
@@ -37,15 +48,118 @@ pub enum Error {
     #[error("too many vertex buffer objects registered (max of 12)")]
     TooManyBuffers,
     /// The given memory could not be converted to a physical address for sharing
-    /// with the GPU. Data should be allocated with [`ctru::linear`].
-    #[error("invalid memory location, address should be allocated with `ctru::linear`")]
-    InvalidMemoryLocation,
+    /// with the GPU. Data should be allocated with [`ctru::linear`]; use
+    /// [`buffer::is_linear`](crate::buffer::is_linear) to check ahead of time.
+    #[error(
+        "invalid memory location{}, data should be allocated with `ctru::linear::LinearAllocator`",
+        fmt_address(.address)
+    )]
+    InvalidMemoryLocation {
+        /// The offending address, if known.
+        address: Option<usize>,
+    },
     /// The given name was not valid for the requested purpose.
     #[error("provided name is invalid")]
     InvalidName,
+    /// A per-light LUT slot was used where only the LUT slots shared by all
+    /// lights are supported.
+    #[error("LUT slot {0:?} belongs to individual lights, use `LightEnv::connect_light_lut`")]
+    #[cfg(not(feature = "mock"))]
+    PerLightLut(crate::light::LightLutId),
+    /// Serialized data had an unknown header or version.
+    #[error("serialized data has an unknown format or version")]
+    InvalidData,
     /// The requested resource could not be found.
     #[error("requested resource could not be found")]
     NotFound,
+    /// An index was too large to be used for indexed drawing (max 65535 supported).
+    #[error("index {0} is out of range for indexed drawing (max of 65535)")]
+    IndexOutOfRange(u32),
+    /// A light index was out of range (max 7 supported).
+    #[error("light index {0} is out of range (max of 7)")]
+    InvalidLightIndex(usize),
+    /// A texture unit was out of range (max 3 supported).
+    #[error("texture unit {0} is out of range (max of 3)")]
+    InvalidTextureUnit(i32),
+    /// All of the hardware lights in a light environment are already in use
+    /// (max 8 supported).
+    #[error("all light slots are in use (max of 8)")]
+    TooManyLights,
+    /// The number of vertices is not valid for the primitive being drawn.
+    #[error("{count} vertices cannot be drawn as {primitive:?}")]
+    #[cfg(not(feature = "mock"))]
+    InvalidVertexCount {
+        /// The primitive being drawn.
+        primitive: crate::buffer::Primitive,
+        /// The number of vertices.
+        count: usize,
+    },
+    /// The vertex attribute registers do not match the input registers used by
+    /// the shader program.
+    #[error("vertex attributes do not match shader inputs (mismatched register v{0})")]
+    AttributeMismatch(u8),
+    /// A texture is bound for sampling while it is also the texture of the
+    /// selected render target, which the GPU can't do in the same draw.
+    #[error("texture is bound for sampling while it is drawn to")]
+    SamplingRenderTarget,
+    /// The register was not added as a fixed attribute to the attribute info
+    /// in use.
+    #[error("register v{0} is not a fixed vertex attribute, use `attrib::Info::add_fixed`")]
+    NotFixedAttribute(u8),
+    /// A uniform upload would write past the end of the float uniform registers
+    /// (96 `vec4` registers).
+    #[error(
+        "uniform upload of {len} registers starting at index {start} overflows the float uniform registers (max of 96)"
+    )]
+    UniformOverflow {
+        /// The first register that would have been written.
+        start: u8,
+        /// The number of registers that would have been written.
+        len: usize,
+    },
+    /// A uniform was bound to an index of a different register file, e.g. a
+    /// matrix to a `.bool` uniform.
+    #[error("cannot bind {uniform:?} uniform to index of kind {index:?}")]
+    UniformKindMismatch {
+        /// The kind of uniform that was being bound.
+        uniform: crate::uniform::Kind,
+        /// The kind of the index, or `None` if it is not a uniform register.
+        index: Option<crate::uniform::Kind>,
+    },
+}
+
+/// A `citro3d` function that reports failure with an integer error code.
+///
+/// `citro3d` error codes are specific to the function that returned them (e.g. `-1`
+/// means "too many buffers" for `BufInfo_Add` but "too many attributes" for
+/// `AttrInfo_AddLoader`), so the function is needed to interpret a code.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeSource {
+    /// Error codes from <https://github.com/devkitPro/citro3d/blob/master/source/buffers.c#L11>
+    #[doc(alias = "BufInfo_Add")]
+    BufInfoAdd,
+    /// Error codes from <https://github.com/devkitPro/citro3d/blob/master/source/attribs.c>
+    #[doc(alias = "AttrInfo_AddLoader")]
+    AttrInfoAddLoader,
+}
+
+impl Error {
+    /// Convert a negative error code returned by a `citro3d` function into a
+    /// specific [`Error`] variant, falling back to [`Error::System`] for codes
+    /// which are not documented for that function.
+    pub fn from_c3d_code(source: CodeSource, code: libc::c_int) -> Self {
+        match (source, code) {
+            (CodeSource::BufInfoAdd, -1) => Self::TooManyBuffers,
+            (CodeSource::BufInfoAdd, -2) => Self::InvalidMemoryLocation { address: None },
+            (CodeSource::AttrInfoAddLoader, -1) => Self::TooManyAttributes,
+            _ => Self::System(code),
+        }
+    }
+}
+
+fn fmt_address(address: &Option<usize>) -> String {
+    address.map_or_else(String::new, |addr| format!(" {addr:#010x}"))
 }
 
 impl From<TryFromIntError> for Error {
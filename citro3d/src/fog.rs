@@ -0,0 +1,154 @@
+//! Depth-based fog, which blends fragments towards a fog color.
+//!
+//! The PICA200 applies fog in a fixed-function stage after the last
+//! [texture combiner](crate::texenv) stage, so no combiner setup is needed for
+//! it: [`Instance::set_fog`](crate::Instance::set_fog) enables it with a
+//! [`FogLut`], which maps each fragment's depth to a fog factor, and a fog
+//! color. The final color is `combiner_output * factor + fog_color * (1 - factor)`,
+//! so a factor of `1.0` means no fog at all. The fog factor isn't available to
+//! the combiners, and the alpha of the combiner output is left as-is.
+//!
+//! For fog which doesn't depend only on depth, e.g. height fog, the factor can
+//! instead be calculated in the vertex shader and applied in a combiner stage
+//! with [`apply_in_combiner`].
+//!
+//! # Example
+//!
+//! ```
+//! # let _runner = test_runner::GdbRunner::default();
+//! # use citro3d::color::Color;
+//! # use citro3d::fog::FogLut;
+//! # use citro3d::math::ClipPlanes;
+//! # let mut instance = citro3d::Instance::new().unwrap();
+//! let lut = FogLut::exponential(
+//!     0.05,
+//!     2.0,
+//!     ClipPlanes {
+//!         near: 0.1,
+//!         far: 100.0,
+//!     },
+//! );
+//! instance.set_fog(lut, Color::new(0.6, 0.7, 0.8), false);
+//! ```
+
+use crate::color::Color;
+use crate::math::ClipPlanes;
+use crate::texenv::{CombineFunc, Mode, Source, TexEnv};
+
+/// The number of entries in a [`FogLut`].
+const LUT_SIZE: usize = 128;
+
+/// A lookup table from fragment depth to fog factor.
+#[doc(alias = "C3D_FogLut")]
+#[derive(Clone, Copy)]
+pub struct FogLut(citro3d_sys::C3D_FogLut);
+
+impl FogLut {
+    /// Build a table by sampling `f` at evenly spaced depths in `0.0..=1.0`.
+    /// `f` returns the fog factor for a depth, from `0.0` (only the fog color)
+    /// to `1.0` (no fog).
+    ///
+    /// The depth is the value written to the depth buffer, as set up with
+    /// [`Instance::set_depth_map`](crate::Instance::set_depth_map). For a
+    /// table based on distances from the camera, see [`FogLut::exponential`].
+    #[doc(alias = "FogLut_FromArray")]
+    pub fn from_fn(f: impl Fn(f32) -> f32) -> Self {
+        let samples: [f32; LUT_SIZE + 1] =
+            std::array::from_fn(|i| f(i as f32 / LUT_SIZE as f32).clamp(0.0, 1.0));
+
+        // The first half holds the values, the second half the difference to
+        // the next value, which the GPU uses to interpolate between entries
+        let mut data = [0.0; LUT_SIZE * 2];
+        let (values, diffs) = data.split_at_mut(LUT_SIZE);
+        for ((value, diff), pair) in values.iter_mut().zip(diffs).zip(samples.windows(2)) {
+            *value = pair[0];
+            *diff = pair[1] - pair[0];
+        }
+
+        let mut lut = citro3d_sys::C3D_FogLut {
+            data: [0; LUT_SIZE],
+        };
+        unsafe { citro3d_sys::FogLut_FromArray(&mut lut, data.as_ptr()) };
+        Self(lut)
+    }
+
+    /// Build a table for exponential fog, with a factor of
+    /// `exp(-(density * distance) ^ gradient)` at each distance from the
+    /// camera. `clip_planes` must match the perspective projection used for
+    /// drawing, so depths can be converted back into distances.
+    #[doc(alias = "FogLut_Exp")]
+    pub fn exponential(density: f32, gradient: f32, clip_planes: ClipPlanes) -> Self {
+        let mut lut = citro3d_sys::C3D_FogLut {
+            data: [0; LUT_SIZE],
+        };
+        unsafe {
+            citro3d_sys::FogLut_Exp(
+                &mut lut,
+                density,
+                gradient,
+                clip_planes.near,
+                clip_planes.far,
+            );
+        }
+        Self(lut)
+    }
+
+    pub(crate) fn as_raw_mut(&mut self) -> *mut citro3d_sys::C3D_FogLut {
+        &mut self.0
+    }
+}
+
+impl std::fmt::Debug for FogLut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FogLut").finish_non_exhaustive()
+    }
+}
+
+/// Configure `env` to blend the output of the previous stage towards `color`,
+/// using the alpha of `factor` as the fog factor (`1.0` for no fog), and keep
+/// the previous stage's alpha. This is usually the last stage, with the factor
+/// output by the vertex shader in the alpha of [`Source::PrimaryColor`].
+///
+/// This is an alternative to the hardware fog set up with
+/// [`Instance::set_fog`](crate::Instance::set_fog), which can only use the
+/// fragment's depth.
+///
+/// # Panics
+///
+/// Panics if `env` is the first stage, since there is no previous stage to
+/// read from.
+pub fn apply_in_combiner(env: &mut TexEnv, color: Color, factor: Source) {
+    env.src(
+        Mode::RGB,
+        Source::Previous,
+        Some(Source::Constant),
+        Some(factor),
+    )
+    .func(Mode::RGB, CombineFunc::Interpolate)
+    .src(Mode::ALPHA, Source::Previous, None, None)
+    .func(Mode::ALPHA, CombineFunc::Replace)
+    .color(color);
+
+    unsafe {
+        citro3d_sys::C3D_TexEnvOpRgb(
+            env.as_raw_mut(),
+            ctru_sys::GPU_TEVOP_RGB_SRC_COLOR,
+            ctru_sys::GPU_TEVOP_RGB_SRC_COLOR,
+            ctru_sys::GPU_TEVOP_RGB_SRC_ALPHA,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lut_from_fn() {
+        let fogged = FogLut::from_fn(|_| 0.0);
+        assert!(fogged.0.data.iter().all(|&entry| entry == 0));
+
+        let linear = FogLut::from_fn(|depth| depth);
+        assert!(linear.0.data.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -1,6 +1,7 @@
 #![feature(new_uninit)]
+#![feature(allocator_api)]
 #![feature(custom_test_frameworks)]
-#![test_runner(test_runner::run_gdb)]
+#![cfg_attr(not(feature = "mock"), test_runner(test_runner::run_gdb))]
 #![feature(doc_cfg)]
 #![feature(doc_auto_cfg)]
 #![doc(html_root_url = "https://rust3ds.github.io/citro3d-rs/crates")]
@@ -17,53 +18,217 @@
 //! ## Feature flags
 #![doc = document_features::document_features!()]
 
+#[cfg(not(any(target_os = "horizon", feature = "mock")))]
+compile_error!("citro3d can only be built for the 3DS, or for testing with the `mock` feature");
+
+// With the `mock` feature, only the parts of the crate which don't need the 3DS
+// libraries are built, against the stand-ins in `mock`. Modules with other
+// parts are replaced by their pure submodule.
+#[cfg(not(feature = "mock"))]
 pub mod attrib;
+#[cfg(not(feature = "mock"))]
+pub mod batch;
+#[cfg(not(feature = "mock"))]
+pub mod blend;
+#[cfg(not(feature = "mock"))]
 pub mod buffer;
+#[cfg(not(feature = "mock"))]
+mod builtin;
+pub mod color;
+#[cfg(not(feature = "mock"))]
+pub mod debug_draw;
 pub mod error;
+#[cfg(not(feature = "mock"))]
+pub mod fog;
+#[cfg(not(feature = "mock"))]
+pub mod light;
+#[cfg(feature = "mock")]
+#[path = "light/lut.rs"]
 pub mod light;
+#[cfg(not(feature = "mock"))]
 pub mod material;
+#[cfg(not(feature = "mock"))]
 pub mod math;
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(not(feature = "mock"))]
+pub mod pipeline;
+#[cfg(not(feature = "mock"))]
 pub mod render;
+#[cfg(not(feature = "mock"))]
 pub mod shader;
+#[cfg(not(feature = "mock"))]
+pub mod sprite;
+#[cfg(not(feature = "mock"))]
+pub mod state;
+#[cfg(not(feature = "mock"))]
 pub mod texenv;
+#[cfg(not(feature = "mock"))]
 pub mod texture;
+#[cfg(feature = "mock")]
+#[path = "texture/format.rs"]
+pub mod texture;
+#[cfg(not(feature = "mock"))]
+pub mod uniform;
+#[cfg(feature = "mock")]
+#[path = "uniform/index.rs"]
 pub mod uniform;
+#[cfg(not(feature = "mock"))]
 mod util;
 
+#[cfg(not(feature = "mock"))]
 use std::fmt;
+#[cfg(not(feature = "mock"))]
 use std::pin::Pin;
+#[cfg(not(feature = "mock"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(feature = "mock"))]
 use std::sync::{Arc, OnceLock};
 
 pub use error::{Error, Result};
+#[cfg(not(feature = "mock"))]
 use static_assertions::assert_impl_all;
+#[cfg(not(feature = "mock"))]
 use util::is_linear_ptr;
 
+#[cfg(not(feature = "mock"))]
 use self::texenv::TexEnv;
+#[cfg(not(feature = "mock"))]
 use self::uniform::Uniform;
 
+#[cfg(not(feature = "mock"))]
 pub mod macros {
     //! Helper macros for working with shaders.
+    //!
+    //! Both macros embed shader binaries with the 4-byte alignment
+    //! [`Library::from_bytes`](crate::shader::Library::from_bytes) requires,
+    //! which [`include_bytes!`] doesn't guarantee.
     pub use citro3d_macros::*;
+
+    #[doc(inline)]
+    pub use crate::include_shbin;
+}
+
+/// Embed a shader binary (`.shbin`) which was already compiled with `picasso`,
+/// and parse it into a [`shader::Library`]. The path is relative to the file
+/// the macro is invoked in, like with [`include_bytes!`].
+///
+/// The bytes are embedded 4-byte aligned, so unlike parsing the output of
+/// `include_bytes!` with [`shader::Library::from_bytes`] this can't fail
+/// because of misaligned data. To compile a `.pica` source file at build time
+/// instead, use [`include_shader!`](macros::include_shader).
+///
+/// This is named `include_shbin!` rather than `include_shader!`, because
+/// that name was already taken by the macro for `.pica` sources from
+/// `citro3d-macros`, which is re-exported alongside it in [`macros`].
+///
+/// # Errors
+///
+/// Evaluates to a `Result`, which is an error if the shader binary is invalid.
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// use citro3d::macros::include_shbin;
+/// use citro3d::uniform::Index;
+///
+/// let library = include_shbin!("../tests/assets/passthrough.shbin").unwrap();
+/// assert_eq!(library.len(), 1);
+///
+/// let program = citro3d::shader::Program::new(library.get(0).unwrap()).unwrap();
+/// assert_eq!(program.get_uniform("projection").unwrap(), Index::from(0));
+/// ```
+#[cfg(not(feature = "mock"))]
+#[macro_export]
+macro_rules! include_shbin {
+    ($path:literal) => {{
+        #[repr(C, align(4))]
+        struct Aligned<Bytes: ?Sized>(Bytes);
+
+        static ALIGNED: &Aligned<[u8]> = &Aligned(*include_bytes!($path));
+        $crate::shader::Library::from_bytes(&ALIGNED.0)
+    }};
+}
+
+// `libctru` silently drops commands that don't fit in the command buffer, so
+// draws are refused unless there is room for the worst case of everything
+// `citro3d` may send with them, see `Instance::draw_cmdbuf_space`.
+
+/// Command buffer space, in bytes, for the draw call itself and the state other
+/// than uniforms and LUTs: vertex attributes and buffers, texture units,
+/// combiners and the fixed-function registers.
+#[cfg(not(feature = "mock"))]
+const DRAW_STATE_SPACE: usize = 0x800;
+/// Command buffer space, in bytes, for uploading all uniforms of one shader:
+/// every float register (16 bytes each), then the integer and bool uniforms
+/// and command headers.
+#[cfg(not(feature = "mock"))]
+const SHADER_UNIFORM_SPACE: usize = uniform::FLOAT_REGISTER_COUNT * 16 + 0x40;
+/// Command buffer space, in bytes, for uploading one light or fog LUT (at most
+/// 256 words plus command headers).
+#[cfg(not(feature = "mock"))]
+const LUT_UPLOAD_SPACE: usize = 256 * 4 + 0x10;
+/// The smallest command buffer that can hold a draw call with a vertex and a
+/// geometry shader and no LUTs.
+#[cfg(not(feature = "mock"))]
+const MIN_CMDBUF_SIZE: usize = DRAW_STATE_SPACE + 2 * SHADER_UNIFORM_SPACE;
+
+/// Whether `citro3d` was initialized by an [`Instance`] that still exists.
+#[cfg(not(feature = "mock"))]
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Check that an [`Instance`] exists, before using the global `citro3d`
+/// context. Using the context before `C3D_Init` crashes instead of failing
+/// cleanly, so texture and combiner creation, binding and the draw calls
+/// return [`Error::FailedToInitialize`] instead.
+#[cfg(not(feature = "mock"))]
+pub(crate) fn ensure_initialized() -> Result<()> {
+    if INITIALIZED.load(Ordering::Acquire) {
+        Ok(())
+    } else {
+        Err(Error::FailedToInitialize)
+    }
 }
 
 /// The single instance for using `citro3d`. This is the base type that an application
 /// should instantiate to use this library.
+#[cfg(not(feature = "mock"))]
 #[non_exhaustive]
 #[must_use]
 pub struct Instance {
-    texenvs: [OnceLock<TexEnv>; texenv::TEXENV_COUNT],
+    texenvs: [OnceLock<TexEnv>; texenv::MAX_STAGES],
     /// The shader in use, we keep it at the rust level because the C API needs it to stay valid
     /// (at a fixed address) once bound
     shader: Option<Pin<Arc<shader::Program>>>,
     light_env: Pin<Box<light::LightEnv>>,
+    /// The size of the command buffer, in bytes
+    cmdbuf_size: usize,
+    /// Whether a draw call was refused this frame for lack of command buffer space
+    cmdbuf_full: bool,
+    /// Whether a frame is being rendered, i.e. `render_frame_with` is running
+    in_frame: bool,
+    depth_test: render::DepthTest,
+    // Like the depth test, `citro3d` can't read these back, so they are tracked
+    // here for `StateGuard` to restore
+    blend: blend::Blend,
+    cull_mode: render::CullMode,
+    scissor: render::Scissor,
+    /// The bound fog LUT, which the C API reads from when drawing
+    fog_lut: Option<Box<fog::FogLut>>,
+    /// When [`Instance::wait_for_vblank`] last returned
+    #[cfg(feature = "std")]
+    last_vblank: Option<std::time::Instant>,
 }
 
+#[cfg(not(feature = "mock"))]
 impl fmt::Debug for Instance {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Instance").finish_non_exhaustive()
     }
 }
 
+#[cfg(not(feature = "mock"))]
 impl Instance {
     /// Initialize the default `citro3d` instance.
     ///
@@ -74,13 +239,18 @@ impl Instance {
         Self::with_cmdbuf_size(citro3d_sys::C3D_DEFAULT_CMDBUF_SIZE.try_into().unwrap())
     }
 
-    /// Initialize the instance with a specified command buffer size.
+    /// Initialize the instance with a specified command buffer size, in bytes.
     ///
     /// # Errors
     ///
-    /// Fails if `citro3d` cannot be initialized.
+    /// Returns [`Error::InvalidSize`] if `size` is too small to hold a single
+    /// draw call along with a full upload of the vertex and geometry shader
+    /// uniforms (about 5 KiB). Fails if `citro3d` cannot be initialized.
     #[doc(alias = "C3D_Init")]
     pub fn with_cmdbuf_size(size: usize) -> Result<Self> {
+        if size < MIN_CMDBUF_SIZE {
+            return Err(Error::InvalidSize);
+        }
         if unsafe { citro3d_sys::C3D_Init(size) } {
             let mut light_env = Box::pin(light::LightEnv::new());
             unsafe {
@@ -88,10 +258,21 @@ impl Instance {
                 // out light_env later
                 citro3d_sys::C3D_LightEnvBind(light_env.as_mut().as_raw_mut());
             }
+            INITIALIZED.store(true, Ordering::Release);
             Ok(Self {
                 texenvs: std::array::from_fn(|_| OnceLock::new()),
                 shader: None,
                 light_env,
+                cmdbuf_size: size,
+                cmdbuf_full: false,
+                in_frame: false,
+                depth_test: render::DepthTest::default(),
+                blend: blend::Blend::default(),
+                cull_mode: render::CullMode::default(),
+                scissor: render::Scissor::default(),
+                fog_lut: None,
+                #[cfg(feature = "std")]
+                last_vblank: None,
             })
         } else {
             Err(Error::FailedToInitialize)
@@ -100,13 +281,38 @@ impl Instance {
 
     /// Select the given render target for drawing the frame.
     ///
+    /// This can be called several times within one
+    /// [`render_frame_with`](Self::render_frame_with) to draw to multiple targets
+    /// in the same frame, e.g. a shadow map followed by the main scene. Draws
+    /// go to the most recently selected target. There is only one `citro3d`
+    /// context, so every [`render::Target`] can be used with any [`Instance`].
+    ///
     /// # Errors
     ///
-    /// Fails if the given target cannot be used for drawing.
+    /// Fails if the given target cannot be used for drawing, including when
+    /// called outside of [`render_frame_with`](Self::render_frame_with).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use citro3d::{render::Target, Instance};
+    /// # fn draw(instance: &mut Instance, top: &Target, bottom: &Target) {
+    /// instance
+    ///     .render_frame_with(|instance| {
+    ///         instance.select_render_target(top).unwrap();
+    ///         // ... draw the top screen ...
+    ///
+    ///         instance.select_render_target(bottom).unwrap();
+    ///         // ... draw the bottom screen ...
+    ///     })
+    ///     .unwrap();
+    /// # }
+    /// ```
     #[doc(alias = "C3D_FrameDrawOn")]
     pub fn select_render_target(&mut self, target: &render::Target<'_>) -> Result<()> {
         let _ = self;
         if unsafe { citro3d_sys::C3D_FrameDrawOn(target.as_raw()) } {
+            render::set_drawing_to(Some(target));
             Ok(())
         } else {
             Err(Error::InvalidRenderTarget)
@@ -116,21 +322,162 @@ impl Instance {
     /// Render a frame. The passed in function/closure can mutate the instance,
     /// such as to [select a render target](Self::select_render_target)
     /// or [bind a new shader program](Self::bind_program).
+    ///
+    /// This waits for the previous frame, see [`render::FrameFlags::SYNC_DRAW`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FrameInProgress`] without calling `f` if a frame is
+    /// already being rendered, i.e. this is called from within `f` of another
+    /// frame.
+    ///
+    /// Returns [`Error::CommandBufferFull`] if any draw call in the frame was
+    /// refused because the GPU command buffer was full (see
+    /// [`Instance::cmdbuf_remaining`]), so the frame is missing those draws.
+    /// The frame is still ended in this case.
     #[doc(alias = "C3D_FrameBegin")]
     #[doc(alias = "C3D_FrameEnd")]
-    pub fn render_frame_with(&mut self, f: impl FnOnce(&mut Self)) {
-        unsafe {
-            citro3d_sys::C3D_FrameBegin(
-                // TODO: begin + end flags should be configurable
-                citro3d_sys::C3D_FRAME_SYNCDRAW,
-            );
+    pub fn render_frame_with(&mut self, f: impl FnOnce(&mut Self)) -> Result<()> {
+        // Without `NONBLOCK`, a frame is always rendered if this succeeds
+        self.render_frame_with_flags(render::FrameFlags::default(), f)
+            .map(|rendered| debug_assert!(rendered))
+    }
+
+    /// Render a frame like [`render_frame_with`](Self::render_frame_with), with
+    /// `flags` controlling how to wait for the GPU beforehand.
+    ///
+    /// Returns `Ok(true)` once the frame was rendered. Only if `flags` contains
+    /// [`render::FrameFlags::NONBLOCK`], this returns `Ok(false)` without
+    /// calling `f` when the GPU was still busy with the previous frame, so the
+    /// frame should be retried later.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FrameInProgress`] without calling `f` if a frame is
+    /// already being rendered. Also returns [`Error::CommandBufferFull`] like
+    /// [`render_frame_with`](Self::render_frame_with).
+    #[doc(alias = "C3D_FrameBegin")]
+    #[doc(alias = "C3D_FrameEnd")]
+    pub fn render_frame_with_flags(
+        &mut self,
+        flags: render::FrameFlags,
+        f: impl FnOnce(&mut Self),
+    ) -> Result<bool> {
+        if self.in_frame {
+            return Err(Error::FrameInProgress);
+        }
+        if !unsafe { citro3d_sys::C3D_FrameBegin(flags.bits()) } {
+            // Outside of a frame, this only fails if it wasn't allowed to wait
+            return if flags.contains(render::FrameFlags::NONBLOCK) {
+                Ok(false)
+            } else {
+                Err(Error::FrameInProgress)
+            };
         }
 
+        self.cmdbuf_full = false;
+        self.in_frame = true;
         f(self);
+        self.in_frame = false;
+        let full = std::mem::take(&mut self.cmdbuf_full);
 
         unsafe {
             citro3d_sys::C3D_FrameEnd(0);
         }
+        // Each frame selects its targets again
+        render::set_drawing_to(None);
+
+        if full {
+            Err(Error::CommandBufferFull)
+        } else {
+            Ok(true)
+        }
+    }
+
+    /// Wait for the next vertical blank of the top screen, and get the time
+    /// elapsed since the previous call returned. The first call returns
+    /// [`Duration::ZERO`](std::time::Duration::ZERO).
+    ///
+    /// This is useful to pace frames rendered without
+    /// [`render::FrameFlags::SYNC_DRAW`], and to scale animations by the frame
+    /// time.
+    #[cfg(feature = "std")]
+    #[doc(alias = "gspWaitForVBlank")]
+    pub fn wait_for_vblank(&mut self) -> std::time::Duration {
+        unsafe { ctru_sys::gspWaitForEvent(ctru_sys::GSPGPU_EVENT_VBlank0, true) };
+
+        let now = std::time::Instant::now();
+        let elapsed = self
+            .last_vblank
+            .map_or(std::time::Duration::ZERO, |last| now - last);
+        self.last_vblank = Some(now);
+        elapsed
+    }
+
+    /// Submit all GPU commands recorded so far and wait for the GPU to finish
+    /// executing them.
+    ///
+    /// Draw calls are normally only recorded into the command buffer, which is
+    /// submitted when the frame ends. This is only needed when something has to
+    /// see the results of draws earlier in the same frame, e.g. before
+    /// [`render::Target::capture_to`] or [`render::Target::read_pixels`] reads a
+    /// target that was just drawn to, in a multi-pass setup. Otherwise, the
+    /// copy would miss the draws still in the command buffer.
+    ///
+    /// This stalls the CPU until the GPU is idle and splits the frame's
+    /// command list, so calling it more than a few times per frame will hurt
+    /// performance. Don't use it just to "make sure" rendering happened.
+    #[doc(alias = "C3D_Flush")]
+    #[doc(alias = "C3D_FlushAwait")]
+    pub fn flush_await(&mut self) {
+        unsafe {
+            citro3d_sys::C3D_FlushAsync();
+            citro3d_sys::C3D_FlushAwait();
+        }
+    }
+
+    /// Get the fraction of the GPU command buffer currently in use, from `0.0`
+    /// (empty) to `1.0` (full).
+    #[doc(alias = "C3D_GetCmdBufUsage")]
+    pub fn cmdbuf_usage(&self) -> f32 {
+        unsafe { citro3d_sys::C3D_GetCmdBufUsage() }
+    }
+
+    /// Get the free space in the GPU command buffer, in bytes, e.g. for a debug
+    /// overlay to tune [`Instance::with_cmdbuf_size`]. Draw calls return
+    /// [`Error::CommandBufferFull`] instead of drawing once this is less than
+    /// the most `citro3d` may send for a draw, which grows with the shaders of
+    /// the bound program and the number of connected light and fog LUTs.
+    #[doc(alias = "C3D_GetCmdBufUsage")]
+    pub fn cmdbuf_remaining(&self) -> usize {
+        let used = (self.cmdbuf_usage() * self.cmdbuf_size as f32) as usize;
+        self.cmdbuf_size.saturating_sub(used)
+    }
+
+    /// Check that a draw call can go ahead: `citro3d` is initialized, no
+    /// texture is sampled while it is drawn to, and there is enough command
+    /// buffer space for the draw.
+    pub(crate) fn prepare_draw(&mut self) -> Result<()> {
+        ensure_initialized()?;
+        render::check_not_sampling_target()?;
+        if self.cmdbuf_remaining() < self.draw_cmdbuf_space() {
+            self.cmdbuf_full = true;
+            return Err(Error::CommandBufferFull);
+        }
+        Ok(())
+    }
+
+    /// The free command buffer space, in bytes, required before a draw call:
+    /// the draw and its fixed state, a full uniform upload for each shader of
+    /// the bound program (both, if none is bound yet) and an upload of every
+    /// connected light and fog LUT.
+    fn draw_cmdbuf_space(&self) -> usize {
+        let shaders = self
+            .shader
+            .as_ref()
+            .map_or(2, |program| program.shader_count());
+        let luts = self.light_env.lut_count() + usize::from(self.fog_lut.is_some());
+        DRAW_STATE_SPACE + shaders * SHADER_UNIFORM_SPACE + luts * LUT_UPLOAD_SPACE
     }
 
     /// Get the buffer info being used, if it exists. Note that the resulting
@@ -165,9 +512,57 @@ impl Instance {
         unsafe { citro3d_sys::C3D_SetAttrInfo(raw.cast_mut()) };
     }
 
+    /// Set the value of a fixed vertex attribute for all following draw calls.
+    /// The attribute must have been added to the attribute info in use with
+    /// [`attrib::Info::add_fixed`], e.g. to draw a whole mesh in one color
+    /// without storing the color in every vertex.
+    ///
+    /// The value stays in effect until it is set again, even if a different
+    /// attribute info is used in the meantime.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFixedAttribute`] if there is no fixed attribute for
+    /// `register` in the current [attribute info](Instance::attr_info).
+    #[doc(alias = "C3D_FixedAttribGetWritePtr")]
+    pub fn set_fixed_attribute(
+        &mut self,
+        register: attrib::Register,
+        value: math::FVec4,
+    ) -> Result<()> {
+        let index = self
+            .attr_info()
+            .and_then(|info| info.fixed_index(register))
+            .ok_or(Error::NotFixedAttribute(register.index()))?;
+
+        // SAFETY: the index is a valid attribute index, so the pointer is to one
+        // of the fixed attributes in the global `citro3d` context
+        unsafe {
+            let ptr = citro3d_sys::C3D_FixedAttribGetWritePtr(index.0.into());
+            *ptr = value.0;
+        }
+
+        Ok(())
+    }
+
     /// Render primitives from the current vertex array buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidVertexCount`] if the slice length is not
+    /// [valid](buffer::Primitive::validate_count) for `primitive` and the bound
+    /// program, or [`Error::SamplingRenderTarget`] if a texture bound with
+    /// [`Tex::bind`](texture::Tex::bind) belongs to the selected render target.
+    /// Returns [`Error::CommandBufferFull`] if there isn't enough space left in
+    /// the GPU command buffer. Nothing is drawn in any of these cases.
     #[doc(alias = "C3D_DrawArrays")]
-    pub fn draw_arrays(&mut self, primitive: buffer::Primitive, vbo_data: buffer::Slice) {
+    pub fn draw_arrays(
+        &mut self,
+        primitive: buffer::Primitive,
+        vbo_data: buffer::Slice,
+    ) -> Result<()> {
+        self.prepare_draw()?;
+        primitive.validate_draw(self.shader.as_deref(), vbo_data.len().try_into()?)?;
         self.set_buffer_info(vbo_data.info());
 
         // TODO: should we also require the attrib info directly here?
@@ -178,10 +573,91 @@ impl Instance {
                 vbo_data.len(),
             );
         }
+        Ok(())
+    }
+
+    /// Bind `program` and `attr_info`, then draw `vbo_data`, for the common case
+    /// of drawing a single mesh. This is the same as calling
+    /// [`Instance::bind_program`], [`Instance::set_attr_info`] and
+    /// [`Instance::draw_arrays`] (which binds the slice's buffer info) in
+    /// order, and the bindings stay in effect afterwards. Use those directly to
+    /// draw several meshes without rebinding each time.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::AttributeMismatch`] if `attr_info` doesn't match the inputs
+    ///   of `program`, see [`attrib::Info::validate`]. Nothing is bound in that
+    ///   case.
+    /// * Any error returned by [`Instance::draw_arrays`].
+    pub fn quick_draw(
+        &mut self,
+        program: Pin<Arc<shader::Program>>,
+        attr_info: &attrib::Info,
+        vbo_data: buffer::Slice,
+        primitive: buffer::Primitive,
+    ) -> Result<()> {
+        attr_info.validate(&program)?;
+
+        self.bind_program(program);
+        self.set_attr_info(attr_info);
+        self.draw_arrays(primitive, vbo_data)
+    }
+
+    /// Draw `vbo_data` once for each of `transforms`, uploading the transform to
+    /// the vertex shader uniform at `uniform` before each draw, e.g. for
+    /// particles or other repeated meshes. The PICA200 has no hardware
+    /// instancing, so this is a loop of draw calls, but the vertex count is only
+    /// validated and the buffer info only bound once.
+    ///
+    /// The uniform is left set to the last transform afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidVertexCount`] if the slice length is not
+    /// [valid](buffer::Primitive::validate_count) for `primitive` and the bound
+    /// program, or [`Error::SamplingRenderTarget`] if a texture bound with
+    /// [`Tex::bind`](texture::Tex::bind) belongs to the selected render target.
+    /// Returns [`Error::UniformKindMismatch`] if `uniform` is not a float uniform
+    /// index, or [`Error::UniformOverflow`] if a matrix doesn't fit at it.
+    /// Returns [`Error::CommandBufferFull`] if there isn't enough space left in
+    /// the GPU command buffer. Nothing is drawn in any of these cases, except
+    /// when the command buffer fills up partway, after drawing the transforms
+    /// before that.
+    #[doc(alias = "C3D_DrawArrays")]
+    pub fn draw_instanced(
+        &mut self,
+        primitive: buffer::Primitive,
+        vbo_data: buffer::Slice,
+        transforms: &[math::Matrix4],
+        uniform: uniform::Index,
+    ) -> Result<()> {
+        self.prepare_draw()?;
+        primitive.validate_draw(self.shader.as_deref(), vbo_data.len().try_into()?)?;
+        if let Some(transform) = transforms.first() {
+            Uniform::from(transform).check_index(uniform)?;
+        }
+        self.set_buffer_info(vbo_data.info());
+
+        for (i, transform) in transforms.iter().enumerate() {
+            if i > 0 {
+                self.prepare_draw()?;
+            }
+            self.bind_vertex_uniform(uniform, transform)?;
+            unsafe {
+                citro3d_sys::C3D_DrawArrays(
+                    primitive as ctru_sys::GPU_Primitive_t,
+                    vbo_data.index(),
+                    vbo_data.len(),
+                );
+            }
+        }
+        Ok(())
     }
+
     /// Indexed drawing
     ///
     /// Draws the vertices in `buf` indexed by `indices`. `indices` must be linearly allocated
+    /// and, for [`IndexType::U16`], in little-endian byte order (see [`buffer::swap_indices_u16`]).
     ///
     /// # Safety
     /// If `indices` goes out of scope before the current frame ends it will cause a use-after-free (possibly by the GPU)
@@ -189,15 +665,25 @@ impl Instance {
     ///
     /// # Panics
     /// If `indices` is not allocated in linear memory
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidVertexCount`] if the number of indices is not
+    /// [valid](buffer::Primitive::validate_count) for `primitive`, or
+    /// [`Error::SamplingRenderTarget`] if a texture bound with
+    /// [`Tex::bind`](texture::Tex::bind) belongs to the selected render target.
+    /// Returns [`Error::CommandBufferFull`] if there isn't enough space left in
+    /// the GPU command buffer. Nothing is drawn in any of these cases.
     #[doc(alias = "C3D_DrawElements")]
     pub unsafe fn draw_elements<'a>(
         &mut self,
         primitive: buffer::Primitive,
         buf: &buffer::Info,
         indices: impl Into<IndexType<'a>>,
-    ) {
-        self.set_buffer_info(buf);
+    ) -> Result<()> {
+        self.prepare_draw()?;
         let indices: IndexType<'a> = indices.into();
+        primitive.validate_draw(self.shader.as_deref(), indices.len())?;
+        self.set_buffer_info(buf);
         let elements = match indices {
             IndexType::U16(v) => v.as_ptr() as *const _,
             IndexType::U8(v) => v.as_ptr() as *const _,
@@ -216,9 +702,15 @@ impl Instance {
             } as i32,
             elements,
         );
+        Ok(())
     }
 
     /// Use the given [`shader::Program`] for subsequent draw calls.
+    ///
+    /// `citro3d` keeps a pointer to the bound program, so the instance holds on
+    /// to `program` until another one is bound. This keeps it alive (and at the
+    /// same address) for as long as the GPU may use it.
+    #[doc(alias = "C3D_BindProgram")]
     pub fn bind_program(&mut self, program: Pin<Arc<shader::Program>>) {
         // SAFETY: AFAICT C3D_BindProgram just copies pointers from the given program,
         // instead of mutating the pointee in any way that would cause UB
@@ -227,12 +719,56 @@ impl Instance {
         }
         self.shader.replace(program);
     }
+
+    /// Get the [`shader::Program`] bound with [`Instance::bind_program`], if any.
+    pub fn current_program(&self) -> Option<&Pin<Arc<shader::Program>>> {
+        self.shader.as_ref()
+    }
+
+    /// Save the current GPU state, which is restored when the returned
+    /// [`state::StateGuard`] is dropped. See the [`state`] module for details.
+    pub fn save_state(&mut self) -> state::StateGuard<'_> {
+        state::StateGuard::new(self)
+    }
+
+    /// Set the ambient color of the active lighting environment, i.e. the one
+    /// returned by [`Instance::light_env_mut`].
+    ///
+    /// The hardware only has a per-environment ambient color (see
+    /// [`light::LightEnv::set_ambient`]), so this is a shorthand which forwards
+    /// to the active environment rather than a separate global setting. An
+    /// environment switched in with [`Instance::set_light_env`] uses its own
+    /// ambient color.
+    pub fn set_ambient(&mut self, color: color::Color) {
+        self.light_env_mut().set_ambient(color);
+    }
+
     pub fn light_env_mut(&mut self) -> Pin<&mut light::LightEnv> {
         self.light_env.as_mut()
     }
 
+    /// Use the given [`light::LightEnv`] for subsequent draw calls, returning the
+    /// previously active one. Only one environment is active at a time, so this
+    /// can be used to switch between lighting setups, e.g. for different passes.
+    #[doc(alias = "C3D_LightEnvBind")]
+    pub fn set_light_env(
+        &mut self,
+        mut light_env: Pin<Box<light::LightEnv>>,
+    ) -> Pin<Box<light::LightEnv>> {
+        // SAFETY: we own the environment and keep it pinned for as long as it is bound
+        unsafe { light_env.as_mut().bind() };
+        std::mem::replace(&mut self.light_env, light_env)
+    }
+
     /// Bind a uniform to the given `index` in the vertex shader for the next draw call.
     ///
+    /// # Errors
+    ///
+    /// * [`Error::UniformKindMismatch`] if `index` belongs to a different
+    ///   register file than the uniform, e.g. binding a matrix to a `.bool`.
+    /// * [`Error::UniformOverflow`] if the uniform would not fit in the float
+    ///   registers starting at `index`.
+    ///
     /// # Example
     ///
     /// ```
@@ -243,14 +779,22 @@ impl Instance {
     /// # let mut instance = citro3d::Instance::new().unwrap();
     /// let idx = uniform::Index::from(0);
     /// let mtx = Matrix4::identity();
-    /// instance.bind_vertex_uniform(idx, &mtx);
+    /// instance.bind_vertex_uniform(idx, &mtx).unwrap();
     /// ```
-    pub fn bind_vertex_uniform(&mut self, index: uniform::Index, uniform: impl Into<Uniform>) {
-        uniform.into().bind(self, shader::Type::Vertex, index);
+    pub fn bind_vertex_uniform(
+        &mut self,
+        index: uniform::Index,
+        uniform: impl Into<Uniform>,
+    ) -> Result<()> {
+        uniform.into().bind(self, shader::Type::Vertex, index)
     }
 
     /// Bind a uniform to the given `index` in the geometry shader for the next draw call.
     ///
+    /// # Errors
+    ///
+    /// See [`Instance::bind_vertex_uniform`].
+    ///
     /// # Example
     ///
     /// ```
@@ -261,10 +805,52 @@ impl Instance {
     /// # let mut instance = citro3d::Instance::new().unwrap();
     /// let idx = uniform::Index::from(0);
     /// let mtx = Matrix4::identity();
-    /// instance.bind_geometry_uniform(idx, &mtx);
+    /// instance.bind_geometry_uniform(idx, &mtx).unwrap();
     /// ```
-    pub fn bind_geometry_uniform(&mut self, index: uniform::Index, uniform: impl Into<Uniform>) {
-        uniform.into().bind(self, shader::Type::Geometry, index);
+    pub fn bind_geometry_uniform(
+        &mut self,
+        index: uniform::Index,
+        uniform: impl Into<Uniform>,
+    ) -> Result<()> {
+        uniform.into().bind(self, shader::Type::Geometry, index)
+    }
+
+    /// Upload an array of matrices to consecutive uniform registers of the given
+    /// shader, starting at `start`. Each matrix occupies four registers, so this
+    /// is suitable for things like bone transforms declared as `.fvec bones[N*4]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UniformOverflow`] if the matrices would not fit in the
+    /// [96 float uniform registers](uniform::float_register_capacity). Nothing
+    /// is uploaded in that case.
+    #[doc(alias = "C3D_FVUnifSet")]
+    pub fn set_uniform_matrices(
+        &mut self,
+        ty: shader::Type,
+        start: uniform::Index,
+        mats: &[math::Matrix4],
+    ) -> Result<()> {
+        uniform::bind_matrices(self, ty, start, mats)
+    }
+
+    /// Upload an array of vectors to consecutive uniform registers of the given
+    /// shader, starting at `start`, e.g. for per-instance data declared as
+    /// `.fvec offsets[N]`. See [`uniform::float_register_capacity`] for how many
+    /// registers are available.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UniformOverflow`] if the vectors would not fit in the
+    /// 96 float uniform registers. Nothing is uploaded in that case.
+    #[doc(alias = "C3D_FVUnifSet")]
+    pub fn set_uniform_vectors(
+        &mut self,
+        ty: shader::Type,
+        start: uniform::Index,
+        vectors: &[math::FVec4],
+    ) -> Result<()> {
+        uniform::bind_vectors(self, ty, start, vectors)
     }
 
     /// Retrieve the [`TexEnv`] for the given stage, initializing it first if necessary.
@@ -280,30 +866,282 @@ impl Instance {
     /// ```
     #[doc(alias = "C3D_GetTexEnv")]
     #[doc(alias = "C3D_TexEnvInit")]
+    #[doc(alias = "get_tex_env")]
     pub fn texenv(&mut self, stage: texenv::Stage) -> &mut texenv::TexEnv {
         let texenv = &mut self.texenvs[stage.0];
-        texenv.get_or_init(|| TexEnv::new(stage));
+        texenv.get_or_init(|| {
+            TexEnv::new(stage).expect("citro3d is initialized while an Instance exists")
+        });
         // We have to do this weird unwrap to get a mutable reference,
         // since there is no `get_mut_or_init` or equivalent
         texenv.get_mut().unwrap()
     }
+
+    /// Reset all [`texenv::MAX_STAGES`] texture combiner stages to their default
+    /// state, e.g. so combiner state from a previous rendering pass doesn't leak
+    /// into the next one.
+    ///
+    /// Note that the texture combiners are global GPU state, so this affects all
+    /// following draw calls.
+    #[doc(alias = "C3D_TexEnvInit")]
+    #[doc(alias = "reset_tex_envs")]
+    pub fn reset_texenvs(&mut self) {
+        for stage in texenv::Stage::all() {
+            self.texenv(stage).reset();
+        }
+    }
+
+    /// Set how fragments are blended with the framebuffer, either from a
+    /// [`blend::BlendPreset`] or a custom [`blend::Blend`]. See the [`blend`]
+    /// module for details.
+    ///
+    /// Blending is global GPU state, so this affects all following draw calls.
+    #[doc(alias = "C3D_AlphaBlend")]
+    pub fn set_blend(&mut self, blend: impl Into<blend::Blend>) {
+        let blend = blend.into();
+        self.blend = blend;
+        unsafe {
+            citro3d_sys::C3D_AlphaBlend(
+                blend.color_eq as _,
+                blend.alpha_eq as _,
+                blend.src_color as _,
+                blend.dst_color as _,
+                blend.src_alpha as _,
+                blend.dst_alpha as _,
+            );
+        }
+    }
+
+    /// Get the blending set with [`Instance::set_blend`].
+    pub fn blend(&self) -> blend::Blend {
+        self.blend
+    }
+
+    /// Set which faces following draw calls cull. Defaults to
+    /// [`render::CullMode::BackCounterClockwise`].
+    #[doc(alias = "C3D_CullFace")]
+    pub fn set_cull_mode(&mut self, mode: render::CullMode) {
+        self.cull_mode = mode;
+        unsafe { citro3d_sys::C3D_CullFace(mode as _) };
+    }
+
+    /// Get the cull mode set with [`Instance::set_cull_mode`].
+    pub fn cull_mode(&self) -> render::CullMode {
+        self.cull_mode
+    }
+
+    /// Set the scissor test for following draw calls. Defaults to
+    /// [`render::Scissor::Disabled`].
+    #[doc(alias = "C3D_SetScissor")]
+    pub fn set_scissor(&mut self, scissor: render::Scissor) {
+        self.scissor = scissor;
+        scissor.apply();
+    }
+
+    /// Get the scissor test set with [`Instance::set_scissor`].
+    pub fn scissor(&self) -> render::Scissor {
+        self.scissor
+    }
+
+    /// Set the color used by the `Constant*` [`blend::Factor`]s.
+    #[doc(alias = "C3D_BlendingColor")]
+    pub fn set_blend_color(&mut self, color: color::Color) {
+        unsafe { citro3d_sys::C3D_BlendingColor(color.to_packed_abgr()) };
+    }
+
+    /// Configure the depth test for following draw calls: whether it is
+    /// enabled, and which [`render::TestFunc`] a fragment's depth must pass
+    /// against the depth buffer to be drawn. Defaults to enabled, with
+    /// [`render::TestFunc::Greater`] (matching the default
+    /// [depth map](Instance::set_depth_map)).
+    ///
+    /// This keeps the current [write mask](Instance::set_write_mask).
+    #[doc(alias = "C3D_DepthTest")]
+    pub fn set_depth_test(&mut self, enabled: bool, func: render::TestFunc) {
+        self.depth_test.enabled = enabled;
+        self.depth_test.func = func;
+        self.depth_test.apply();
+    }
+
+    /// Configure the alpha test for following draw calls, which discards
+    /// fragments whose alpha doesn't pass `func` against `reference`, e.g.
+    /// [`render::TestFunc::Greater`] with `0` to cut out fully transparent
+    /// texels. Disabled by default.
+    #[doc(alias = "C3D_AlphaTest")]
+    pub fn set_alpha_test(&mut self, enabled: bool, func: render::TestFunc, reference: u8) {
+        unsafe { citro3d_sys::C3D_AlphaTest(enabled, func.into(), reference.into()) };
+    }
+
+    /// Configure the stencil test for following draw calls, which passes if
+    /// `reference & input_mask` passes `func` against the stencil buffer value
+    /// `& input_mask`. `write_mask` selects the stencil bits which can be
+    /// written. Disabled by default, and only has an effect on render targets
+    /// [with a stencil buffer](render::Target::has_stencil).
+    #[doc(alias = "C3D_StencilTest")]
+    pub fn set_stencil_test(
+        &mut self,
+        enabled: bool,
+        func: render::TestFunc,
+        reference: u8,
+        input_mask: u8,
+        write_mask: u8,
+    ) {
+        unsafe {
+            citro3d_sys::C3D_StencilTest(
+                enabled,
+                func.into(),
+                reference.into(),
+                input_mask.into(),
+                write_mask.into(),
+            );
+        }
+    }
+
+    /// Set which channels of the render target are written by following draw
+    /// calls, e.g. [`render::WriteMask::DEPTH`] for a depth prepass or
+    /// an empty mask for a stencil-only pass. Defaults to [`render::WriteMask::ALL`].
+    ///
+    /// The write mask shares a register with the depth test, so this keeps the
+    /// current depth test settings.
+    #[doc(alias = "C3D_DepthTest")]
+    pub fn set_write_mask(&mut self, mask: render::WriteMask) {
+        self.depth_test.write_mask = mask;
+        self.depth_test.apply();
+    }
+
+    /// Get the write mask set with [`Instance::set_write_mask`], e.g. to restore
+    /// it after a depth-only pass.
+    pub fn write_mask(&self) -> render::WriteMask {
+        self.depth_test.write_mask
+    }
+
+    /// Set which color channels are written by following draw calls, keeping
+    /// whether depth is written. See [`Instance::set_write_mask`].
+    #[doc(alias = "C3D_DepthTest")]
+    pub fn set_color_mask(&mut self, r: bool, g: bool, b: bool, a: bool) {
+        let mut mask = self.depth_test.write_mask & render::WriteMask::DEPTH;
+        mask.set(render::WriteMask::RED, r);
+        mask.set(render::WriteMask::GREEN, g);
+        mask.set(render::WriteMask::BLUE, b);
+        mask.set(render::WriteMask::ALPHA, a);
+        self.set_write_mask(mask);
+    }
+
+    /// Set how clip space Z coordinates are mapped to depth buffer values:
+    /// `depth = z / w * scale + offset` if `z_buffer` is `true`, or
+    /// `depth = z * scale + offset` (W-buffering) otherwise. `citro3d` defaults
+    /// to Z-buffering with a scale of `-1.0` and an offset
+    /// of `0.0`, matching the projections in [`math::Projection`].
+    ///
+    /// There is no depth clamp: the PICA200 always clips geometry against the
+    /// near and far planes, and has no register to clamp depth instead. To
+    /// keep e.g. a skybox or shadow volume from being clipped by the far plane,
+    /// have the vertex shader write a Z just inside it (as `-w` times slightly
+    /// less than one, with the default projections), or use a projection with
+    /// a far plane beyond the geometry.
+    #[doc(alias = "C3D_DepthMap")]
+    #[doc(alias = "depth_clamp")]
+    pub fn set_depth_map(&mut self, z_buffer: bool, scale: f32, offset: f32) {
+        unsafe { citro3d_sys::C3D_DepthMap(z_buffer, scale, offset) };
+    }
+
+    /// Enable depth-based fog for following draw calls, blending fragments
+    /// towards `color` by the factor `lut` maps their depth to. See the
+    /// [`fog`] module for details.
+    ///
+    /// If the fog is strongest near the camera instead of far away, the
+    /// depths are reversed compared to `lut`; `flip_depth` looks them up as
+    /// `1.0 - depth` instead.
+    #[doc(alias = "C3D_FogGasMode")]
+    #[doc(alias = "C3D_FogColor")]
+    #[doc(alias = "C3D_FogLutBind")]
+    pub fn set_fog(&mut self, lut: fog::FogLut, color: color::Color, flip_depth: bool) {
+        let lut = self.fog_lut.insert(Box::new(lut));
+        unsafe {
+            citro3d_sys::C3D_FogGasMode(ctru_sys::GPU_FOG, ctru_sys::GPU_PLAIN_DENSITY, flip_depth);
+            citro3d_sys::C3D_FogColor(color.to_packed_abgr());
+            citro3d_sys::C3D_FogLutBind(lut.as_raw_mut());
+        }
+    }
+
+    /// Disable the fog enabled with [`Instance::set_fog`].
+    #[doc(alias = "C3D_FogGasMode")]
+    pub fn disable_fog(&mut self) {
+        unsafe {
+            citro3d_sys::C3D_FogGasMode(ctru_sys::GPU_NO_FOG, ctru_sys::GPU_PLAIN_DENSITY, false);
+            citro3d_sys::C3D_FogLutBind(std::ptr::null_mut());
+        }
+        self.fog_lut = None;
+    }
+
+    /// Reset the global render state to the defaults set by `C3D_Init`, e.g.
+    /// before handing the GPU over to an unrelated rendering system. Unlike
+    /// [`Instance::save_state`], nothing is restored afterwards.
+    ///
+    /// This touches exactly the following state:
+    ///
+    /// * Depth test: enabled, passing when the fragment's depth is greater than
+    ///   the buffer's. Early depth test: disabled.
+    /// * Write mask: [`render::WriteMask::ALL`].
+    /// * Depth map: Z-buffering with scale `-1.0` and offset `0.0`.
+    /// * Alpha test: disabled.
+    /// * Stencil test: disabled, with all stencil operations set to keep.
+    /// * Blending: [`blend::BlendPreset::Alpha`], with a transparent black
+    ///   [blend color](Instance::set_blend_color).
+    /// * Face culling: back faces (counter-clockwise winding) are culled.
+    /// * Scissor test: disabled.
+    /// * Fog: disabled.
+    /// * All texture combiner stages, see [`Instance::reset_texenvs`].
+    ///
+    /// The bound shader program, vertex attributes, buffers, textures, uniforms
+    /// and lighting environment are left as they are.
+    #[doc(alias = "C3D_DepthTest")]
+    #[doc(alias = "C3D_CullFace")]
+    #[doc(alias = "C3D_SetScissor")]
+    pub fn reset_render_state(&mut self) {
+        unsafe {
+            citro3d_sys::C3D_EarlyDepthTest(false, ctru_sys::GPU_EARLYDEPTH_GREATER, 0);
+            citro3d_sys::C3D_StencilOp(
+                ctru_sys::GPU_STENCIL_KEEP,
+                ctru_sys::GPU_STENCIL_KEEP,
+                ctru_sys::GPU_STENCIL_KEEP,
+            );
+        }
+
+        self.depth_test = render::DepthTest::default();
+        self.depth_test.apply();
+        self.set_alpha_test(false, render::TestFunc::Always, 0);
+        self.set_stencil_test(false, render::TestFunc::Always, 0, 0xFF, 0);
+        self.set_depth_map(true, -1.0, 0.0);
+        self.set_blend(blend::Blend::default());
+        self.set_cull_mode(render::CullMode::default());
+        self.set_scissor(render::Scissor::default());
+        self.set_blend_color(color::Color::from_packed(0));
+        self.disable_fog();
+        self.reset_texenvs();
+    }
 }
 
+#[cfg(not(feature = "mock"))]
 impl Drop for Instance {
     #[doc(alias = "C3D_Fini")]
     fn drop(&mut self) {
+        INITIALIZED.store(false, Ordering::Release);
         unsafe {
             citro3d_sys::C3D_Fini();
         }
     }
 }
 
+#[cfg(not(feature = "mock"))]
 assert_impl_all!(Instance: Send, Sync);
 
+#[cfg(not(feature = "mock"))]
 pub enum IndexType<'a> {
     U16(&'a [u16]),
     U8(&'a [u8]),
 }
+#[cfg(not(feature = "mock"))]
 impl IndexType<'_> {
     fn len(&self) -> usize {
         match self {
@@ -313,14 +1151,92 @@ impl IndexType<'_> {
     }
 }
 
+#[cfg(not(feature = "mock"))]
 impl<'a> From<&'a [u8]> for IndexType<'a> {
     fn from(v: &'a [u8]) -> Self {
         Self::U8(v)
     }
 }
 
+#[cfg(not(feature = "mock"))]
 impl<'a> From<&'a [u16]> for IndexType<'a> {
     fn from(v: &'a [u16]) -> Self {
         Self::U16(v)
     }
 }
+
+#[cfg(all(test, not(feature = "mock")))]
+mod tests {
+    use super::*;
+    use crate::macros::include_shader;
+
+    static SHADER_BYTES: &[u8] = include_shader!("../examples/assets/vshader.pica");
+
+    #[test]
+    fn render_frame_in_frame() {
+        let mut instance = Instance::new().unwrap();
+        let mut nested = None;
+        instance
+            .render_frame_with(|instance| {
+                nested = Some(
+                    instance.render_frame_with_flags(render::FrameFlags::NONBLOCK, |_| {
+                        unreachable!("nested frame was rendered")
+                    }),
+                );
+            })
+            .unwrap();
+        assert!(matches!(nested, Some(Err(Error::FrameInProgress))));
+
+        // The frame was still ended, so the next one can be rendered
+        let mut rendered = false;
+        instance.render_frame_with(|_| rendered = true).unwrap();
+        assert!(rendered);
+    }
+
+    #[test]
+    fn draw_refused_when_cmdbuf_full() {
+        assert!(matches!(
+            Instance::with_cmdbuf_size(MIN_CMDBUF_SIZE - 1),
+            Err(Error::InvalidSize)
+        ));
+
+        let mut instance = Instance::with_cmdbuf_size(0x4000).unwrap();
+        let library = shader::Library::from_bytes(SHADER_BYTES).unwrap();
+        let program = shader::Program::new(library.get(0).unwrap()).unwrap();
+        instance.bind_program(Arc::pin(program));
+
+        let mut attr_info = attrib::Info::new();
+        attr_info
+            .add_loader(attrib::Register::new(0).unwrap(), attrib::Format::Float, 3)
+            .unwrap();
+        attr_info
+            .add_loader(attrib::Register::new(1).unwrap(), attrib::Format::Float, 3)
+            .unwrap();
+        instance.set_attr_info(&attr_info);
+
+        let vbo = buffer::LinearBuffer::from_slice(&[[0.0f32; 6]; 3]);
+        let mut buf_info = buffer::Info::new();
+        let slice = buf_info.add(vbo.as_ref(), &attr_info).unwrap();
+
+        let mut refused = None;
+        let result = instance.render_frame_with(|instance| {
+            refused = (0..10_000).find_map(|_| {
+                instance
+                    .draw_arrays(buffer::Primitive::Triangles, slice)
+                    .err()
+            });
+            assert!(instance.cmdbuf_remaining() < instance.draw_cmdbuf_space());
+        });
+        assert!(matches!(refused, Some(Error::CommandBufferFull)));
+        assert!(matches!(result, Err(Error::CommandBufferFull)));
+
+        // The next frame has the whole command buffer again
+        instance
+            .render_frame_with(|instance| {
+                instance
+                    .draw_arrays(buffer::Primitive::Triangles, slice)
+                    .unwrap();
+            })
+            .unwrap();
+    }
+}
@@ -26,7 +26,7 @@
 //!     LutInputId::D0,
 //!     LutInput::NormalView,
 //!     LightLut::from_fn(|x| x.powf(10.0)),
-//! );
+//! ).unwrap();
 //! ```
 //!
 //! This places the LUT in `D0` (refer to [the diagram][hardware]) and connects the input wire as the dot product
@@ -38,15 +38,21 @@
 //!
 //! [hardware]: https://raw.githubusercontent.com/wwylele/misc-3ds-diagram/master/pica-pipeline.svg
 
-use std::{marker::PhantomPinned, mem::MaybeUninit, ops::Range, pin::Pin};
+use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin, ptr};
 
 use pin_array::PinArray;
 
+#[cfg(test)]
+use self::lut::{pack_lut, LUT_BUF_SZ};
+pub use self::lut::{LightLut, LightLutDistAtten, LUT_BYTES_LEN};
 use crate::{
+    color::Color,
     material::Material,
     math::{FVec3, FVec4},
 };
 
+mod lut;
+
 /// Index for one of the 8 hardware lights in the GPU pipeline
 ///
 /// Usually you don't want to construct one of these directly but use [`LightEnv::create_light`]
@@ -70,6 +76,33 @@ impl LightIndex {
     }
 }
 
+impl TryFrom<usize> for LightIndex {
+    type Error = crate::Error;
+
+    /// Create a `LightIndex` like [`LightIndex::new`], but returning
+    /// [`Error::InvalidLightIndex`](crate::Error::InvalidLightIndex) instead of
+    /// panicking if `idx` is out of range.
+    fn try_from(idx: usize) -> crate::Result<Self> {
+        if idx < NB_LIGHTS {
+            Ok(Self(idx as u8))
+        } else {
+            Err(crate::Error::InvalidLightIndex(idx))
+        }
+    }
+}
+
+impl std::fmt::Debug for LightIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::fmt::Display for LightIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Light#{}", self.0)
+    }
+}
+
 #[derive(Default)]
 struct LightLutStorage {
     spot: Option<LightLut>,
@@ -135,6 +168,24 @@ impl LightEnv {
         }
     }
 
+    /// Set the ambient color of this environment, which is added (along with
+    /// the material's emission color) to every lit fragment regardless of the
+    /// lights. This replaces the [`Material::ambient`] color set with
+    /// [`LightEnv::set_material`], keeping the other material colors.
+    ///
+    /// The PICA200 has a single ambient register, which `citro3d` computes from
+    /// the material of the bound environment. So there is no ambient separate
+    /// from the lighting environment, see [`Instance::set_ambient`](crate::Instance::set_ambient).
+    #[doc(alias = "C3D_LightEnvMaterial")]
+    pub fn set_ambient(self: Pin<&mut Self>, color: Color) {
+        let mut raw = self.as_raw().material;
+        raw.ambient = color.to_parts_bgr();
+        // Safety: see `set_material`
+        unsafe {
+            citro3d_sys::C3D_LightEnvMaterial(self.as_raw_mut() as *mut _, &raw);
+        }
+    }
+
     pub fn lights(&self) -> &LightArray {
         &self.lights
     }
@@ -149,13 +200,74 @@ impl LightEnv {
             .unwrap()
             .as_pin_mut()
     }
-    pub fn create_light(mut self: Pin<&mut Self>) -> Option<LightIndex> {
+    /// Get mutable references to two different lights at once, similar to
+    /// [`slice::split_at_mut`].
+    ///
+    /// Returns `None` if `a == b`, or if either light has not been created.
+    pub fn lights_mut_pair(
+        self: Pin<&mut Self>,
+        a: LightIndex,
+        b: LightIndex,
+    ) -> Option<(Pin<&mut Light>, Pin<&mut Light>)> {
+        let (a, b) = get_pin_pair(self.lights_mut(), a.as_usize(), b.as_usize())?;
+        Some((a.as_pin_mut()?, b.as_pin_mut()?))
+    }
+    /// Enable or disable every light that has been created, e.g. to switch
+    /// between lit and unlit rendering. Slots without a light are left alone.
+    #[doc(alias = "C3D_LightEnable")]
+    pub fn set_all_enabled(self: Pin<&mut Self>, enabled: bool) {
+        let mut lights = self.lights_mut();
+        for i in 0..NB_LIGHTS {
+            if let Some(light) = lights.as_mut().get_pin(i).unwrap().as_pin_mut() {
+                light.set_enabled(enabled);
+            }
+        }
+    }
+
+    /// The number of LUTs connected to the environment and its lights, which
+    /// `citro3d` may upload before a draw.
+    pub(crate) fn lut_count(&self) -> usize {
+        let light_luts = self.lights().iter().flatten().map(|light| {
+            usize::from(light.spot.is_some()) + usize::from(light.diffuse_atten.is_some())
+        });
+        self.luts.iter().flatten().count() + light_luts.sum::<usize>()
+    }
+
+    /// Get the number of lights which can still be created with
+    /// [`LightEnv::create_light`], out of the 8 hardware lights.
+    pub fn free_slots(&self) -> usize {
+        self.lights().iter().filter(|l| l.is_none()).count()
+    }
+
+    /// Whether all 8 hardware lights have been created, i.e. whether
+    /// [`LightEnv::create_light`] would return `None`.
+    pub fn is_full(&self) -> bool {
+        self.free_slots() == 0
+    }
+
+    /// Create a new light in the first free slot, returning its index. See
+    /// [`LightEnv::try_create_light`] for a version returning an error.
+    ///
+    /// Returns `None` if all 8 hardware lights are already in use.
+    pub fn create_light(self: Pin<&mut Self>) -> Option<LightIndex> {
+        self.try_create_light().ok()
+    }
+
+    /// Create a new light in the first free slot, returning its index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TooManyLights`](crate::Error::TooManyLights) if all 8
+    /// hardware lights are already in use, see [`LightEnv::free_slots`].
+    #[doc(alias = "C3D_LightInit")]
+    pub fn try_create_light(mut self: Pin<&mut Self>) -> crate::Result<LightIndex> {
         let idx = self
             .lights()
             .iter()
             .enumerate()
             .find(|(_, n)| n.is_none())
-            .map(|(n, _)| n)?;
+            .map(|(n, _)| n)
+            .ok_or(crate::Error::TooManyLights)?;
 
         self.as_mut()
             .lights_mut()
@@ -179,8 +291,49 @@ impl LightEnv {
             r as usize, idx,
             "citro3d chose a different light to us? this shouldn't be possible"
         );
-        Some(LightIndex::new(idx))
+        Ok(LightIndex::new(idx))
     }
+    /// Set up classic three-point lighting: a bright white key light, a dimmer
+    /// cool fill light to soften the key light's shadows, and a back light to
+    /// separate objects from the background, at the given positions. A Phong
+    /// specular LUT is connected to [`LightLutId::D0`], so highlights show up
+    /// on materials with a specular color.
+    ///
+    /// Returns the indices of the `[key, fill, back]` lights, which can be
+    /// adjusted further with [`LightEnv::light_mut`], or `None` (without
+    /// creating any lights) if fewer than 3 light slots are free.
+    pub fn setup_three_point(
+        mut self: Pin<&mut Self>,
+        key: FVec3,
+        fill: FVec3,
+        back: FVec3,
+    ) -> Option<[LightIndex; 3]> {
+        if self.free_slots() < 3 {
+            return None;
+        }
+
+        let rig = [
+            (key, Color::new(1.0, 1.0, 1.0)),
+            (fill, Color::new(0.35, 0.4, 0.5)),
+            (back, Color::new(0.6, 0.6, 0.6)),
+        ];
+        let mut indices = [LightIndex::new(0); 3];
+        for (index, (position, color)) in indices.iter_mut().zip(rig) {
+            *index = self.as_mut().create_light()?;
+            let mut light = self.as_mut().light_mut(*index)?;
+            light.as_mut().set_position(position);
+            light.set_light_color(color);
+        }
+
+        self.connect_lut(
+            LightLutId::D0,
+            LutInput::NormalHalf,
+            LightLut::from_fn(|x| x.powf(20.0), false),
+        )
+        .ok()?;
+        Some(indices)
+    }
+
     fn lut_id_to_index(id: LightLutId) -> Option<usize> {
         match id {
             LightLutId::D0 => Some(0),
@@ -218,23 +371,106 @@ impl LightEnv {
         }
         lut
     }
-    pub fn connect_lut(mut self: Pin<&mut Self>, id: LightLutId, input: LutInput, data: LightLut) {
-        let idx = Self::lut_id_to_index(id);
+    /// Connect a LUT to one of the slots shared by all lights.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PerLightLut`](crate::Error::PerLightLut) for
+    /// [`LightLutId::SpotLightAttenuation`] and [`LightLutId::DistanceAttenuation`],
+    /// which are stored on individual lights. Use [`LightEnv::connect_light_lut`]
+    /// for those instead.
+    pub fn connect_lut(
+        mut self: Pin<&mut Self>,
+        id: LightLutId,
+        input: LutInput,
+        data: LightLut,
+    ) -> crate::Result<()> {
+        let idx = Self::lut_id_to_index(id).ok_or(crate::Error::PerLightLut(id))?;
         let (raw, lut) = unsafe {
             // this is needed to do structural borrowing as otherwise
             // the compiler rejects the reborrow needed with the pin
             let me = self.as_mut().get_unchecked_mut();
-            let lut = idx.map(|i| me.luts[i].insert(data));
+            let lut = me.luts[idx].insert(data);
             let raw = &mut me.raw;
-            let lut = match lut {
-                Some(l) => (&mut l.0) as *mut _,
-                None => core::ptr::null_mut(),
-            };
-            (raw, lut)
+            (raw, (&mut lut.0) as *mut _)
         };
         unsafe {
             citro3d_sys::C3D_LightEnvLut(raw, id as u8, input as u8, false, lut);
         }
+        Ok(())
+    }
+    /// Replace the data of a LUT already connected with [`LightEnv::connect_lut`],
+    /// keeping its input. This reuses the stored LUT instead of connecting the
+    /// slot again, e.g. to animate a material's specular sharpness every frame.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::PerLightLut`](crate::Error::PerLightLut) for the per-light
+    ///   [`LightLutId::SpotLightAttenuation`] and [`LightLutId::DistanceAttenuation`]
+    ///   slots, see [`Light::set_spotlight_lut`] and [`Light::set_distance_attenutation`]
+    /// * [`Error::NotFound`](crate::Error::NotFound) if no LUT is connected to `id`
+    pub fn update_lut(
+        mut self: Pin<&mut Self>,
+        id: LightLutId,
+        data: LightLut,
+    ) -> crate::Result<()> {
+        let idx = Self::lut_id_to_index(id).ok_or(crate::Error::PerLightLut(id))?;
+        // SAFETY: the stored LUT is overwritten in place, so the pointer to it
+        // held by `raw` stays valid
+        let me = unsafe { self.as_mut().get_unchecked_mut() };
+        let lut = me.luts[idx].as_mut().ok_or(crate::Error::NotFound)?;
+        *lut = data;
+        // Mark the LUT as dirty so citro3d uploads it again, like `C3D_LightEnvLut`
+        // does. See C3DF_LightEnv_LutDirty in citro3d's internal light header.
+        me.raw.flags |= 1 << (26 + idx);
+        Ok(())
+    }
+
+    /// Connect a LUT to any slot, including the per-light
+    /// [`LightLutId::SpotLightAttenuation`] and [`LightLutId::DistanceAttenuation`]
+    /// slots, which [`LightEnv::connect_lut`] can't set.
+    ///
+    /// The per-light slots only affect the light at `idx`. All other slots are
+    /// shared by every light, so for those this is the same as
+    /// [`LightEnv::connect_lut`] and `idx` is only checked. Distance attenuation
+    /// ignores `input` and uses `data` for distances from 0 to 1, use
+    /// [`Light::set_distance_attenutation`] for other ranges.
+    ///
+    /// Returns `idx` on success, i.e. the light affected by per-light slots.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`](crate::Error::NotFound) if there is no light at `idx`.
+    pub fn connect_light_lut(
+        mut self: Pin<&mut Self>,
+        idx: LightIndex,
+        id: LightLutId,
+        input: LutInput,
+        data: LightLut,
+    ) -> crate::Result<LightIndex> {
+        let light = self.as_mut().light_mut(idx).ok_or(crate::Error::NotFound)?;
+
+        match id {
+            LightLutId::SpotLightAttenuation => {
+                light.set_spotlight_lut(Some(data));
+                // The spotlight LUT input is shared by all lights, so this
+                // only selects the input and doesn't store a LUT
+                unsafe {
+                    citro3d_sys::C3D_LightEnvLut(
+                        self.as_raw_mut(),
+                        id as u8,
+                        input as u8,
+                        false,
+                        core::ptr::null_mut(),
+                    );
+                }
+            }
+            LightLutId::DistanceAttenuation => {
+                light.set_distance_attenutation(Some(LightLutDistAtten::from_lut(0.0..1.0, data)));
+            }
+            _ => self.connect_lut(id, input, data)?,
+        }
+        Ok(idx)
     }
     pub fn set_fresnel(mut self: Pin<&mut Self>, sel: FresnelSelector) {
         unsafe { citro3d_sys::C3D_LightEnvFresnel(self.as_raw_mut(), sel as _) }
@@ -246,6 +482,23 @@ impl LightEnv {
         }
     }
 
+    /// Make this the active light environment for subsequent draw calls. Only
+    /// one environment is active at a time, so this replaces any previously
+    /// bound environment.
+    ///
+    /// Prefer [`Instance::set_light_env`](crate::Instance::set_light_env),
+    /// which keeps the environment alive for as long as it is bound.
+    ///
+    /// # Safety
+    ///
+    /// `citro3d` keeps a pointer to the bound environment, which it reads and
+    /// writes during draw calls. The environment must not be moved, dropped or
+    /// accessed through other references until a different environment is bound.
+    #[doc(alias = "C3D_LightEnvBind")]
+    pub unsafe fn bind(self: Pin<&mut Self>) {
+        citro3d_sys::C3D_LightEnvBind(self.as_raw_mut());
+    }
+
     pub fn as_raw(&self) -> &citro3d_sys::C3D_LightEnv {
         &self.raw
     }
@@ -282,8 +535,42 @@ impl Light {
         let mut p = FVec4::new(p.x(), p.y(), p.z(), 1.0);
         unsafe { citro3d_sys::C3D_LightPosition(self.as_raw_mut(), &mut p.0) }
     }
+    /// Set the color of the light from RGB components in the range `0.0..=1.0`.
+    /// See also [`Light::set_light_color`].
     pub fn set_color(self: Pin<&mut Self>, r: f32, g: f32, b: f32) {
-        unsafe { citro3d_sys::C3D_LightColor(self.as_raw_mut(), r, g, b) }
+        self.set_light_color(Color::new(r, g, b));
+    }
+    /// Set the color of the light. The alpha component is ignored.
+    ///
+    /// The GPU has separate ambient, diffuse and specular colors for each
+    /// light, which `citro3d` multiplies with the matching colors of the
+    /// environment's [`Material`](crate::material::Material) (which describes
+    /// the surface) before uploading them. This sets all of the light's colors
+    /// at once; use [`Light::set_specular_color`] afterwards for different
+    /// specular highlights.
+    #[doc(alias = "C3D_LightColor")]
+    pub fn set_light_color(self: Pin<&mut Self>, color: Color) {
+        unsafe { citro3d_sys::C3D_LightColor(self.as_raw_mut(), color.r, color.g, color.b) }
+    }
+    /// Set the color of the specular highlights of this light, separately from
+    /// the diffuse color set with [`Light::set_light_color`]. This is used for
+    /// both the `specular0` and `specular1` colors of the material. The alpha
+    /// component is ignored, and the others are clamped to `0.0..=1.0` like
+    /// the diffuse color.
+    ///
+    /// Setting the light color afterwards overrides this again.
+    pub fn set_specular_color(self: Pin<&mut Self>, color: Color) {
+        let color = Color::new(
+            color.r.clamp(0.0, 1.0),
+            color.g.clamp(0.0, 1.0),
+            color.b.clamp(0.0, 1.0),
+        );
+        let raw = self.as_raw_mut();
+        raw.specular0 = color.to_parts_bgr();
+        raw.specular1 = color.to_parts_bgr();
+        // C3DF_Light_MatDirty, so the colors are multiplied with the material
+        // and uploaded again before the next draw
+        raw.flags |= 1 << 2;
     }
     #[doc(alias = "C3D_LightEnable")]
     pub fn set_enabled(self: Pin<&mut Self>, enabled: bool) {
@@ -293,6 +580,32 @@ impl Light {
     pub fn set_shadow(self: Pin<&mut Self>, shadow: bool) {
         unsafe { citro3d_sys::C3D_LightShadowEnable(self.as_raw_mut(), shadow) }
     }
+    /// Set the spotlight attenuation LUT for this light, or disable the spotlight
+    /// with `None`. See also [`LightEnv::connect_light_lut`], which also selects
+    /// the LUT input.
+    #[doc(alias = "C3D_LightSpotLut")]
+    pub fn set_spotlight_lut(mut self: Pin<&mut Self>, lut: Option<LightLut>) {
+        // See `set_distance_attenutation` for why the borrows are split up like this
+        {
+            let me = unsafe { self.as_mut().get_unchecked_mut() };
+            me.spot = lut;
+        }
+        let (raw, c_lut) = {
+            let me = unsafe { self.as_mut().get_unchecked_mut() };
+            let raw = &mut me.raw;
+            let c_lut = me.spot.as_mut().map(|l| &mut l.0);
+            (raw, c_lut)
+        };
+        unsafe {
+            citro3d_sys::C3D_LightSpotLut(
+                raw,
+                match c_lut {
+                    Some(l) => l,
+                    None => std::ptr::null_mut(),
+                },
+            );
+        }
+    }
     pub fn set_distance_attenutation(mut self: Pin<&mut Self>, lut: Option<LightLutDistAtten>) {
         {
             let me = unsafe { self.as_mut().get_unchecked_mut() };
@@ -327,99 +640,6 @@ unsafe impl Sync for Light {}
 unsafe impl Send for LightEnv {}
 unsafe impl Sync for LightEnv {}
 
-/// Lookup table for light data
-///
-/// For more refer to the module documentation
-#[derive(Clone, Copy, Debug)]
-#[repr(transparent)]
-pub struct LightLut(citro3d_sys::C3D_LightLut);
-
-impl PartialEq for LightLut {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.data == other.0.data
-    }
-}
-impl Eq for LightLut {}
-
-impl std::hash::Hash for LightLut {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.data.hash(state);
-    }
-}
-
-#[cfg(test)]
-extern "C" fn c_powf(a: f32, b: f32) -> f32 {
-    a.powf(b)
-}
-
-type LutArray = [u32; 256];
-const LUT_BUF_SZ: usize = 512;
-
-impl LightLut {
-    /// Create a LUT by memoizing a function
-    pub fn from_fn(mut f: impl FnMut(f32) -> f32, negative: bool) -> Self {
-        let base: i32 = 128;
-        let diff = if negative { 0 } else { base };
-        let min = -128 + diff;
-        let max = base + diff;
-        assert_eq!(min.abs_diff(max), 2 * base as u32);
-        let mut data = [0.0f32; LUT_BUF_SZ];
-        for i in min..=max {
-            let x = i as f32 / max as f32;
-            let v = f(x);
-            let idx = if negative { i & 0xFF } else { i } as usize;
-            if i < max {
-                data[idx] = v;
-            }
-            if i > min {
-                data[idx + 255] = v - data[idx - 1];
-            }
-        }
-        let lut = unsafe {
-            let mut lut = MaybeUninit::zeroed();
-            citro3d_sys::LightLut_FromArray(lut.as_mut_ptr(), data.as_mut_ptr());
-            lut.assume_init()
-        };
-        Self(lut)
-    }
-
-    /// Get a reference to the underlying data
-    pub fn data(&self) -> &LutArray {
-        &self.0.data
-    }
-
-    /// Get a mutable reference to the underlying data
-    pub fn data_mut(&mut self) -> &mut LutArray {
-        &mut self.0.data
-    }
-
-    #[cfg(test)]
-    fn phong_citro3d(shininess: f32) -> Self {
-        let lut = unsafe {
-            let mut lut = MaybeUninit::uninit();
-            citro3d_sys::LightLut_FromFunc(lut.as_mut_ptr(), Some(c_powf), shininess, false);
-            lut.assume_init()
-        };
-        Self(lut)
-    }
-}
-
-pub struct LightLutDistAtten {
-    raw: citro3d_sys::C3D_LightLutDA,
-}
-
-impl LightLutDistAtten {
-    pub fn new(range: Range<f32>, mut f: impl FnMut(f32) -> f32) -> Self {
-        let mut raw: citro3d_sys::C3D_LightLutDA = unsafe { MaybeUninit::zeroed().assume_init() };
-        let dist = range.end - range.start;
-        raw.scale = 1.0 / dist;
-        raw.bias = -range.start * raw.scale;
-        let lut = LightLut::from_fn(|x| f(range.start + dist * x), false);
-        raw.lut = citro3d_sys::C3D_LightLut { data: *lut.data() };
-        Self { raw }
-    }
-}
-
 /// This is used to decide what the input should be to a [`LightLut`]
 #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
 #[repr(u8)]
@@ -475,9 +695,76 @@ pub enum BumpMode {
 
 type LightArray = PinArray<Option<Light>, NB_LIGHTS>;
 
+/// Get pinned mutable references to the elements of `array` at `a` and `b`.
+/// Returns `None` if `a == b` or either is out of bounds.
+fn get_pin_pair<T, const N: usize>(
+    array: Pin<&mut PinArray<T, N>>,
+    a: usize,
+    b: usize,
+) -> Option<(Pin<&mut T>, Pin<&mut T>)> {
+    if a == b {
+        return None;
+    }
+
+    // SAFETY: nothing is moved out of the array, and the elements are pinned
+    // again below
+    let array = unsafe { array.get_unchecked_mut() };
+
+    // Both references are derived from a single pointer to the whole array,
+    // since reborrowing the array for the second one would invalidate the first
+    let offset = |idx: usize| {
+        let elem = ptr::from_ref(array.get(idx)?).cast::<u8>();
+        // SAFETY: both pointers are into the same array
+        Some(unsafe { elem.offset_from(ptr::from_ref(&*array).cast::<u8>()) } as usize)
+    };
+    let (offset_a, offset_b) = (offset(a)?, offset(b)?);
+    let base = ptr::from_mut(array);
+
+    // SAFETY: the offsets are of two different elements in the array, so the
+    // references are disjoint
+    unsafe {
+        Some((
+            Pin::new_unchecked(&mut *base.byte_add(offset_a).cast::<T>()),
+            Pin::new_unchecked(&mut *base.byte_add(offset_b).cast::<T>()),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::LightLut;
+    use std::pin::Pin;
+
+    use pin_array::PinArray;
+
+    use super::{get_pin_pair, LightEnv, LightIndex, LightLut, LightLutId, LutInput};
+    use crate::color::Color;
+
+    extern "C" fn c_powf(a: f32, b: f32) -> f32 {
+        a.powf(b)
+    }
+
+    impl LightLut {
+        fn phong_citro3d(shininess: f32) -> Self {
+            let lut = unsafe {
+                let mut lut = std::mem::MaybeUninit::uninit();
+                citro3d_sys::LightLut_FromFunc(lut.as_mut_ptr(), Some(c_powf), shininess, false);
+                lut.assume_init()
+            };
+            Self(lut)
+        }
+    }
+
+    #[test]
+    fn light_index_conversions() {
+        let idx = LightIndex::try_from(3).unwrap();
+        assert_eq!(idx.as_usize(), 3);
+        assert_eq!(format!("{idx}"), "Light#3");
+        assert_eq!(format!("{idx:?}"), "Light#3");
+        assert!(matches!(
+            LightIndex::try_from(8),
+            Err(crate::Error::InvalidLightIndex(8))
+        ));
+    }
 
     #[test]
     fn lut_data_phong_matches_for_own_and_citro3d() {
@@ -485,4 +772,77 @@ mod tests {
         let rs = LightLut::from_fn(|i| i.powf(30.0), false);
         assert_eq!(c3d, rs);
     }
+
+    #[test]
+    fn update_lut_in_place() {
+        let mut env = Box::pin(LightEnv::new());
+        assert!(matches!(
+            env.as_mut()
+                .update_lut(LightLutId::D1, LightLut::from_fn(|x| x, false)),
+            Err(crate::Error::NotFound)
+        ));
+
+        env.as_mut()
+            .connect_lut(
+                LightLutId::D1,
+                LutInput::NormalView,
+                LightLut::from_fn(|x| x, false),
+            )
+            .unwrap();
+        let ptr = env.as_raw().luts[1];
+        env.as_mut()
+            .update_lut(LightLutId::D1, LightLut::phong_citro3d(30.0))
+            .unwrap();
+
+        assert_eq!(env.luts[1], Some(LightLut::phong_citro3d(30.0)));
+        assert_eq!(env.as_raw().luts[1], ptr);
+        assert_ne!(env.as_raw().flags & (1 << 27), 0);
+    }
+
+    #[test]
+    fn specular_color_is_separate() {
+        let mut env = Box::pin(LightEnv::new());
+        let idx = env.as_mut().create_light().unwrap();
+        let mut light = env.as_mut().light_mut(idx).unwrap();
+        light.as_mut().set_light_color(Color::new(1.0, 0.5, 0.25));
+        light.as_mut().set_specular_color(Color::new(0.0, 0.0, 1.0));
+
+        let raw = light.as_raw();
+        assert_eq!(raw.diffuse, [0.25, 0.5, 1.0]);
+        assert_eq!(raw.specular0, [1.0, 0.0, 0.0]);
+        assert_eq!(raw.specular1, [1.0, 0.0, 0.0]);
+        assert_ne!(raw.flags & (1 << 2), 0);
+
+        light
+            .as_mut()
+            .set_specular_color(Color::new(2.0, -1.0, 0.5));
+        assert_eq!(light.as_raw().specular0, [0.5, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn pack_lut_matches_citro3d() {
+        let mut data: [f32; super::LUT_BUF_SZ] =
+            std::array::from_fn(|i| (i as f32 / 128.0 - 1.5).sin() * 1.5);
+        let ours = super::pack_lut(&data);
+
+        let mut lut = unsafe { std::mem::MaybeUninit::zeroed().assume_init() };
+        unsafe { citro3d_sys::LightLut_FromArray(&mut lut, data.as_mut_ptr()) };
+        assert_eq!(LightLut(lut).data(), &ours);
+    }
+
+    /// Doesn't call into `citro3d`, so it can also be run under Miri to check
+    /// the aliasing of the two references.
+    #[test]
+    fn pin_pair_is_disjoint() {
+        let mut array: Pin<Box<PinArray<Option<u32>, 4>>> = Box::pin(PinArray::default());
+        let (mut a, mut b) = get_pin_pair(array.as_mut(), 3, 1).unwrap();
+        *a = Some(3);
+        *b = Some(1);
+        *a = a.map(|n| n * 10);
+        assert_eq!(array.get(3), Some(&Some(30)));
+        assert_eq!(array.get(1), Some(&Some(1)));
+
+        assert!(get_pin_pair(array.as_mut(), 2, 2).is_none());
+        assert!(get_pin_pair(array.as_mut(), 0, 4).is_none());
+    }
 }
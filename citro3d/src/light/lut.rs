@@ -0,0 +1,261 @@
+//! Light lookup tables, which are computed on the CPU.
+
+use std::mem::MaybeUninit;
+use std::ops::Range;
+
+#[cfg(feature = "mock")]
+use crate::mock::citro3d_sys;
+
+/// Lookup table for light data
+///
+/// For more refer to the module documentation
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct LightLut(pub(super) citro3d_sys::C3D_LightLut);
+
+impl PartialEq for LightLut {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.data == other.0.data
+    }
+}
+impl Eq for LightLut {}
+
+impl std::hash::Hash for LightLut {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.data.hash(state);
+    }
+}
+
+type LutArray = [u32; 256];
+pub(super) const LUT_BUF_SZ: usize = 512;
+
+/// Magic bytes and format version at the start of serialized [`LightLut`] data.
+const LUT_MAGIC: [u8; 8] = *b"C3DLUT\x01\x00";
+
+/// The length of [`LightLut::to_bytes`]'s output.
+pub const LUT_BYTES_LEN: usize = LUT_MAGIC.len() + std::mem::size_of::<LutArray>();
+
+impl LightLut {
+    /// Create a LUT by memoizing a function
+    pub fn from_fn(f: impl FnMut(f32) -> f32, negative: bool) -> Self {
+        let mut lut = Self(unsafe { MaybeUninit::zeroed().assume_init() });
+        lut.from_fn_into(f, negative);
+        lut
+    }
+
+    /// Recompute the LUT from a function in place, producing the same data as
+    /// [`LightLut::from_fn`]. This avoids building a new LUT each time, e.g. when
+    /// animating a LUT every frame.
+    pub fn from_fn_into(&mut self, mut f: impl FnMut(f32) -> f32, negative: bool) {
+        let max: i32 = if negative { 128 } else { 256 };
+        let min = max - 256;
+        let index = |i: i32| (if negative { i & 0xFF } else { i }) as usize;
+
+        // The first half holds the samples, the second half the difference from
+        // each sample to the next one
+        let mut data = [0.0f32; LUT_BUF_SZ];
+        let mut prev = f(min as f32 / max as f32);
+        data[index(min)] = prev;
+        for i in min + 1..=max {
+            let v = f(i as f32 / max as f32);
+            let idx = index(i);
+            if i < max {
+                data[idx] = v;
+            }
+            data[idx + 255] = v - prev;
+            prev = v;
+        }
+        self.pack(&mut data);
+    }
+
+    /// Create a LUT from 256 precomputed samples, e.g. a table exported by an
+    /// offline tool, in the order the GPU stores them: for inputs from `0.0`
+    /// to `1.0`, or with `negative`, for inputs from `0.0` to `1.0` followed by
+    /// inputs from `-1.0` to `0.0`.
+    ///
+    /// The differences between neighbouring samples are calculated from the
+    /// samples themselves. There is no sample for an input of exactly `1.0`,
+    /// so the last difference is assumed to be the same as the one before it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSize`](crate::Error::InvalidSize) if `samples`
+    /// does not have exactly 256 entries.
+    #[doc(alias = "LightLut_FromArray")]
+    pub fn from_samples(samples: &[f32], negative: bool) -> crate::Result<Self> {
+        let samples: &[f32; 256] = samples.try_into().map_err(|_| crate::Error::InvalidSize)?;
+
+        // The entry holding the sample for the next larger input, if any
+        let next = |i: usize| match (negative, i) {
+            (false, 255) | (true, 127) => None,
+            (true, 255) => Some(0),
+            _ => Some(i + 1),
+        };
+
+        let mut data = [0.0f32; LUT_BUF_SZ];
+        let (values, diffs) = data.split_at_mut(256);
+        values.copy_from_slice(samples);
+        for (i, diff) in diffs.iter_mut().enumerate() {
+            *diff = match next(i) {
+                Some(next) => samples[next] - samples[i],
+                None => samples[i] - samples[i - 1],
+            };
+        }
+
+        let mut lut = Self(citro3d_sys::C3D_LightLut { data: [0; 256] });
+        lut.pack(&mut data);
+        Ok(lut)
+    }
+
+    /// Pack samples followed by their differences into the GPU's format.
+    fn pack(&mut self, data: &mut [f32; LUT_BUF_SZ]) {
+        unsafe { citro3d_sys::LightLut_FromArray(&mut self.0, data.as_mut_ptr()) };
+    }
+
+    /// Serialize the LUT, e.g. to bake it into an asset at build time instead of
+    /// running [`LightLut::from_fn`] on the device. The data starts with a magic
+    /// header and version, followed by the LUT entries as little-endian `u32`s.
+    pub fn to_bytes(&self) -> [u8; LUT_BYTES_LEN] {
+        let mut bytes = [0; LUT_BYTES_LEN];
+        let (header, data) = bytes.split_at_mut(LUT_MAGIC.len());
+        header.copy_from_slice(&LUT_MAGIC);
+        for (chunk, entry) in data.chunks_exact_mut(4).zip(self.data()) {
+            chunk.copy_from_slice(&entry.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a LUT previously serialized with [`LightLut::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if `bytes` is not
+    ///   [`LUT_BYTES_LEN`] bytes long
+    /// * [`Error::InvalidData`](crate::Error::InvalidData) if the header doesn't
+    ///   match, e.g. because the data was serialized by an incompatible version
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        if bytes.len() != LUT_BYTES_LEN {
+            return Err(crate::Error::InvalidSize);
+        }
+        let (header, data) = bytes.split_at(LUT_MAGIC.len());
+        if header != LUT_MAGIC {
+            return Err(crate::Error::InvalidData);
+        }
+
+        let mut lut = Self(citro3d_sys::C3D_LightLut { data: [0; 256] });
+        for (entry, chunk) in lut.data_mut().iter_mut().zip(data.chunks_exact(4)) {
+            *entry = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Ok(lut)
+    }
+
+    /// Get a reference to the underlying data
+    pub fn data(&self) -> &LutArray {
+        &self.0.data
+    }
+
+    /// Get a mutable reference to the underlying data
+    pub fn data_mut(&mut self) -> &mut LutArray {
+        &mut self.0.data
+    }
+}
+
+/// A Rust port of `LightLut_FromArray`, packing each sample into the low 12 bits
+/// of an entry as unsigned 0.12 fixed point, and the difference to the next
+/// sample into the high 12 bits as sign + 0.11 fixed point.
+#[cfg(any(test, feature = "mock"))]
+pub(super) fn pack_lut(data: &[f32; LUT_BUF_SZ]) -> LutArray {
+    let (samples, diffs) = data.split_at(256);
+    std::array::from_fn(|i| {
+        let (sample, diff) = (samples[i], diffs[i]);
+        let val = if sample > 0.0 {
+            let sample = sample * 4096.0;
+            if sample < 4096.0 {
+                sample as u32
+            } else {
+                0xFFF
+            }
+        } else {
+            0
+        };
+        let val2 = if diff != 0.0 {
+            let sign = if diff < 0.0 { 0x800 } else { 0 };
+            let diff = diff.abs() * 2048.0;
+            sign | if diff < 2048.0 { diff as u32 } else { 0x7FF }
+        } else {
+            0
+        };
+        val | (val2 << 12)
+    })
+}
+
+pub struct LightLutDistAtten {
+    // Only read when binding it to a light
+    #[cfg_attr(feature = "mock", allow(dead_code))]
+    pub(super) raw: citro3d_sys::C3D_LightLutDA,
+}
+
+impl LightLutDistAtten {
+    pub fn new(range: Range<f32>, mut f: impl FnMut(f32) -> f32) -> Self {
+        let mut raw: citro3d_sys::C3D_LightLutDA = unsafe { MaybeUninit::zeroed().assume_init() };
+        let dist = range.end - range.start;
+        raw.scale = 1.0 / dist;
+        raw.bias = -range.start * raw.scale;
+        let lut = LightLut::from_fn(|x| f(range.start + dist * x), false);
+        raw.lut = citro3d_sys::C3D_LightLut { data: *lut.data() };
+        Self { raw }
+    }
+
+    /// Use an existing LUT for distance attenuation, where the LUT input from
+    /// 0 to 1 covers the distances in `range`.
+    pub fn from_lut(range: Range<f32>, lut: LightLut) -> Self {
+        let mut raw: citro3d_sys::C3D_LightLutDA = unsafe { MaybeUninit::zeroed().assume_init() };
+        raw.scale = 1.0 / (range.end - range.start);
+        raw.bias = -range.start * raw.scale;
+        raw.lut = lut.0;
+        Self { raw }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lut_from_fn_into_reuses_lut() {
+        let mut lut = LightLut::from_fn(|i| i.powf(10.0), false);
+        lut.from_fn_into(|i| i.powf(30.0), false);
+        assert_eq!(lut, LightLut::from_fn(|i| i.powf(30.0), false));
+    }
+
+    #[test]
+    fn lut_from_samples() {
+        let samples: Vec<_> = (0..256).map(|i| i as f32 / 256.0).collect();
+        assert_eq!(
+            LightLut::from_samples(&samples, false).unwrap(),
+            LightLut::from_fn(|x| x, false)
+        );
+        assert!(matches!(
+            LightLut::from_samples(&samples[1..], true),
+            Err(crate::Error::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn lut_bytes_round_trip() {
+        let lut = LightLut::from_fn(|i| i.powf(10.0), false);
+        let bytes = lut.to_bytes();
+        assert_eq!(LightLut::from_bytes(&bytes).unwrap(), lut);
+
+        let mut bad_header = bytes;
+        bad_header[6] = 0xFF;
+        assert!(matches!(
+            LightLut::from_bytes(&bad_header),
+            Err(crate::Error::InvalidData)
+        ));
+        assert!(matches!(
+            LightLut::from_bytes(&bytes[1..]),
+            Err(crate::Error::InvalidSize)
+        ));
+    }
+}
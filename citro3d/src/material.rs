@@ -1,3 +1,5 @@
+pub use crate::color::Color;
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Material {
     pub ambient: Option<Color>,
@@ -17,25 +19,3 @@ impl Material {
         }
     }
 }
-
-/// RGB color in linear space ([0, 1])
-#[derive(Debug, Default, Clone, Copy)]
-pub struct Color {
-    pub r: f32,
-    pub g: f32,
-    pub b: f32,
-}
-
-impl Color {
-    pub fn new(r: f32, g: f32, b: f32) -> Self {
-        Self { r, g, b }
-    }
-    /// Split into BGR ordered parts
-    ///
-    /// # Reason for existence
-    /// The C version of [`Material`] expects colours in BGR order (don't ask why it is beyond my comprehension)
-    /// so we have to reorder when converting
-    pub fn to_parts_bgr(self) -> [f32; 3] {
-        [self.b, self.g, self.r]
-    }
-}
@@ -3,11 +3,13 @@
 // TODO: bench FFI calls into `inline statics` generated by bindgen, vs
 // reimplementing some of those calls. Many of them are pretty trivial impls
 
+mod camera;
 mod fvec;
 mod matrix;
 mod ops;
 mod projection;
 
+pub use camera::Camera;
 pub use fvec::{FVec, FVec3, FVec4};
 pub use matrix::Matrix4;
 pub use projection::{
@@ -0,0 +1,225 @@
+use std::f32::consts::FRAC_PI_2;
+
+use super::{
+    AspectRatio, ClipPlanes, CoordinateOrientation, FVec3, Matrix4, Perspective, Projection,
+    StereoDisplacement,
+};
+
+/// How close the view direction can get to straight up or down, in radians.
+/// [`Matrix4::looking_at`] breaks down when looking along the up vector.
+const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+
+/// A perspective camera, looking from a position at a target with +Y up.
+///
+/// This is a convenience layer over [`Matrix4::looking_at`] and
+/// [`Projection::perspective`] which keeps the parameters in one place and
+/// supports orbit and first-person style movement. The resulting matrices are
+/// ready to bind as uniforms, and the projection is rotated for the 3DS
+/// screens like the default [`Projection`].
+///
+/// Angles for [`Camera::turn`] and [`Camera::orbit`] are in radians: positive
+/// yaw turns right and positive pitch looks up, in either
+/// [`CoordinateOrientation`].
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use citro3d::math::{Camera, FVec3, Matrix4};
+/// let mut camera = Camera::new(FVec3::new(0.0, 2.0, 5.0), FVec3::splat(0.0));
+/// camera.orbit(0.1, 0.0);
+///
+/// let view: Matrix4 = camera.view();
+/// let projection: Matrix4 = camera.projection();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    /// The position of the camera.
+    pub position: FVec3,
+    /// The point the camera looks at.
+    pub target: FVec3,
+    /// The vertical field of view, in radians.
+    pub vertical_fov_radians: f32,
+    /// The aspect ratio of the screen rendered to.
+    pub aspect_ratio: AspectRatio,
+    /// The near and far clip planes.
+    pub clip_planes: ClipPlanes,
+    /// The coordinate system used for both matrices.
+    pub coordinates: CoordinateOrientation,
+}
+
+impl Camera {
+    /// Create a camera at `position` looking at `target`, with a 45° field of
+    /// view for the top screen, clip planes at `0.1` and `100.0`, and the
+    /// default [`CoordinateOrientation`].
+    pub fn new(position: FVec3, target: FVec3) -> Self {
+        Self {
+            position,
+            target,
+            vertical_fov_radians: std::f32::consts::FRAC_PI_4,
+            aspect_ratio: AspectRatio::TopScreen,
+            clip_planes: ClipPlanes {
+                near: 0.1,
+                far: 100.0,
+            },
+            coordinates: CoordinateOrientation::default(),
+        }
+    }
+
+    /// Get the view matrix, transforming world space into camera space.
+    pub fn view(&self) -> Matrix4 {
+        Matrix4::looking_at(
+            self.position,
+            self.target,
+            FVec3::new(0.0, 1.0, 0.0),
+            self.coordinates,
+        )
+    }
+
+    /// Get the projection matrix, transforming camera space into clip space.
+    pub fn projection(&self) -> Matrix4 {
+        self.perspective().into()
+    }
+
+    /// Get the left and right eye projection matrices for stereoscopic 3D. See
+    /// [`StereoDisplacement::new`] for the parameters.
+    pub fn stereo_projections(
+        &self,
+        interocular_distance: f32,
+        screen_depth: f32,
+    ) -> (Matrix4, Matrix4) {
+        let (left, right) = StereoDisplacement::new(interocular_distance, screen_depth);
+        self.perspective().stereo_matrices(left, right)
+    }
+
+    /// Get the unit vector pointing from the position to the target.
+    pub fn forward(&self) -> FVec3 {
+        (self.target - self.position).normalize()
+    }
+
+    /// Get the unit vector pointing to the right of the view direction, in the
+    /// horizontal plane.
+    pub fn right(&self) -> FVec3 {
+        let up = FVec3::new(0.0, 1.0, 0.0);
+        let right = if self.coordinates.is_left_handed() {
+            up.cross(self.forward())
+        } else {
+            self.forward().cross(up)
+        };
+        right.normalize()
+    }
+
+    /// Rotate the view direction around the camera's position, like turning
+    /// the head in a first-person game. The distance to the target is kept.
+    /// Pitch stops just short of looking straight up or down.
+    pub fn turn(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.target - self.position;
+        self.target = self.position + self.rotate(offset, yaw, pitch);
+    }
+
+    /// Rotate the camera's position around the target, keeping it in view and
+    /// at the same distance. The angles turn the view direction the same way
+    /// as [`Camera::turn`].
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.target - self.position;
+        self.position = self.target - self.rotate(offset, yaw, pitch);
+    }
+
+    /// Move the camera towards the target by `amount`, without moving the
+    /// target. The camera stops just before reaching it.
+    pub fn zoom(&mut self, amount: f32) {
+        let distance = self.position.distance(self.target);
+        let distance = (distance - amount).max(self.clip_planes.near);
+        self.position = self.target - self.forward() * distance;
+    }
+
+    /// Move both the position and target relative to the view direction, like
+    /// walking in a first-person game: `forward` along the horizontal view
+    /// direction, `right` sideways and `up` along +Y.
+    pub fn move_by(&mut self, forward: f32, right: f32, up: f32) {
+        let right_dir = self.right();
+        let forward_dir = FVec3::new(0.0, 1.0, 0.0).cross(right_dir);
+        let forward_dir = if self.coordinates.is_left_handed() {
+            -forward_dir
+        } else {
+            forward_dir
+        };
+
+        let delta = forward_dir * forward + right_dir * right + FVec3::new(0.0, up, 0.0);
+        self.position = self.position + delta;
+        self.target = self.target + delta;
+    }
+
+    fn perspective(&self) -> Projection<Perspective> {
+        Projection::perspective(
+            self.vertical_fov_radians,
+            self.aspect_ratio,
+            self.clip_planes,
+        )
+        .coordinates(self.coordinates)
+    }
+
+    /// Rotate `offset` by `yaw` around +Y and `pitch` up or down, keeping its
+    /// length.
+    fn rotate(&self, offset: FVec3, yaw: f32, pitch: f32) -> FVec3 {
+        // With yaw measured from -Z towards +X, positive yaw turns right in
+        // right-handed coordinates and left in left-handed ones
+        let yaw = if self.coordinates.is_left_handed() {
+            -yaw
+        } else {
+            yaw
+        };
+
+        let distance = offset.magnitude();
+        let dir = offset / distance;
+        let current_pitch = dir.y().clamp(-1.0, 1.0).asin();
+        let current_yaw = dir.x().atan2(-dir.z());
+
+        let pitch = (current_pitch + pitch).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        let yaw = current_yaw + yaw;
+        FVec3::new(
+            pitch.cos() * yaw.sin(),
+            pitch.sin(),
+            -pitch.cos() * yaw.cos(),
+        ) * distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+
+    #[test]
+    fn turn_and_orbit() {
+        let mut camera = Camera::new(FVec3::splat(0.0), FVec3::new(0.0, 0.0, -2.0));
+        camera.turn(FRAC_PI_2, 0.0);
+        assert_abs_diff_eq!(camera.target, FVec3::new(2.0, 0.0, 0.0), epsilon = 1e-5);
+        assert_abs_diff_eq!(camera.right(), FVec3::new(0.0, 0.0, 1.0), epsilon = 1e-5);
+
+        camera.orbit(0.0, 10.0);
+        assert_abs_diff_eq!(camera.position.distance(camera.target), 2.0, epsilon = 1e-5);
+        assert!(camera.forward().y() > 0.99);
+
+        let mut camera = Camera::new(FVec3::splat(0.0), FVec3::new(0.0, 0.0, 1.0));
+        camera.coordinates = CoordinateOrientation::LeftHanded;
+        camera.turn(FRAC_PI_2, 0.0);
+        assert_abs_diff_eq!(camera.target, FVec3::new(1.0, 0.0, 0.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn move_and_zoom() {
+        let mut camera = Camera::new(FVec3::splat(0.0), FVec3::new(0.0, -1.0, -1.0));
+        camera.move_by(1.0, 2.0, 3.0);
+        assert_abs_diff_eq!(camera.position, FVec3::new(2.0, 3.0, -1.0), epsilon = 1e-5);
+        assert_abs_diff_eq!(camera.target, FVec3::new(2.0, 2.0, -2.0), epsilon = 1e-5);
+
+        camera.zoom(100.0);
+        assert_abs_diff_eq!(
+            camera.position.distance(camera.target),
+            camera.clip_planes.near,
+            epsilon = 1e-5
+        );
+    }
+}
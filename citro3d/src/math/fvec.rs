@@ -124,6 +124,7 @@ impl FVec4 {
     /// assert_abs_diff_eq!(v.magnitude(), 2.0);
     /// ```
     #[doc(alias = "FVec4_Magnitude")]
+    #[doc(alias = "length")]
     pub fn magnitude(self) -> f32 {
         unsafe { citro3d_sys::FVec4_Magnitude(self.0) }
     }
@@ -230,6 +231,7 @@ impl FVec3 {
     /// assert_abs_diff_eq!(v.magnitude(), 3.0);
     /// ```
     #[doc(alias = "FVec3_Magnitude")]
+    #[doc(alias = "length")]
     pub fn magnitude(self) -> f32 {
         unsafe { citro3d_sys::FVec3_Magnitude(self.0) }
     }
@@ -297,4 +299,59 @@ mod tests {
         let expected = [1.0, 2.0, 3.0];
         assert_abs_diff_eq!(&actual[..], &expected[..]);
     }
+
+    fn components3(v: FVec3) -> [f32; 3] {
+        [v.x(), v.y(), v.z()]
+    }
+
+    fn components4(v: FVec4) -> [f32; 4] {
+        [v.x(), v.y(), v.z(), v.w()]
+    }
+
+    #[test]
+    fn fvec3_matches_scalar() {
+        let (l, r) = ([1.5, -2.0, 0.25], [-3.0, 0.5, 4.0]);
+        let (vl, vr) = (FVec3::new(l[0], l[1], l[2]), FVec3::new(r[0], r[1], r[2]));
+
+        let dot: f32 = l.iter().zip(r).map(|(a, b)| a * b).sum();
+        assert_abs_diff_eq!(vl.dot(vr), dot);
+
+        let cross = [
+            l[1] * r[2] - l[2] * r[1],
+            l[2] * r[0] - l[0] * r[2],
+            l[0] * r[1] - l[1] * r[0],
+        ];
+        assert_abs_diff_eq!(&components3(vl.cross(vr))[..], &cross[..]);
+
+        let magnitude = l.iter().map(|a| a * a).sum::<f32>().sqrt();
+        assert_abs_diff_eq!(vl.magnitude(), magnitude);
+
+        let normalized = l.map(|a| a / magnitude);
+        assert_abs_diff_eq!(&components3(vl.normalize())[..], &normalized[..]);
+
+        let sum = [l[0] + r[0], l[1] + r[1], l[2] + r[2]];
+        assert_abs_diff_eq!(&components3(vl + vr)[..], &sum[..]);
+
+        let scaled = l.map(|a| a * 3.0);
+        assert_abs_diff_eq!(&components3(vl * 3.0)[..], &scaled[..]);
+    }
+
+    #[test]
+    fn fvec4_matches_scalar() {
+        let (l, r) = ([1.5, -2.0, 0.25, 2.0], [-3.0, 0.5, 4.0, 1.0]);
+        let vl = FVec4::new(l[0], l[1], l[2], l[3]);
+        let vr = FVec4::new(r[0], r[1], r[2], r[3]);
+
+        let dot: f32 = l.iter().zip(r).map(|(a, b)| a * b).sum();
+        assert_abs_diff_eq!(vl.dot(vr), dot);
+
+        let magnitude = l.iter().map(|a| a * a).sum::<f32>().sqrt();
+        assert_abs_diff_eq!(vl.magnitude(), magnitude);
+
+        let normalized = l.map(|a| a / magnitude);
+        assert_abs_diff_eq!(&components4(vl.normalize())[..], &normalized[..]);
+
+        let diff = [l[0] - r[0], l[1] - r[1], l[2] - r[2], l[3] - r[3]];
+        assert_abs_diff_eq!(&components4(vl - vr)[..], &diff[..]);
+    }
 }
@@ -0,0 +1,54 @@
+//! Stand-ins for the parts of `ctru-sys` and `citro3d-sys` used by the modules
+//! which are built with the `mock` feature, so they can be tested on a host
+//! without the 3DS libraries.
+
+/// Texture formats, see `GPU_TEXCOLOR` in `<3ds/gpu/enums.h>`.
+#[allow(non_camel_case_types)]
+pub(crate) mod ctru_sys {
+    pub type GPU_TEXCOLOR = u8;
+
+    pub const GPU_RGBA8: GPU_TEXCOLOR = 0x0;
+    pub const GPU_RGB8: GPU_TEXCOLOR = 0x1;
+    pub const GPU_RGBA5551: GPU_TEXCOLOR = 0x2;
+    pub const GPU_RGB565: GPU_TEXCOLOR = 0x3;
+    pub const GPU_RGBA4: GPU_TEXCOLOR = 0x4;
+    pub const GPU_LA8: GPU_TEXCOLOR = 0x5;
+    pub const GPU_HILO8: GPU_TEXCOLOR = 0x6;
+    pub const GPU_L8: GPU_TEXCOLOR = 0x7;
+    pub const GPU_A8: GPU_TEXCOLOR = 0x8;
+    pub const GPU_LA4: GPU_TEXCOLOR = 0x9;
+    pub const GPU_L4: GPU_TEXCOLOR = 0xA;
+    pub const GPU_A4: GPU_TEXCOLOR = 0xB;
+    pub const GPU_ETC1: GPU_TEXCOLOR = 0xC;
+    pub const GPU_ETC1A4: GPU_TEXCOLOR = 0xD;
+}
+
+/// Light LUTs, see `<c3d/light.h>` and `<c3d/lightlut.h>`.
+#[allow(non_camel_case_types, non_snake_case)]
+pub(crate) mod citro3d_sys {
+    #[derive(Clone, Copy, Debug)]
+    #[repr(C)]
+    pub struct C3D_LightLut {
+        pub data: [u32; 256],
+    }
+
+    // Only read by `citro3d` when drawing
+    #[allow(dead_code)]
+    #[derive(Clone, Copy, Debug)]
+    #[repr(C)]
+    pub struct C3D_LightLutDA {
+        pub scale: f32,
+        pub bias: f32,
+        pub lut: C3D_LightLut,
+    }
+
+    /// Pack 256 samples followed by 256 differences into `lut`, using the Rust
+    /// port of the real function.
+    ///
+    /// # Safety
+    ///
+    /// `lut` must be valid for writes, and `data` must point to 512 floats.
+    pub unsafe fn LightLut_FromArray(lut: *mut C3D_LightLut, data: *mut f32) {
+        unsafe { (*lut).data = crate::light::pack_lut(&*data.cast()) };
+    }
+}
@@ -0,0 +1,95 @@
+//! Bundling of the state needed to draw with a shader program.
+//!
+//! Drawing anything needs a bound [`shader::Program`], an [`attrib::Info`]
+//! describing the vertex data, and [texture combiners](crate::texenv) to turn
+//! the shader output into fragment colors. A [`Pipeline`] holds all of these
+//! and applies them together with [`Pipeline::bind`].
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::texenv::{Stage, TexEnv};
+use crate::{attrib, shader, Instance};
+
+/// A shader program along with the vertex attributes and texture combiner
+/// stages to draw with it.
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use std::pin::Pin;
+/// # use std::sync::Arc;
+/// # use citro3d::pipeline::Pipeline;
+/// # use citro3d::texenv::{CombineFunc, Mode, Source, Stage};
+/// # use citro3d::{attrib, shader};
+/// # fn setup(
+/// #     instance: &mut citro3d::Instance,
+/// #     program: Pin<Arc<shader::Program>>,
+/// #     attr_info: attrib::Info,
+/// # ) {
+/// let pipeline = Pipeline::new(program, attr_info).stage(Stage::new(0).unwrap(), |env| {
+///     env.src(Mode::BOTH, Source::PrimaryColor, None, None)
+///         .func(Mode::BOTH, CombineFunc::Replace);
+/// });
+///
+/// pipeline.bind(instance);
+/// // ... draw ...
+/// # }
+/// ```
+#[must_use]
+pub struct Pipeline {
+    program: Pin<Arc<shader::Program>>,
+    attr_info: attrib::Info,
+    stages: Vec<(Stage, Box<dyn Fn(&mut TexEnv) + Send + Sync>)>,
+}
+
+impl Pipeline {
+    /// Create a pipeline drawing with `program` and vertex data laid out as
+    /// described by `attr_info`, without any texture combiner stages.
+    pub fn new(program: Pin<Arc<shader::Program>>, attr_info: attrib::Info) -> Self {
+        Self {
+            program,
+            attr_info,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Add a texture combiner stage, which is set up by calling `configure`
+    /// with the stage's [`TexEnv`] whenever the pipeline is bound. The stage
+    /// is reset to its default state first.
+    ///
+    /// If the same stage is added more than once, the last one is used.
+    pub fn stage(
+        mut self,
+        stage: Stage,
+        configure: impl Fn(&mut TexEnv) + Send + Sync + 'static,
+    ) -> Self {
+        self.stages.retain(|(s, _)| s.index() != stage.index());
+        self.stages.push((stage, Box::new(configure)));
+        self
+    }
+
+    /// Get the shader program this pipeline draws with.
+    pub fn program(&self) -> &Pin<Arc<shader::Program>> {
+        &self.program
+    }
+
+    /// Get the vertex attributes this pipeline draws with.
+    pub fn attr_info(&self) -> &attrib::Info {
+        &self.attr_info
+    }
+
+    /// Bind the shader program, set the vertex attributes and configure the
+    /// texture combiners, in that order. Stages which were not added to the
+    /// pipeline are reset, so combiner state from earlier draws doesn't leak in.
+    pub fn bind(&self, instance: &mut Instance) {
+        instance.bind_program(self.program.clone());
+        instance.set_attr_info(&self.attr_info);
+
+        instance.reset_texenvs();
+        for (stage, configure) in &self.stages {
+            configure(instance.texenv(*stage));
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! of data to the GPU, including the format of color and depth data to be rendered.
 
 use std::cell::RefMut;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use citro3d_sys::{
     C3D_RenderTarget, C3D_RenderTargetCreate, C3D_RenderTargetDelete, C3D_DEPTHTYPE,
@@ -10,23 +11,148 @@ use ctru::services::gfx::Screen;
 use ctru::services::gspgpu::FramebufferFormat;
 use ctru_sys::{GPU_COLORBUF, GPU_DEPTHBUF};
 
+use crate::color::Color;
+use crate::texture::{Tex, TexFormat, TexKind};
 use crate::{Error, Result};
 
 mod transfer;
 
+pub use transfer::{Antialias, Flags as TransferFlags, Format as TransferFormat};
+
+/// The data address of the texture of the [`Target`] last selected with
+/// [`Instance::select_render_target`](crate::Instance::select_render_target),
+/// or 0 if it isn't a texture target.
+static DRAWING_TO: AtomicUsize = AtomicUsize::new(0);
+
+/// Record which target following draws go to, to catch textures being sampled
+/// while they are drawn to.
+pub(crate) fn set_drawing_to(target: Option<&Target<'_>>) {
+    let addr = target
+        .and_then(Target::as_texture)
+        .map_or(0, |tex| tex.data_ptr() as usize);
+    DRAWING_TO.store(addr, Ordering::Relaxed);
+}
+
+/// Whether `tex` belongs to the selected render target.
+pub(crate) fn is_drawing_to(tex: &Tex) -> bool {
+    DRAWING_TO.load(Ordering::Relaxed) == tex.data_ptr() as usize
+}
+
+/// The data addresses of the textures bound to each texture unit with
+/// [`Tex::bind`], or 0 for none.
+static BOUND_TEXTURES: [AtomicUsize; 4] = [const { AtomicUsize::new(0) }; 4];
+
+/// Record that `tex` was bound to `unit_id`.
+pub(crate) fn set_bound(unit_id: i32, tex: &Tex) {
+    if let Some(unit) = usize::try_from(unit_id)
+        .ok()
+        .and_then(|unit| BOUND_TEXTURES.get(unit))
+    {
+        unit.store(tex.data_ptr() as usize, Ordering::Relaxed);
+    }
+}
+
+/// Forget any bindings of `tex`, so a later texture at the same address isn't
+/// mistaken for it.
+pub(crate) fn clear_bound(tex: &Tex) {
+    for unit in &BOUND_TEXTURES {
+        let _ = unit.compare_exchange(
+            tex.data_ptr() as usize,
+            0,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Check that the texture of the selected render target isn't bound to any
+/// texture unit, before drawing. This catches a texture being bound before its
+/// target is selected, which [`Tex::bind`] can't.
+pub(crate) fn check_not_sampling_target() -> Result<()> {
+    let target = DRAWING_TO.load(Ordering::Relaxed);
+    if target != 0
+        && BOUND_TEXTURES
+            .iter()
+            .any(|unit| unit.load(Ordering::Relaxed) == target)
+    {
+        Err(Error::SamplingRenderTarget)
+    } else {
+        Ok(())
+    }
+}
+
 /// A render target for `citro3d`. Frame data will be written to this target
-/// to be rendered on the GPU and displayed on the screen.
+/// to be rendered on the GPU, and then either displayed on a screen or used as
+/// a texture.
+///
+/// # Lifecycle
+///
+/// Targets are meant to be created once and reused for every frame, since
+/// creating one allocates its buffers in VRAM. Each frame, select the target
+/// with [`Instance::select_render_target`](crate::Instance::select_render_target),
+/// [clear](Target::clear) it if needed and draw to it. The contents stay in
+/// place between frames.
+///
+/// A target created with [`Target::from_texture`] renders into its texture,
+/// which can be sampled by draws to other targets later in the same frame,
+/// e.g. for post-processing or shadow maps. The GPU runs the commands in
+/// order, so no extra synchronization is needed. The texture must not be
+/// sampled while its own target is selected: binding it then fails with
+/// [`Error::SamplingRenderTarget`], and so does drawing while it is still bound
+/// from an earlier pass, until another texture is bound to that unit.
+///
+/// # Example
+///
+/// Ping-pong between two texture targets, each pass reading the result of the
+/// one before:
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use citro3d::render::Target;
+/// # use citro3d::texture::{Tex, TexFormat, TexParams};
+/// # let mut instance = citro3d::Instance::new().unwrap();
+/// let new_target = || {
+///     let params = TexParams::new_2d(256, 256).format(TexFormat::Rgba8).use_vram(true);
+///     Target::from_texture(Tex::new(params).unwrap(), None).unwrap()
+/// };
+/// let mut targets = [new_target(), new_target()];
+///
+/// instance
+///     .render_frame_with(|instance| {
+///         for pass in 0..4 {
+///             let [read, write] = if pass % 2 == 0 { [0, 1] } else { [1, 0] };
+///             instance.select_render_target(&targets[write]).unwrap();
+///             targets[read].as_texture().unwrap().bind(0).unwrap();
+///             // ... draw a full-screen quad sampling texture unit 0 ...
+///         }
+///     })
+///     .unwrap();
+/// ```
 #[doc(alias = "C3D_RenderTarget")]
 pub struct Target<'screen> {
     raw: *mut citro3d_sys::C3D_RenderTarget,
+    color_format: ColorFormat,
+    depth_format: Option<DepthFormat>,
     // This is unused after construction, but ensures unique access to the
     // screen this target writes to during rendering
-    _screen: RefMut<'screen, dyn Screen>,
+    _screen: Option<RefMut<'screen, dyn Screen>>,
+    texture: Option<Tex>,
 }
 
 impl Drop for Target<'_> {
     #[doc(alias = "C3D_RenderTargetDelete")]
     fn drop(&mut self) {
+        if let Some(tex) = &self.texture {
+            // Don't let a later texture at the same address look like it is
+            // being drawn to
+            let _ = DRAWING_TO.compare_exchange(
+                tex.data_ptr() as usize,
+                0,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+        // This doesn't free the texture data, which is dropped afterwards
         unsafe {
             C3D_RenderTargetDelete(self.raw);
         }
@@ -47,13 +173,57 @@ impl<'screen> Target<'screen> {
         height: usize,
         screen: RefMut<'screen, dyn Screen>,
         depth_format: Option<DepthFormat>,
+    ) -> Result<Self> {
+        Self::with_antialias(width, height, screen, depth_format, Antialias::None)
+    }
+
+    /// Create a new render target like [`Target::new`], but drawn at a higher
+    /// resolution and downscaled when copied to the screen, to smooth edges.
+    /// `width` and `height` are the size of the screen's framebuffer, and the
+    /// target itself is larger by [`Antialias::factors`].
+    ///
+    /// This costs 2-4x the fill rate and VRAM, see [`Antialias`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the target could not be created, e.g. because there is not
+    /// enough VRAM for it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _runner = test_runner::GdbRunner::default();
+    /// # use ctru::services::gfx::{Gfx, RawFrameBuffer, Screen};
+    /// # use citro3d::render::{Antialias, DepthFormat, Target};
+    /// # let gfx = Gfx::new().unwrap();
+    /// # let _instance = citro3d::Instance::new().unwrap();
+    /// let mut bottom_screen = gfx.bottom_screen.borrow_mut();
+    /// let RawFrameBuffer { width, height, .. } = bottom_screen.raw_framebuffer();
+    /// let target = Target::with_antialias(
+    ///     width,
+    ///     height,
+    ///     bottom_screen,
+    ///     Some(DepthFormat::Depth24),
+    ///     Antialias::ScaleXY,
+    /// )
+    /// .unwrap();
+    /// ```
+    #[doc(alias = "C3D_RenderTargetCreate")]
+    #[doc(alias = "C3D_RenderTargetSetOutput")]
+    pub fn with_antialias(
+        width: usize,
+        height: usize,
+        screen: RefMut<'screen, dyn Screen>,
+        depth_format: Option<DepthFormat>,
+        antialias: Antialias,
     ) -> Result<Self> {
         let color_format: ColorFormat = screen.framebuffer_format().into();
+        let (scale_x, scale_y) = antialias.factors();
 
         let raw = unsafe {
             C3D_RenderTargetCreate(
-                width.try_into()?,
-                height.try_into()?,
+                (width * scale_x).try_into()?,
+                (height * scale_y).try_into()?,
                 color_format as GPU_COLORBUF,
                 depth_format.map_or(C3D_DEPTHTYPE { __i: -1 }, DepthFormat::as_raw),
             )
@@ -64,9 +234,10 @@ impl<'screen> Target<'screen> {
         }
 
         // Set the render target to actually output to the given screen
-        let flags = transfer::Flags::default()
+        let flags = TransferFlags::default()
             .in_format(color_format.into())
-            .out_format(color_format.into());
+            .out_format(color_format.into())
+            .antialias(antialias);
 
         unsafe {
             citro3d_sys::C3D_RenderTargetSetOutput(
@@ -79,19 +250,215 @@ impl<'screen> Target<'screen> {
 
         Ok(Self {
             raw,
-            _screen: screen,
+            color_format,
+            depth_format,
+            _screen: Some(screen),
+            texture: None,
         })
     }
 
-    /// Clear the render target with the given 32-bit RGBA color and depth buffer value.
-    /// Use `flags` to specify whether color and/or depth should be overwritten.
+    /// Create a render target which draws into `texture`, optionally with a
+    /// depth buffer of its own. See the [lifecycle](Target#lifecycle) docs for
+    /// how to use it.
+    ///
+    /// Keep in mind that triangles are rasterized in the rotated orientation
+    /// of the 3DS screens: a projection made for a screen draws sideways into
+    /// the texture, which is undone when it is sampled the same way.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidFormat`] if `texture` is not a 2D texture, or its
+    ///   format is not one of the [`ColorFormat`]s the GPU can render to.
+    /// * [`Error::FailedToInitialize`] if the target could not be created.
+    #[doc(alias = "C3D_RenderTargetCreateFromTex")]
+    pub fn from_texture(texture: Tex, depth_format: Option<DepthFormat>) -> Result<Self> {
+        let color_format = [
+            ColorFormat::RGBA8,
+            ColorFormat::RGB8,
+            ColorFormat::RGBA5551,
+            ColorFormat::RGB565,
+            ColorFormat::RGBA4,
+        ]
+        .into_iter()
+        .find(|format| format.tex_format() == texture.format())
+        .filter(|_| texture.kind() == TexKind::Tex2d)
+        .ok_or(Error::InvalidFormat)?;
+
+        let raw = unsafe {
+            citro3d_sys::C3D_RenderTargetCreateFromTex(
+                texture.as_raw().cast_mut(),
+                ctru_sys::GPU_TEXFACE_2D,
+                0,
+                depth_format.map_or(C3D_DEPTHTYPE { __i: -1 }, DepthFormat::as_raw),
+            )
+        };
+
+        if raw.is_null() {
+            return Err(Error::FailedToInitialize);
+        }
+
+        Ok(Self {
+            raw,
+            color_format,
+            depth_format,
+            _screen: None,
+            texture: Some(texture),
+        })
+    }
+
+    /// Get the texture this target draws into, if it was created with
+    /// [`Target::from_texture`].
+    pub fn as_texture(&self) -> Option<&Tex> {
+        self.texture.as_ref()
+    }
+
+    /// Clear the render target with the given color, depth (from `0.0` to `1.0`)
+    /// and stencil values. Use `flags` to specify whether color and/or depth
+    /// should be overwritten. The stencil value is only used with
+    /// [`DepthFormat::Depth24Stencil8`], and is cleared along with the depth.
+    ///
+    /// The values are packed to match the target's [`ColorFormat`] and
+    /// [`DepthFormat`].
+    #[doc(alias = "C3D_RenderTargetClear")]
+    pub fn clear(&mut self, flags: ClearFlags, color: Color, depth: f32, stencil: u8) {
+        let color = self.color_format.pack(color);
+        let depth = self
+            .depth_format
+            .map_or(0, |format| format.pack_clear(depth, stencil));
+        self.clear_raw(flags, color, depth);
+    }
+
+    /// Clear the render target with color and depth values that are already
+    /// packed in the target's formats (see [`ColorFormat::pack`] and
+    /// [`DepthFormat::pack_clear`]).
     #[doc(alias = "C3D_RenderTargetClear")]
-    pub fn clear(&mut self, flags: ClearFlags, rgba_color: u32, depth: u32) {
+    pub fn clear_raw(&mut self, flags: ClearFlags, color: u32, depth: u32) {
         unsafe {
-            citro3d_sys::C3D_RenderTargetClear(self.raw, flags.bits(), rgba_color, depth);
+            citro3d_sys::C3D_RenderTargetClear(self.raw, flags.bits(), color, depth);
         }
     }
 
+    /// Copy the contents of the color buffer to the top-left corner of `tex`,
+    /// e.g. for feedback effects or to draw a screenshot as a texture. This
+    /// waits for the GPU to finish rendering first.
+    ///
+    /// The image is copied as-is, so it has the same orientation as the color
+    /// buffer: the 3DS screens are rotated, so e.g. a top screen target is 240
+    /// pixels wide and 400 high. Sampling `tex` in the same way the target was
+    /// rendered gives back the original image.
+    ///
+    /// To capture draws from earlier in the current frame, call
+    /// [`Instance::flush_await`](crate::Instance::flush_await) first.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidFormat`] if `tex` is not a 2D texture in the
+    ///   [`TexFormat`] matching the target's [`ColorFormat`].
+    /// * [`Error::InvalidSize`] if `tex` is smaller than the target.
+    #[doc(alias = "C3D_SyncTextureCopy")]
+    pub fn capture_to(&self, tex: &mut Tex) -> Result<()> {
+        if tex.kind() != TexKind::Tex2d || tex.format() != self.color_format.tex_format() {
+            return Err(Error::InvalidFormat);
+        }
+        let (width, height) = self.size();
+        if usize::from(tex.width()) < width || usize::from(tex.height()) < height {
+            return Err(Error::InvalidSize);
+        }
+
+        let bytes_per_pixel = self.color_format.bits_per_pixel() / 8;
+        // One row of 8x8 tiles from the color buffer, and the rest of the same
+        // row in the texture
+        let tile_row = width * 8 * bytes_per_pixel;
+        let gap = (usize::from(tex.width()) - width) * 8 * bytes_per_pixel;
+        unsafe {
+            self.copy_color_buf(
+                tex.data_non_null().as_ptr(),
+                buffer_dim(tile_row, gap),
+                tile_row * height / 8,
+            );
+        }
+        Ok(())
+    }
+
+    /// Read back the contents of the color buffer, e.g. to take a screenshot.
+    /// This waits for the GPU to finish rendering first.
+    ///
+    /// The pixels are returned as RGBA8 (`[r, g, b, a]` for each pixel) in
+    /// row-major order, rotated to match the screen: for a top screen target,
+    /// this is a 400 by 240 image.
+    ///
+    /// To read back draws from earlier in the current frame, call
+    /// [`Instance::flush_await`](crate::Instance::flush_await) first.
+    #[doc(alias = "C3D_SyncTextureCopy")]
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let (width, height) = self.size();
+        let bytes_per_pixel = self.color_format.bits_per_pixel() / 8;
+        let size = width * height * bytes_per_pixel;
+
+        let mut tiled = Vec::with_capacity_in(size, ctru::linear::LinearAllocator);
+        tiled.resize(size, 0);
+        unsafe {
+            self.copy_color_buf(tiled.as_mut_ptr(), buffer_dim(size, 0), size);
+        }
+
+        // The color buffer is rotated 90 degrees from the screen, so each row
+        // of the screen is a column of the color buffer, right to left
+        let (screen_width, screen_height) = (height, width);
+        let mut pixels = Vec::with_capacity(screen_width * screen_height * 4);
+        for screen_y in 0..screen_height {
+            for screen_x in 0..screen_width {
+                let (x, y) = (width - 1 - screen_y, screen_x);
+                let offset = crate::texture::tiled_index(x, y, width) * bytes_per_pixel;
+                let pixel = &tiled[offset..offset + bytes_per_pixel];
+                pixels.extend_from_slice(&self.color_format.unpack(pixel));
+            }
+        }
+        pixels
+    }
+
+    /// Get the `(width, height)` of the color buffer.
+    fn size(&self) -> (usize, usize) {
+        // SAFETY: the target is valid for the lifetime of `self`
+        let frame_buf = unsafe { &(*self.raw).frameBuf };
+        (frame_buf.width.into(), frame_buf.height.into())
+    }
+
+    /// Copy `size` bytes of the color buffer to `dst` with a texture copy, which
+    /// also waits for rendering to finish and keeps the CPU caches coherent.
+    ///
+    /// # Safety
+    ///
+    /// `dst` must be valid for the writes described by `out_dim` and `size`.
+    unsafe fn copy_color_buf(&self, dst: *mut u8, out_dim: u32, size: usize) {
+        let frame_buf = &(*self.raw).frameBuf;
+        let tile_row = usize::from(frame_buf.width) * 8 * self.color_format.bits_per_pixel() / 8;
+        citro3d_sys::C3D_SyncTextureCopy(
+            frame_buf.colorBuf.cast(),
+            buffer_dim(tile_row, 0),
+            dst.cast(),
+            out_dim,
+            size as u32,
+            0,
+        );
+    }
+
+    /// Get the format of this target's color buffer.
+    pub fn color_format(&self) -> ColorFormat {
+        self.color_format
+    }
+
+    /// Get the format of this target's depth buffer, or `None` if it was
+    /// created without one.
+    pub fn depth_format(&self) -> Option<DepthFormat> {
+        self.depth_format
+    }
+
+    /// Whether this target has a stencil buffer, i.e. whether it was created
+    /// with [`DepthFormat::Depth24Stencil8`].
+    pub fn has_stencil(&self) -> bool {
+        self.depth_format.is_some_and(DepthFormat::has_stencil)
+    }
+
     /// Return the underlying `citro3d` render target for this target.
     pub(crate) fn as_raw(&self) -> *mut C3D_RenderTarget {
         self.raw
@@ -111,9 +478,202 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    /// Which channels of the render target are written by draw calls, see
+    /// [`Instance::set_write_mask`](crate::Instance::set_write_mask).
+    #[doc(alias = "GPU_WRITEMASK")]
+    pub struct WriteMask: u8 {
+        #[allow(missing_docs)]
+        const RED = ctru_sys::GPU_WRITE_RED;
+        #[allow(missing_docs)]
+        const GREEN = ctru_sys::GPU_WRITE_GREEN;
+        #[allow(missing_docs)]
+        const BLUE = ctru_sys::GPU_WRITE_BLUE;
+        #[allow(missing_docs)]
+        const ALPHA = ctru_sys::GPU_WRITE_ALPHA;
+        /// The depth buffer.
+        const DEPTH = ctru_sys::GPU_WRITE_DEPTH;
+        /// All color channels.
+        const COLOR = ctru_sys::GPU_WRITE_COLOR;
+        /// All color channels and the depth buffer.
+        const ALL = ctru_sys::GPU_WRITE_ALL;
+    }
+}
+
+/// A comparison function, used by the depth, alpha and stencil tests to decide
+/// whether a fragment passes. The fragment's value is on the left hand side,
+/// e.g. [`TestFunc::Greater`] passes if the fragment's depth is greater than the
+/// depth buffer's.
+///
+/// Sampling [shadow textures](crate::texture::TexKind::Shadow2d) also compares
+/// depths, but that comparison is fixed in the hardware and doesn't use this.
+#[doc(alias = "GPU_TESTFUNC")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TestFunc {
+    /// Never pass.
+    Never = ctru_sys::GPU_NEVER,
+    /// Always pass.
+    Always = ctru_sys::GPU_ALWAYS,
+    /// Pass if the values are equal.
+    Equal = ctru_sys::GPU_EQUAL,
+    /// Pass if the values are not equal.
+    NotEqual = ctru_sys::GPU_NOTEQUAL,
+    /// Pass if the fragment's value is less.
+    Less = ctru_sys::GPU_LESS,
+    /// Pass if the fragment's value is less or equal.
+    LessEqual = ctru_sys::GPU_LEQUAL,
+    /// Pass if the fragment's value is greater.
+    Greater = ctru_sys::GPU_GREATER,
+    /// Pass if the fragment's value is greater or equal.
+    GreaterEqual = ctru_sys::GPU_GEQUAL,
+}
+
+impl From<TestFunc> for ctru_sys::GPU_TESTFUNC {
+    fn from(func: TestFunc) -> Self {
+        func as _
+    }
+}
+
+impl TryFrom<ctru_sys::GPU_TESTFUNC> for TestFunc {
+    type Error = Error;
+
+    fn try_from(value: ctru_sys::GPU_TESTFUNC) -> Result<Self> {
+        match value {
+            ctru_sys::GPU_NEVER => Ok(Self::Never),
+            ctru_sys::GPU_ALWAYS => Ok(Self::Always),
+            ctru_sys::GPU_EQUAL => Ok(Self::Equal),
+            ctru_sys::GPU_NOTEQUAL => Ok(Self::NotEqual),
+            ctru_sys::GPU_LESS => Ok(Self::Less),
+            ctru_sys::GPU_LEQUAL => Ok(Self::LessEqual),
+            ctru_sys::GPU_GREATER => Ok(Self::Greater),
+            ctru_sys::GPU_GEQUAL => Ok(Self::GreaterEqual),
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+/// Which faces are culled (not drawn), based on the winding of their vertices
+/// on screen.
+#[doc(alias = "GPU_CULLMODE")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CullMode {
+    /// Draw all faces.
+    None = ctru_sys::GPU_CULL_NONE,
+    /// Cull faces with counter-clockwise winding.
+    FrontCounterClockwise = ctru_sys::GPU_CULL_FRONT_CCW,
+    /// Cull faces with clockwise winding, i.e. keep counter-clockwise ones.
+    /// This is what `citro3d` is initialized with.
+    #[default]
+    BackCounterClockwise = ctru_sys::GPU_CULL_BACK_CCW,
+}
+
+/// The scissor test, which limits drawing to (or outside of) a rectangle of
+/// the render target. Coordinates are in pixels of the render target, i.e. in
+/// the same rotated orientation as its `width` and `height`, and the `right`
+/// and `bottom` edges are exclusive.
+#[doc(alias = "GPU_SCISSORMODE")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Scissor {
+    /// Draw to the whole render target. This is what `citro3d` is initialized
+    /// with.
+    #[default]
+    Disabled,
+    /// Only draw inside the rectangle.
+    Inside {
+        left: u32,
+        top: u32,
+        right: u32,
+        bottom: u32,
+    },
+    /// Only draw outside the rectangle.
+    Outside {
+        left: u32,
+        top: u32,
+        right: u32,
+        bottom: u32,
+    },
+}
+
+impl Scissor {
+    pub(crate) fn apply(&self) {
+        let (mode, [left, top, right, bottom]) = match *self {
+            Self::Disabled => (ctru_sys::GPU_SCISSOR_DISABLE, [0; 4]),
+            Self::Inside {
+                left,
+                top,
+                right,
+                bottom,
+            } => (ctru_sys::GPU_SCISSOR_NORMAL, [left, top, right, bottom]),
+            Self::Outside {
+                left,
+                top,
+                right,
+                bottom,
+            } => (ctru_sys::GPU_SCISSOR_INVERT, [left, top, right, bottom]),
+        };
+        unsafe { citro3d_sys::C3D_SetScissor(mode, left, top, right, bottom) };
+    }
+}
+
+/// The depth test configuration, which shares a register with the write mask.
+/// `citro3d` can't read it back, so the [`Instance`](crate::Instance) keeps
+/// track of it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DepthTest {
+    pub enabled: bool,
+    pub func: TestFunc,
+    pub write_mask: WriteMask,
+}
+
+impl Default for DepthTest {
+    /// The depth test `citro3d` is initialized with.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            func: TestFunc::Greater,
+            write_mask: WriteMask::ALL,
+        }
+    }
+}
+
+impl DepthTest {
+    pub fn apply(&self) {
+        unsafe {
+            citro3d_sys::C3D_DepthTest(self.enabled, self.func.into(), self.write_mask.bits());
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Control how [`Instance::render_frame_with_flags`](crate::Instance::render_frame_with_flags)
+    /// waits for the GPU before starting a frame.
+    #[doc(alias = "C3D_FRAME_SYNCDRAW")]
+    #[doc(alias = "C3D_FRAME_NONBLOCK")]
+    pub struct FrameFlags: u8 {
+        /// Wait for the previous frame to finish being drawn and displayed
+        /// before starting the next one, which paces rendering to the screen's
+        /// refresh rate. Without this, frames are started as soon as possible
+        /// and may be shown partway through a screen refresh, causing tearing.
+        const SYNC_DRAW = citro3d_sys::C3D_FRAME_SYNCDRAW;
+        /// Don't block if the GPU is still busy with the previous frame, and
+        /// skip the frame instead. This lets the CPU do other work while the GPU
+        /// renders, but the skipped frame's draws must be retried later.
+        const NONBLOCK = citro3d_sys::C3D_FRAME_NONBLOCK;
+    }
+}
+
+impl Default for FrameFlags {
+    /// [`FrameFlags::SYNC_DRAW`], as used by [`Instance::render_frame_with`](crate::Instance::render_frame_with).
+    fn default() -> Self {
+        Self::SYNC_DRAW
+    }
+}
+
 /// The color format to use when rendering on the GPU.
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[doc(alias = "GPU_COLORBUF")]
 pub enum ColorFormat {
     /// 8-bit Red + 8-bit Green + 8-bit Blue + 8-bit Alpha.
@@ -128,6 +688,86 @@ pub enum ColorFormat {
     RGBA4 = ctru_sys::GPU_RB_RGBA4,
 }
 
+/// Equivalent to the `GX_BUFFER_DIM` macro: the line width and gap between
+/// lines (both in bytes) of a texture copy.
+fn buffer_dim(line: usize, gap: usize) -> u32 {
+    (((gap / 16) as u32) << 16) | ((line / 16) as u32 & 0xFFFF)
+}
+
+impl ColorFormat {
+    /// Bits needed to store each pixel.
+    pub fn bits_per_pixel(self) -> usize {
+        self.tex_format().bits_per_pixel()
+    }
+
+    /// The [`TexFormat`] with the same layout, e.g. for textures used as a copy
+    /// of a render target.
+    pub fn tex_format(self) -> TexFormat {
+        match self {
+            Self::RGBA8 => TexFormat::Rgba8,
+            Self::RGB8 => TexFormat::Rgb8,
+            Self::RGBA5551 => TexFormat::Rgba5551,
+            Self::RGB565 => TexFormat::Rgb565,
+            Self::RGBA4 => TexFormat::Rgba4,
+        }
+    }
+
+    /// Convert a pixel as stored in memory in this format to `[r, g, b, a]`.
+    fn unpack(self, pixel: &[u8]) -> [u8; 4] {
+        let mut bytes = [0; 4];
+        bytes[..pixel.len()].copy_from_slice(pixel);
+        let v = u32::from_le_bytes(bytes);
+        // Scale a `bits`-bit component at `shift` up to 8 bits
+        let c = |shift: u32, bits: u32| {
+            let max = (1 << bits) - 1;
+            (((v >> shift) & max) * 255 / max) as u8
+        };
+        match self {
+            Self::RGBA8 => [c(24, 8), c(16, 8), c(8, 8), c(0, 8)],
+            Self::RGB8 => [c(16, 8), c(8, 8), c(0, 8), 0xFF],
+            Self::RGBA5551 => [c(11, 5), c(6, 5), c(1, 5), c(0, 1)],
+            Self::RGB565 => [c(11, 5), c(5, 6), c(0, 5), 0xFF],
+            Self::RGBA4 => [c(12, 4), c(8, 4), c(4, 4), c(0, 4)],
+        }
+    }
+
+    /// Pack `color` into the bits used for this format when clearing a render
+    /// target, e.g. `0xRRGGBBAA` for [`ColorFormat::RGBA8`] or a 16-bit
+    /// `RRRRRGGGGGGBBBBB` value for [`ColorFormat::RGB565`]. Components are
+    /// truncated to the format's precision.
+    pub fn pack(self, color: Color) -> u32 {
+        let [r, g, b, a] = color.to_rgba8().map(u32::from);
+        match self {
+            Self::RGBA8 => color.to_packed(),
+            Self::RGB8 => color.to_packed() >> 8,
+            Self::RGBA5551 => ((r >> 3) << 11) | ((g >> 3) << 6) | ((b >> 3) << 1) | (a >> 7),
+            Self::RGB565 => ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3),
+            Self::RGBA4 => ((r >> 4) << 12) | ((g >> 4) << 8) | ((b >> 4) << 4) | (a >> 4),
+        }
+    }
+}
+
+impl TryFrom<GPU_COLORBUF> for ColorFormat {
+    type Error = Error;
+
+    fn try_from(value: GPU_COLORBUF) -> Result<Self> {
+        match value {
+            ctru_sys::GPU_RB_RGBA8 => Ok(Self::RGBA8),
+            ctru_sys::GPU_RB_RGB8 => Ok(Self::RGB8),
+            ctru_sys::GPU_RB_RGBA5551 => Ok(Self::RGBA5551),
+            ctru_sys::GPU_RB_RGB565 => Ok(Self::RGB565),
+            ctru_sys::GPU_RB_RGBA4 => Ok(Self::RGBA4),
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+impl From<ColorFormat> for GPU_COLORBUF {
+    fn from(value: ColorFormat) -> Self {
+        value as GPU_COLORBUF
+    }
+}
+
 impl From<FramebufferFormat> for ColorFormat {
     fn from(format: FramebufferFormat) -> Self {
         match format {
@@ -143,7 +783,7 @@ impl From<FramebufferFormat> for ColorFormat {
 
 /// The depth buffer format to use when rendering.
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[doc(alias = "GPU_DEPTHBUF")]
 #[doc(alias = "C3D_DEPTHTYPE")]
 pub enum DepthFormat {
@@ -156,9 +796,105 @@ pub enum DepthFormat {
 }
 
 impl DepthFormat {
+    /// Whether this format includes a stencil buffer alongside the depth buffer.
+    pub fn has_stencil(self) -> bool {
+        matches!(self, Self::Depth24Stencil8)
+    }
+
+    /// Pack a depth value (clamped to `0.0..=1.0`) and stencil value into the
+    /// bits used for this format when clearing a render target. `stencil` is
+    /// ignored for formats without a stencil buffer.
+    pub fn pack_clear(self, depth: f32, stencil: u8) -> u32 {
+        let scale = |max: u32| (depth.clamp(0.0, 1.0) * max as f32).round() as u32;
+        match self {
+            Self::Depth16 => scale(0xFFFF),
+            Self::Depth24 => scale(0xFF_FFFF),
+            Self::Depth24Stencil8 => (u32::from(stencil) << 24) | scale(0xFF_FFFF),
+        }
+    }
+
     fn as_raw(self) -> C3D_DEPTHTYPE {
         C3D_DEPTHTYPE {
             __e: self as GPU_DEPTHBUF,
         }
     }
 }
+
+impl TryFrom<GPU_DEPTHBUF> for DepthFormat {
+    type Error = Error;
+
+    fn try_from(value: GPU_DEPTHBUF) -> Result<Self> {
+        match value {
+            ctru_sys::GPU_RB_DEPTH16 => Ok(Self::Depth16),
+            ctru_sys::GPU_RB_DEPTH24 => Ok(Self::Depth24),
+            ctru_sys::GPU_RB_DEPTH24_STENCIL8 => Ok(Self::Depth24Stencil8),
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+impl From<DepthFormat> for GPU_DEPTHBUF {
+    fn from(value: DepthFormat) -> Self {
+        value as GPU_DEPTHBUF
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_func_conversions() {
+        for func in [
+            TestFunc::Never,
+            TestFunc::Always,
+            TestFunc::Equal,
+            TestFunc::NotEqual,
+            TestFunc::Less,
+            TestFunc::LessEqual,
+            TestFunc::Greater,
+            TestFunc::GreaterEqual,
+        ] {
+            let raw: ctru_sys::GPU_TESTFUNC = func.into();
+            assert_eq!(TestFunc::try_from(raw).unwrap(), func);
+        }
+        assert!(matches!(TestFunc::try_from(8), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn pack_clear_color() {
+        let color = Color::from_rgba8(0xFF, 0x80, 0x00, 0xFF);
+        assert_eq!(ColorFormat::RGBA8.pack(color), 0xFF80_00FF);
+        assert_eq!(ColorFormat::RGB8.pack(color), 0xFF_8000);
+        assert_eq!(ColorFormat::RGBA5551.pack(color), 0b11111_10000_00000_1);
+        assert_eq!(ColorFormat::RGB565.pack(color), 0b11111_100000_00000);
+        assert_eq!(ColorFormat::RGBA4.pack(color), 0xF80F);
+    }
+
+    #[test]
+    fn unpack_pixels() {
+        for format in [
+            ColorFormat::RGBA8,
+            ColorFormat::RGB8,
+            ColorFormat::RGBA5551,
+            ColorFormat::RGB565,
+            ColorFormat::RGBA4,
+        ] {
+            let color = Color::from_rgba8(0xFF, 0, 0xFF, 0xFF);
+            let bytes = format.pack(color).to_le_bytes();
+            let pixel = &bytes[..format.bits_per_pixel() / 8];
+            assert_eq!(format.unpack(pixel), [0xFF, 0, 0xFF, 0xFF], "{format:?}");
+        }
+    }
+
+    #[test]
+    fn pack_clear_depth() {
+        assert_eq!(DepthFormat::Depth16.pack_clear(1.0, 0xFF), 0xFFFF);
+        assert_eq!(DepthFormat::Depth24.pack_clear(0.5, 0xFF), 0x80_0000);
+        assert_eq!(
+            DepthFormat::Depth24Stencil8.pack_clear(2.0, 0x12),
+            0x12FF_FFFF
+        );
+        assert_eq!(DepthFormat::Depth24Stencil8.pack_clear(-1.0, 0), 0);
+    }
+}
@@ -1,5 +1,5 @@
-use citro3d_sys::{GX_TRANSFER_IN_FORMAT, GX_TRANSFER_OUT_FORMAT};
-use ctru_sys::GX_TRANSFER_FORMAT;
+use citro3d_sys::{GX_TRANSFER_IN_FORMAT, GX_TRANSFER_OUT_FORMAT, GX_TRANSFER_SCALING};
+use ctru_sys::{GX_TRANSFER_FORMAT, GX_TRANSFER_SCALE};
 
 use super::ColorFormat;
 
@@ -20,12 +20,62 @@ impl Flags {
         Self(self.0 | GX_TRANSFER_OUT_FORMAT(fmt as GX_TRANSFER_FORMAT))
     }
 
+    /// Downscale the data while transferring it, averaging neighbouring pixels
+    /// to smooth out edges. The source must be larger than the destination by
+    /// the factors of `mode`, see [`Antialias`].
+    #[must_use]
+    #[doc(alias = "GX_TRANSFER_SCALING")]
+    pub fn antialias(self, mode: Antialias) -> Self {
+        Self(self.0 | GX_TRANSFER_SCALING(mode as GX_TRANSFER_SCALE))
+    }
+
     #[must_use]
     pub fn bits(self) -> u32 {
         self.0
     }
 }
 
+/// Downscaling applied by a display transfer, which is the closest the 3DS has
+/// to multisample anti-aliasing.
+///
+/// A render target using it is drawn at a higher resolution and averaged down
+/// when it is copied to the screen, see [`Target::with_antialias`]. The axes
+/// are those of the framebuffer, which is rotated relative to the screen: `X`
+/// is the target's `width`, i.e. the screen's short, vertical edge.
+///
+/// # Performance
+///
+/// The GPU draws, and the transfer reads, 2 ([`Antialias::ScaleX`]) or 4
+/// ([`Antialias::ScaleXY`]) times as many pixels, and the color and depth
+/// buffers take that much more VRAM. Fragment-heavy scenes get correspondingly
+/// slower, and a 4x top screen target alone uses most of the 6 MiB of VRAM.
+///
+/// [`Target::with_antialias`]: super::Target::with_antialias
+#[repr(u8)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[doc(alias = "GX_TRANSFER_SCALE")]
+pub enum Antialias {
+    /// No downscaling.
+    #[default]
+    None = ctru_sys::GX_TRANSFER_SCALE_NO,
+    /// Average pairs of pixels along the X axis (2x1).
+    ScaleX = ctru_sys::GX_TRANSFER_SCALE_X,
+    /// Average 2x2 blocks of pixels.
+    ScaleXY = ctru_sys::GX_TRANSFER_SCALE_XY,
+}
+
+impl Antialias {
+    /// Get how many times larger the source of the transfer is than the
+    /// destination, as `(x, y)`.
+    pub fn factors(self) -> (usize, usize) {
+        match self {
+            Self::None => (1, 1),
+            Self::ScaleX => (2, 1),
+            Self::ScaleXY => (2, 2),
+        }
+    }
+}
+
 /// The color format to use when transferring data to/from the GPU.
 ///
 /// NOTE: this a distinct type from [`ColorFormat`] because they are not implicitly
@@ -5,14 +5,16 @@
 //! documentation for <https://github.com/devkitPro/picasso>.
 
 use std::error::Error;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::marker::PhantomPinned;
 use std::mem::MaybeUninit;
+#[cfg(feature = "std")]
+use std::path::Path;
 use std::pin::Pin;
 use std::ptr::NonNull;
 use std::sync::Arc;
 
-use crate::uniform;
+use crate::{attrib, uniform};
 
 /// A PICA200 shader program. It may have one or both of:
 ///
@@ -25,6 +27,11 @@ use crate::uniform;
 #[derive(Clone)]
 pub struct Program {
     program: ctru_sys::shaderProgram_s,
+    /// The number of vertices the geometry shader takes for each primitive, if known
+    geometry_vertices: Option<u8>,
+    /// Libraries the shaders were loaded from, if they were shared with the
+    /// program. Dropped after the program is freed.
+    libraries: Vec<Arc<Library>>,
     /// needs to be pin'd to work properly with C3D_Context BindProgram
     _p: PhantomPinned,
 }
@@ -35,6 +42,9 @@ unsafe impl Sync for Program {}
 impl Program {
     /// Create a new shader program from a vertex shader.
     ///
+    /// The [`Library`] of `vertex_shader` must outlive this program. Use
+    /// [`Program::from_library`] to have the program keep it alive instead.
+    ///
     /// # Errors
     ///
     /// Returns an error if:
@@ -57,6 +67,8 @@ impl Program {
         if ret == 0 {
             Ok(Self {
                 program,
+                geometry_vertices: None,
+                libraries: Vec::new(),
                 _p: PhantomPinned,
             })
         } else {
@@ -64,8 +76,69 @@ impl Program {
         }
     }
 
+    /// Create a new shader program from the vertex shader at `index` in
+    /// `library`. Unlike [`Program::new`], the program keeps the library alive
+    /// itself, so it doesn't need to be kept around separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no entrypoint at `index`, or for the same
+    /// reasons as [`Program::new`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _runner = test_runner::GdbRunner::default();
+    /// # use std::sync::Arc;
+    /// # use citro3d::shader::{Library, Program};
+    /// # fn load(vsh: &[u8], gsh: &[u8]) -> Result<Program, Box<dyn std::error::Error>> {
+    /// // The vertex and geometry shaders are in separate shader binaries
+    /// let vertex = Arc::new(Library::from_bytes(vsh)?);
+    /// let geometry = Arc::new(Library::from_bytes(gsh)?);
+    ///
+    /// let mut program = Program::from_library(vertex, 0)?;
+    /// program.set_geometry_from_library(geometry, 0, 3)?;
+    /// // Both libraries are dropped along with `program`
+    /// Ok(program)
+    /// # }
+    /// ```
+    pub fn from_library(library: Arc<Library>, index: usize) -> Result<Self, ctru::Error> {
+        let mut program = Self::new(library.get(index).ok_or_else(missing_entrypoint)?)?;
+        program.libraries.push(library);
+        Ok(program)
+    }
+
+    /// Set the geometry shader of this program to the entrypoint at `index` in
+    /// `library`, which will receive `vertices_per_primitive` vertices each time
+    /// it runs. Like [`Program::from_library`], the program keeps the library
+    /// alive itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no entrypoint at `index`, or for the same
+    /// reasons as [`Program::set_geometry_shader_vertices`].
+    pub fn set_geometry_from_library(
+        &mut self,
+        library: Arc<Library>,
+        index: usize,
+        vertices_per_primitive: u8,
+    ) -> Result<(), ctru::Error> {
+        let geometry_shader = library.get(index).ok_or_else(missing_entrypoint)?;
+        self.set_geometry_shader_vertices(geometry_shader, vertices_per_primitive)?;
+        self.libraries.push(library);
+        Ok(())
+    }
+
     /// Set the geometry shader for a given program.
     ///
+    /// `stride` is the number of vertex shader output registers the geometry
+    /// shader receives each time it runs, i.e. the number of vertex shader
+    /// outputs times the number of vertices per primitive. See also
+    /// [`Program::set_geometry_shader_vertices`], which calculates it for you.
+    ///
+    /// Programs with a geometry shader must be drawn with
+    /// [`Primitive::GeometryPrim`](crate::buffer::Primitive::GeometryPrim).
+    ///
     /// # Errors
     ///
     /// Returns an error if the input shader is not a geometry shader or is
@@ -80,6 +153,94 @@ impl Program {
             ctru_sys::shaderProgramSetGsh(&mut self.program, geometry_shader.as_raw(), stride)
         };
 
+        if ret == 0 {
+            let outputs = self.vertex_output_count();
+            self.geometry_vertices =
+                (outputs != 0 && stride % outputs == 0).then_some(stride / outputs);
+            Ok(())
+        } else {
+            Err(ctru::Error::from(ret))
+        }
+    }
+
+    /// Set the geometry shader for a given program, which will receive
+    /// `vertices_per_primitive` vertices from the vertex shader each time it runs
+    /// (e.g. 3 for a shader which processes triangles).
+    ///
+    /// This sets the [stride](Program::set_geometry_shader) to the number of
+    /// vertex shader outputs times `vertices_per_primitive`. Draw calls with
+    /// [`Primitive::GeometryPrim`](crate::buffer::Primitive::GeometryPrim) using
+    /// this program must then use a multiple of `vertices_per_primitive` vertices.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input shader is not a geometry shader or is
+    /// otherwise invalid, or if the resulting stride doesn't fit in a `u8`.
+    #[doc(alias = "shaderProgramSetGsh")]
+    pub fn set_geometry_shader_vertices(
+        &mut self,
+        geometry_shader: Entrypoint,
+        vertices_per_primitive: u8,
+    ) -> Result<(), ctru::Error> {
+        let stride = self
+            .vertex_output_count()
+            .checked_mul(vertices_per_primitive)
+            .ok_or_else(|| ctru::Error::Other("geometry shader stride is too large".into()))?;
+        self.set_geometry_shader(geometry_shader, stride)?;
+        self.geometry_vertices = Some(vertices_per_primitive);
+        Ok(())
+    }
+
+    /// Get the number of vertices the geometry shader receives for each
+    /// primitive, if this program has a geometry shader and the number is
+    /// known (see [`Program::set_geometry_shader_vertices`]).
+    pub fn geometry_vertices_per_primitive(&self) -> Option<u8> {
+        self.geometry_vertices
+    }
+
+    /// The number of shaders in this program: 2 with a geometry shader, 1
+    /// otherwise.
+    pub(crate) fn shader_count(&self) -> usize {
+        if self.program.geometryShader.is_null() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The number of output registers written by the vertex shader.
+    fn vertex_output_count(&self) -> u8 {
+        let vertex_instance = self.program.vertexShader;
+        assert!(
+            !vertex_instance.is_null(),
+            "vertex shader should never be null!"
+        );
+
+        // SAFETY: the DVLE is owned by the `Library` the shader was created from
+        unsafe { (*(*vertex_instance).dvle).outmapMask.count_ones() as u8 }
+    }
+
+    /// Replace the vertex shader of this program, keeping any geometry shader
+    /// that was set. This can be used with [`Library::from_file`] to iterate on
+    /// shaders without restarting the application.
+    ///
+    /// A program can only be modified while it is not bound, since binding it
+    /// shares it with the [`Instance`](crate::Instance). After a reload, bind the
+    /// program again for the new shader to take effect, and look up any uniform
+    /// indices again with [`Program::get_uniform`] as they may have changed.
+    ///
+    /// As with [`Program::new`], the [`Library`] of `vertex_shader` must outlive
+    /// this program.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input shader is not a vertex shader or is
+    /// otherwise invalid.
+    #[doc(alias = "shaderProgramSetVsh")]
+    pub fn reload_vertex(&mut self, vertex_shader: Entrypoint) -> Result<(), ctru::Error> {
+        let ret =
+            unsafe { ctru_sys::shaderProgramSetVsh(&mut self.program, vertex_shader.as_raw()) };
+
         if ret == 0 {
             Ok(())
         } else {
@@ -89,6 +250,10 @@ impl Program {
 
     /// Get the index of a uniform by name.
     ///
+    /// The index is tagged with the [`Kind`](uniform::Kind) of register the
+    /// uniform was declared with, based on the start register recorded in the
+    /// shader's uniform table.
+    ///
     /// # Errors
     ///
     /// * If the given `name` contains a null byte
@@ -113,11 +278,63 @@ impl Program {
         }
     }
 
+    /// Get a bitmask of the input registers (`v0`..`v15`) declared by the vertex shader.
+    pub(crate) fn input_register_mask(&self) -> u16 {
+        self.vertex_inputs()
+            .flat_map(|(_, e)| e.startReg..=e.endReg.min(0xF))
+            .fold(0, |mask, reg| mask | (1 << reg))
+    }
+
+    /// Get the input register of the vertex shader input named `name`, e.g.
+    /// `v0` for an input declared with `.in inpos v0` (or `.alias inpos v0`).
+    /// This can be used instead of hardcoding register numbers when setting up
+    /// [`attrib::Info`].
+    ///
+    /// Returns `None` if the vertex shader has no input with that name.
+    pub fn input_register(&self, name: &str) -> Option<attrib::Register> {
+        self.vertex_inputs()
+            .find(|(n, _)| n.to_bytes() == name.as_bytes())
+            .and_then(|(_, e)| attrib::Register::new(e.startReg).ok())
+    }
+
+    /// Iterate over the names and uniform table entries of the vertex shader's
+    /// input registers.
+    fn vertex_inputs(&self) -> impl Iterator<Item = (&CStr, &ctru_sys::DVLE_uniformEntry_s)> {
+        let vertex_instance = self.program.vertexShader;
+        assert!(
+            !vertex_instance.is_null(),
+            "vertex shader should never be null!"
+        );
+
+        // SAFETY: the DVLE is owned by the `Library` the shader was created from,
+        // and we only read from its uniform and symbol tables.
+        let (entries, symbols) = unsafe {
+            let dvle = &*(*vertex_instance).dvle;
+            (
+                std::slice::from_raw_parts(dvle.uniformTableData, dvle.uniformTableSize as usize),
+                dvle.symbolTableData,
+            )
+        };
+
+        // Input registers are stored in the uniform table as 0x00..0x10, see
+        // https://www.3dbrew.org/wiki/SHBIN#Uniform_Table_Entry
+        entries.iter().filter(|e| e.startReg < 0x10).map(move |e| {
+            // SAFETY: symbol offsets point to null-terminated names in the
+            // symbol table, which lives as long as the DVLE
+            let name = unsafe { CStr::from_ptr(symbols.add(e.symbolOfs.into())) };
+            (name, e)
+        })
+    }
+
     pub(crate) fn as_raw(self: &Pin<Arc<Self>>) -> *const ctru_sys::shaderProgram_s {
         &self.program
     }
 }
 
+fn missing_entrypoint() -> ctru::Error {
+    ctru::Error::Other("shader library has no entrypoint at the given index".into())
+}
+
 static_assertions::assert_impl_all!(Program: Send, Sync);
 static_assertions::assert_not_impl_any!(Program: Unpin);
 
@@ -132,7 +349,7 @@ impl Drop for Program {
 
 /// The type of a shader.
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Type {
     /// A vertex shader.
     Vertex = ctru_sys::GPU_VERTEX_SHADER,
@@ -153,8 +370,18 @@ impl From<Type> for u8 {
 /// This is the result of parsing a shader binary (`.shbin`), and the resulting
 /// [`Entrypoint`]s can be used as part of a [`Program`].
 #[doc(alias = "DVLB_s")]
-#[derive(Debug)]
-pub struct Library(NonNull<ctru_sys::DVLB_s>);
+pub struct Library(
+    NonNull<ctru_sys::DVLB_s>,
+    /// The shader binary, if we own it. The parsed DVLB points into this data,
+    /// so it must live as long as the library.
+    Option<Box<[u32]>>,
+);
+
+impl std::fmt::Debug for Library {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Library").field(&self.0).finish()
+    }
+}
 
 // Safety: we are the owner of the DVLB
 unsafe impl Send for Library {}
@@ -166,7 +393,9 @@ impl Library {
     /// # Errors
     ///
     /// An error is returned if the input data does not have an alignment of 4
-    /// (cannot be safely converted to `&[u32]`).
+    /// (cannot be safely converted to `&[u32]`). Shaders embedded with
+    /// [`include_shader!`](crate::macros::include_shader) or
+    /// [`include_shbin!`](crate::macros::include_shbin) are always aligned.
     #[doc(alias = "DVLB_ParseFile")]
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
         let aligned: &[u32] = bytemuck::try_cast_slice(bytes)?;
@@ -180,7 +409,33 @@ impl Library {
             )
         };
         let lib = NonNull::new(lib).ok_or(Box::new(super::Error::FailedToInitialize))?;
-        Ok(Self(lib))
+        Ok(Self(lib, None))
+    }
+
+    /// Read and parse a shader library from a compiled shader binary (`.shbin`)
+    /// file, e.g. to reload shaders at runtime during development.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file cannot be read or the shader binary is invalid.
+    #[doc(alias = "DVLB_ParseFile")]
+    #[cfg(feature = "std")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes_copied(&bytes)
+    }
+
+    /// Like [`Library::from_bytes`], but copies `bytes` into a buffer owned by
+    /// the library first, so it has no alignment requirement.
+    pub(crate) fn from_bytes_copied(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        // Copy into a u32 buffer to guarantee the alignment `from_bytes` needs
+        let mut data = vec![0u32; bytes.len().div_ceil(4)].into_boxed_slice();
+        bytemuck::cast_slice_mut::<_, u8>(&mut data)[..bytes.len()].copy_from_slice(bytes);
+
+        let mut lib = Self::from_bytes(bytemuck::cast_slice(&data))?;
+        // Moving the box doesn't move the data it points to, so the parsed DVLB stays valid
+        lib.1 = Some(data);
+        Ok(lib)
     }
 
     /// Get the number of [`Entrypoint`]s in this shader library.
@@ -208,6 +463,18 @@ impl Library {
             None
         }
     }
+
+    /// Get metadata about each [`Entrypoint`] in this library, in order, e.g.
+    /// to check that a shader binary has the expected shaders without creating
+    /// a [`Program`].
+    #[must_use]
+    pub fn describe(&self) -> Vec<EntrypointInfo> {
+        (0..self.len())
+            .filter_map(|i| self.get(i))
+            .map(|entrypoint| entrypoint.info())
+            .collect()
+    }
+
     pub fn get_uniform(&self, name: &str) -> Option<uniform::Index> {
         let dvle = unsafe { (*self.0.as_ptr()).DVLE.cast_const() };
         assert!(!dvle.is_null(), "dvle should not be null");
@@ -246,7 +513,81 @@ pub struct Entrypoint<'lib> {
 }
 
 impl<'lib> Entrypoint<'lib> {
+    /// Get metadata about this entrypoint.
+    #[must_use]
+    pub fn info(self) -> EntrypointInfo {
+        // SAFETY: the DVLE is owned by the library, which outlives `self`
+        let dvle = unsafe { &*self.ptr };
+
+        let ty = if dvle.type_ == ctru_sys::GEOMETRY_SHDR {
+            Type::Geometry
+        } else {
+            Type::Vertex
+        };
+        let geometry_mode = (ty == Type::Geometry).then(|| match dvle.gshMode {
+            ctru_sys::GSH_VARIABLE_PRIM => GeometryMode::VariablePrimitive {
+                vertices: dvle.gshVariableVtxNum,
+            },
+            ctru_sys::GSH_FIXED_PRIM => GeometryMode::FixedPrimitive {
+                start: dvle.gshFixedVtxStart,
+                vertices: dvle.gshFixedVtxNum,
+            },
+            _ => GeometryMode::Point,
+        });
+
+        EntrypointInfo {
+            ty,
+            output_mask: dvle.outmapMask.into(),
+            geometry_mode,
+        }
+    }
+
     fn as_raw(self) -> *mut ctru_sys::DVLE_s {
         self.ptr
     }
 }
+
+/// Metadata about an [`Entrypoint`], see [`Library::describe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntrypointInfo {
+    /// Whether this is a vertex or geometry shader.
+    pub ty: Type,
+    /// A bitmask of the output registers (`o0`..`o15`) written by the shader.
+    pub output_mask: u16,
+    /// How the geometry shader receives its input vertices, or `None` for
+    /// vertex shaders.
+    pub geometry_mode: Option<GeometryMode>,
+}
+
+impl EntrypointInfo {
+    /// The number of output registers written by the shader.
+    pub fn output_count(&self) -> u32 {
+        self.output_mask.count_ones()
+    }
+}
+
+/// How a geometry shader receives vertices from the vertex shader, as set with
+/// the `.gsh` directive in picasso.
+#[doc(alias = "DVLE_geoShaderMode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryMode {
+    /// The geometry shader runs once per vertex.
+    #[doc(alias = "GSH_POINT")]
+    Point,
+    /// The geometry shader runs on primitives with a variable number of
+    /// vertices, the first `vertices` of which are always present.
+    #[doc(alias = "GSH_VARIABLE_PRIM")]
+    VariablePrimitive {
+        /// The number of vertices always passed to the geometry shader.
+        vertices: u8,
+    },
+    /// The geometry shader runs on primitives with a fixed number of vertices,
+    /// which are written to consecutive uniform registers.
+    #[doc(alias = "GSH_FIXED_PRIM")]
+    FixedPrimitive {
+        /// The first float uniform register the vertices are written to.
+        start: u8,
+        /// The number of vertices per primitive.
+        vertices: u8,
+    },
+}
@@ -0,0 +1,202 @@
+//! Batched drawing of textured 2D quads ("sprites"), e.g. for UI or 2D games.
+//!
+//! A [`SpriteBatch`] collects [`Sprite`]s, then draws them all with a single
+//! draw call using a built-in shader and an orthographic projection in pixel
+//! coordinates, with the origin in the top-left corner of the screen and +Y
+//! pointing down.
+
+use crate::builtin::BuiltinShader;
+use crate::color::Color;
+use crate::macros::include_shader;
+use crate::math::{ClipPlanes, Matrix4, Projection};
+use crate::texenv::{CombineFunc, Mode, Source, Stage};
+use crate::texture::Tex;
+use crate::{attrib, Instance, Result};
+
+static SHADER_BYTES: &[u8] = include_shader!("sprite/shader.pica");
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Vertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+/// A textured rectangle drawn by a [`SpriteBatch`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    /// The position of the top-left corner, in pixels.
+    pub position: [f32; 2],
+    /// The width and height, in pixels.
+    pub size: [f32; 2],
+    /// The texture coordinates of the top-left and bottom-right corners, as
+    /// `[u0, v0, u1, v1]`. `v = 0.0` is the first row of the texture data as
+    /// uploaded with [`Tex::upload_linear`].
+    pub uv: [f32; 4],
+    /// A color the texture is multiplied with. Use [`Color::new(1.0, 1.0, 1.0)`](Color::new)
+    /// to draw the texture unchanged.
+    pub tint: Color,
+}
+
+impl Sprite {
+    /// A sprite showing the whole texture, untinted.
+    pub fn new(position: [f32; 2], size: [f32; 2]) -> Self {
+        Self {
+            position,
+            size,
+            uv: [0.0, 0.0, 1.0, 1.0],
+            tint: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn vertices(&self) -> [Vertex; 6] {
+        let [x, y] = self.position;
+        let [w, h] = self.size;
+        let [u0, v0, u1, v1] = self.uv;
+        let Color { r, g, b, a } = self.tint;
+        let vertex = |pos, uv| Vertex {
+            pos,
+            uv,
+            color: [r, g, b, a],
+        };
+
+        let top_left = vertex([x, y], [u0, v0]);
+        let top_right = vertex([x + w, y], [u1, v0]);
+        let bottom_left = vertex([x, y + h], [u0, v1]);
+        let bottom_right = vertex([x + w, y + h], [u1, v1]);
+        [
+            top_left,
+            bottom_left,
+            bottom_right,
+            top_left,
+            bottom_right,
+            top_right,
+        ]
+    }
+}
+
+/// A helper for drawing many [`Sprite`]s sharing a texture.
+///
+/// The vertex data for each flush is kept alive until
+/// [`SpriteBatch::next_frame`] is called, since the GPU reads it while
+/// rendering the frame.
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use citro3d::sprite::{Sprite, SpriteBatch};
+/// # use citro3d::texture::{Tex, TexFormat};
+/// # let mut instance = citro3d::Instance::new().unwrap();
+/// let texture = Tex::new_simple(64, 64, TexFormat::Rgba8).unwrap();
+/// let mut sprites = SpriteBatch::new().unwrap();
+///
+/// instance
+///     .render_frame_with(|instance| {
+///         sprites.next_frame();
+///         sprites.add(Sprite::new([10.0, 10.0], [64.0, 64.0]));
+///         sprites.add(Sprite::new([100.0, 10.0], [32.0, 32.0]));
+///         sprites.flush(instance, &texture, [400.0, 240.0]).unwrap();
+///     })
+///     .unwrap();
+/// ```
+pub struct SpriteBatch {
+    shader: BuiltinShader<Vertex>,
+    pending: Vec<Vertex>,
+}
+
+impl SpriteBatch {
+    /// Load the built-in shader and create an empty sprite batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FailedToInitialize`](crate::Error::FailedToInitialize)
+    /// if the shader could not be loaded.
+    pub fn new() -> Result<Self> {
+        let shader = BuiltinShader::load(
+            SHADER_BYTES,
+            &[
+                (attrib::Register::new(0)?, attrib::Format::Float, 2),
+                (attrib::Register::new(1)?, attrib::Format::Float, 2),
+                (attrib::Register::new(2)?, attrib::Format::Float, 4),
+            ],
+        )?;
+
+        Ok(Self {
+            shader,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Add a sprite to be drawn by the next [`SpriteBatch::flush`].
+    pub fn add(&mut self, sprite: Sprite) {
+        self.pending.extend(sprite.vertices());
+    }
+
+    /// Get the number of sprites waiting to be drawn.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len() / 6
+    }
+
+    /// Whether there are no sprites waiting to be drawn.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Free the vertex data of previous flushes. Call this at the start of each
+    /// frame, once the GPU has finished rendering the previous one.
+    pub fn next_frame(&mut self) {
+        self.shader.next_frame();
+    }
+
+    /// Draw all sprites added since the last flush with `texture`, on a screen
+    /// (e.g. `[400.0, 240.0]` for the top screen) which is `screen_size` pixels
+    /// wide and high.
+    ///
+    /// The previously bound shader program, vertex attributes and first texture
+    /// combiner stage are restored afterwards. `texture` stays bound to texture
+    /// unit 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `texture` could not be bound (see [`Tex::bind`]), or
+    /// the vertex data could not be sent to the GPU.
+    pub fn flush(
+        &mut self,
+        instance: &mut Instance,
+        texture: &Tex,
+        [width, height]: [f32; 2],
+    ) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let projection: Matrix4 = Projection::orthographic(
+            0.0..width,
+            // Flipped so +Y points down the screen
+            height..0.0,
+            ClipPlanes {
+                near: -1.0,
+                far: 1.0,
+            },
+        )
+        .into();
+        let pending = std::mem::take(&mut self.pending);
+        texture.bind(0)?;
+        self.shader
+            .draw(instance, &projection, &pending, |instance| {
+                instance
+                    .texenv(Stage::new(0).unwrap())
+                    .src(
+                        Mode::BOTH,
+                        Source::Texture0,
+                        Some(Source::PrimaryColor),
+                        None,
+                    )
+                    .func(Mode::BOTH, CombineFunc::Modulate);
+            })
+    }
+}
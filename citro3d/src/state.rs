@@ -0,0 +1,125 @@
+//! Saving and restoring global GPU state.
+//!
+//! Most `citro3d` state (texture combiners, vertex attributes, the bound shader
+//! program, ...) is global, so code which changes it for its own rendering pass
+//! will affect every draw call that comes after it. A [`StateGuard`] records the
+//! current state and restores it when dropped, so e.g. a UI library can change
+//! whatever it needs without leaking those changes into the rest of the frame.
+
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::{attrib, blend, render, shader, texenv, Instance};
+
+/// A guard which restores the GPU state saved by [`Instance::save_state`] when
+/// it is dropped. The guard can be used in place of the [`Instance`] it was
+/// created from.
+///
+/// The following state is restored:
+///
+/// * All [`texenv::MAX_STAGES`] texture combiner stages
+/// * The [`attrib::Info`] in use
+/// * The bound [`shader::Program`], if there was one
+/// * The depth test and [write mask](Instance::set_write_mask)
+/// * The [blending](Instance::set_blend)
+/// * The [cull mode](Instance::set_cull_mode)
+/// * The [scissor test](Instance::set_scissor)
+///
+/// Other render state, e.g. the alpha and stencil tests or the blend color,
+/// is not saved, since `citro3d` has no way to read it back and the
+/// [`Instance`] doesn't track it.
+///
+/// If no program was bound when the state was saved, there's no way to
+/// unbind one, so a program bound through the guard stays bound afterwards.
+/// The [`Instance`] keeps a reference to it until another program is bound,
+/// so it must not depend on anything which could be freed in the meantime,
+/// e.g. by owning its [`shader::Library`] (see [`shader::Program::from_library`]).
+///
+/// # Example
+///
+/// ```
+/// # let _runner = test_runner::GdbRunner::default();
+/// # use citro3d::texenv::{CombineFunc, Mode, Stage};
+/// # let mut instance = citro3d::Instance::new().unwrap();
+/// {
+///     let mut state = instance.save_state();
+///     state
+///         .texenv(Stage::new(0).unwrap())
+///         .func(Mode::BOTH, CombineFunc::Modulate);
+///     // ... draw something ...
+/// }
+/// // The combiner is back to how it was before `save_state`
+/// ```
+#[must_use = "state is restored as soon as the guard is dropped"]
+pub struct StateGuard<'instance> {
+    instance: &'instance mut Instance,
+    texenvs: [citro3d_sys::C3D_TexEnv; texenv::MAX_STAGES],
+    attr_info: Option<attrib::Info>,
+    program: Option<Pin<Arc<shader::Program>>>,
+    depth_test: render::DepthTest,
+    blend: blend::Blend,
+    cull_mode: render::CullMode,
+    scissor: render::Scissor,
+}
+
+impl<'instance> StateGuard<'instance> {
+    pub(crate) fn new(instance: &'instance mut Instance) -> Self {
+        let texenvs = std::array::from_fn(|i| {
+            // SAFETY: all stage indices are valid, and the returned pointer is
+            // to a combiner in the global `citro3d` context
+            unsafe { *citro3d_sys::C3D_GetTexEnv(i as _) }
+        });
+
+        Self {
+            texenvs,
+            attr_info: instance.attr_info(),
+            program: instance.current_program().cloned(),
+            depth_test: instance.depth_test,
+            blend: instance.blend(),
+            cull_mode: instance.cull_mode(),
+            scissor: instance.scissor(),
+            instance,
+        }
+    }
+}
+
+impl Deref for StateGuard<'_> {
+    type Target = Instance;
+
+    fn deref(&self) -> &Self::Target {
+        self.instance
+    }
+}
+
+impl DerefMut for StateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.instance
+    }
+}
+
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        for (i, texenv) in self.texenvs.iter().enumerate() {
+            // SAFETY: see `new`. Getting the combiner also marks it as dirty,
+            // so the restored state is sent to the GPU before the next draw.
+            unsafe { *citro3d_sys::C3D_GetTexEnv(i as _) = *texenv };
+        }
+
+        if let Some(attr_info) = &self.attr_info {
+            self.instance.set_attr_info(attr_info);
+        }
+
+        self.instance.depth_test = self.depth_test;
+        self.instance.depth_test.apply();
+        self.instance.set_blend(self.blend);
+        self.instance.set_cull_mode(self.cull_mode);
+        self.instance.set_scissor(self.scissor);
+
+        // If there was no program bound before, there's no way to unbind the
+        // current one, so it is left in place. The instance keeps it alive.
+        if let Some(program) = self.program.take() {
+            self.instance.bind_program(program);
+        }
+    }
+}
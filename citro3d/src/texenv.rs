@@ -1,34 +1,95 @@
 //! Texture combiner support. See <https://www.khronos.org/opengl/wiki/Texture_Combiners>
 //! for more details.
+//!
+//! The [`MAX_STAGES`] combiner stages are applied in order, and each stage can
+//! read the output of the stage before it with [`Source::Previous`]. For example,
+//! to modulate a texture by the vertex color and then add a constant color:
+//!
+//! ```
+//! # let _runner = test_runner::GdbRunner::default();
+//! # use citro3d::texenv::{CombineFunc, Mode, Source, Stage};
+//! # let mut instance = citro3d::Instance::new().unwrap();
+//! instance
+//!     .texenv(Stage::new(0).unwrap())
+//!     .src(Mode::BOTH, Source::Texture0, Some(Source::PrimaryColor), None)
+//!     .func(Mode::BOTH, CombineFunc::Modulate);
+//!
+//! // Stage 1 reads the result of stage 0
+//! instance
+//!     .texenv(Stage::new(1).unwrap())
+//!     .src(Mode::RGB, Source::Previous, Some(Source::Constant), None)
+//!     .func(Mode::RGB, CombineFunc::Add);
+//! ```
+//!
+//! # Fragment lighting
+//!
+//! The [fragment lighting](crate::light) results are only used if a combiner
+//! stage reads them. The lighting has two outputs:
+//!
+//! * [`Source::FragmentPrimaryColor`]: the diffuse and ambient lighting, which
+//!   is usually multiplied with the surface color (e.g. a texture).
+//! * [`Source::FragmentSecondaryColor`]: the specular highlights, which should
+//!   be added on top afterwards, not multiplied with the surface color.
+//!
+//! For a lit, textured surface this takes two stages:
+//!
+//! ```
+//! # let _runner = test_runner::GdbRunner::default();
+//! # use citro3d::texenv::{CombineFunc, Mode, Source, Stage};
+//! # let mut instance = citro3d::Instance::new().unwrap();
+//! instance
+//!     .texenv(Stage::new(0).unwrap())
+//!     .src(Mode::BOTH, Source::Texture0, Some(Source::FragmentPrimaryColor), None)
+//!     .func(Mode::BOTH, CombineFunc::Modulate);
+//!
+//! instance.texenv(Stage::new(1).unwrap()).add_specular();
+//! ```
+//!
+//! For untextured surfaces, [`TexEnv::lighting_passthrough`] does both in one
+//! stage.
 
 use std::ptr::NonNull;
 
 use bitflags::bitflags;
 
+use crate::color::Color;
+
 /// A texture combiner, also called a "texture environment" (hence the struct name).
 /// See also [`texenv.h` documentation](https://oreo639.github.io/citro3d/texenv_8h.html).
 #[doc(alias = "C3D_TexEnv")]
 #[derive(Debug)]
-pub struct TexEnv(NonNull<citro3d_sys::C3D_TexEnv>);
+pub struct TexEnv(NonNull<citro3d_sys::C3D_TexEnv>, Stage);
 
 /// We don't own the pointer but we are the only access safe code will have to it
 /// and there is only 1 of us for each pointer in that case
 unsafe impl Send for TexEnv {}
 unsafe impl Sync for TexEnv {}
 
+/// The number of texture combiner stages available on the GPU.
 // https://oreo639.github.io/citro3d/texenv_8h.html#a9eda91f8e7252c91f873b1d43e3728b6
-pub(crate) const TEXENV_COUNT: usize = 6;
+pub const MAX_STAGES: usize = 6;
 
 impl TexEnv {
-    pub(crate) fn new(stage: Stage) -> Self {
+    pub(crate) fn new(stage: Stage) -> crate::Result<Self> {
+        crate::ensure_initialized()?;
         let mut result = unsafe {
             Self(
                 NonNull::new(citro3d_sys::C3D_GetTexEnv(stage.0 as _))
                     .expect("failed to get textenv, this is a bug"),
+                stage,
             )
         };
         result.reset();
-        result
+        Ok(result)
+    }
+
+    pub(crate) fn as_raw_mut(&mut self) -> *mut citro3d_sys::C3D_TexEnv {
+        self.0.as_ptr()
+    }
+
+    /// Get the [`Stage`] this texture combiner is for.
+    pub fn stage(&self) -> Stage {
+        self.1
     }
 
     /// Re-initialize the texture combiner to its default state.
@@ -45,6 +106,11 @@ impl TexEnv {
     /// - `mode`: which [`Mode`]\(s) to set the sourc operand(s) for.
     /// - `source0`: the first [`Source`] operand to the texture combiner
     /// - `source1` and `source2`: optional additional [`Source`] operands to use
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is the first stage and any of the sources is
+    /// [`Source::Previous`], since there is no previous stage to read from.
     #[doc(alias = "C3D_TexEnvSrc")]
     pub fn src(
         &mut self,
@@ -53,6 +119,10 @@ impl TexEnv {
         source1: Option<Source>,
         source2: Option<Source>,
     ) -> &mut Self {
+        for source in [Some(source0), source1, source2].into_iter().flatten() {
+            self.check_source(source);
+        }
+
         unsafe {
             citro3d_sys::C3D_TexEnvSrc(
                 self.0.as_ptr(),
@@ -65,6 +135,94 @@ impl TexEnv {
         self
     }
 
+    /// Set the first source operand for the given [`Mode`]\(s) to be the output
+    /// of the previous stage ([`Source::Previous`]), leaving the other operands
+    /// unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is the first stage, since there is no previous stage to
+    /// read from.
+    pub fn chain_previous(&mut self, mode: Mode) -> &mut Self {
+        self.check_source(Source::Previous);
+
+        let chain = |sources: u16| (sources & !0xF) | Source::Previous as u16;
+        // SAFETY: we are the only ones with access to this stage's combiner
+        let raw = unsafe { self.0.as_mut() };
+        if mode.contains(Mode::RGB) {
+            raw.srcRgb = chain(raw.srcRgb);
+        }
+        if mode.contains(Mode::ALPHA) {
+            raw.srcAlpha = chain(raw.srcAlpha);
+        }
+        self
+    }
+
+    fn check_source(&self, source: Source) {
+        assert!(
+            self.1.index() != 0 || !matches!(source, Source::Previous),
+            "texenv stage 0 cannot use `Source::Previous`, there is no previous stage",
+        );
+    }
+
+    /// Set the constant color used by [`Source::Constant`] for this stage.
+    #[doc(alias = "C3D_TexEnvColor")]
+    pub fn color(&mut self, color: Color) -> &mut Self {
+        unsafe {
+            citro3d_sys::C3D_TexEnvColor(self.0.as_ptr(), color.to_packed_abgr());
+        }
+        self
+    }
+
+    /// Configure this stage to output the result of the fragment lighting set up
+    /// with [`LightEnv`](crate::light::LightEnv), i.e. the sum of
+    /// [`Source::FragmentPrimaryColor`] (diffuse and ambient) and
+    /// [`Source::FragmentSecondaryColor`] (specular). Without a stage like this
+    /// the lighting results are never used, so lit objects usually end up black
+    /// or unlit.
+    ///
+    /// This is normally the first stage. Later stages can then use
+    /// [`Source::Previous`] to combine the lit color with e.g. a texture.
+    pub fn lighting_passthrough(&mut self) -> &mut Self {
+        self.src(
+            Mode::BOTH,
+            Source::FragmentPrimaryColor,
+            Some(Source::FragmentSecondaryColor),
+            None,
+        )
+        .func(Mode::BOTH, CombineFunc::Add)
+    }
+
+    /// Read back the current configuration of this stage, e.g. to debug the
+    /// combiner setup.
+    #[must_use]
+    pub fn describe(&self) -> TexEnvDesc {
+        // SAFETY: we are the only ones with access to this stage's combiner
+        TexEnvDesc::from_raw(unsafe { self.0.as_ref() })
+    }
+
+    /// Configure this stage to add the specular lighting
+    /// ([`Source::FragmentSecondaryColor`]) to the color of the previous stage,
+    /// keeping its alpha. This is usually the stage after the diffuse lighting
+    /// was combined with the surface color, see the
+    /// [module documentation](self#fragment-lighting).
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is the first stage, since there is no previous stage to
+    /// read from.
+    pub fn add_specular(&mut self) -> &mut Self {
+        self.src(
+            Mode::RGB,
+            Source::Previous,
+            Some(Source::FragmentSecondaryColor),
+            None,
+        )
+        .func(Mode::RGB, CombineFunc::Add)
+        .src(Mode::ALPHA, Source::Previous, None, None)
+        .func(Mode::ALPHA, CombineFunc::Replace)
+    }
+
     /// Configure the texture combination function.
     ///
     /// # Parameters
@@ -97,7 +255,7 @@ bitflags! {
 /// A source operand of a [`TexEnv`]'s texture combination.
 #[doc(alias = "GPU_TEVSRC")]
 #[allow(missing_docs)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 #[non_exhaustive]
 pub enum Source {
@@ -116,7 +274,7 @@ pub enum Source {
 /// The combination function to apply to the [`TexEnv`] operands.
 #[doc(alias = "GPU_COMBINEFUNC")]
 #[allow(missing_docs)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 #[non_exhaustive]
 pub enum CombineFunc {
@@ -131,6 +289,85 @@ pub enum CombineFunc {
     // Dot3Rgba = ctru_sys::GPU_DOT3_RGBA,
 }
 
+impl Source {
+    fn from_raw(raw: u16) -> Option<Self> {
+        Some(match raw as u8 {
+            ctru_sys::GPU_PRIMARY_COLOR => Self::PrimaryColor,
+            ctru_sys::GPU_FRAGMENT_PRIMARY_COLOR => Self::FragmentPrimaryColor,
+            ctru_sys::GPU_FRAGMENT_SECONDARY_COLOR => Self::FragmentSecondaryColor,
+            ctru_sys::GPU_TEXTURE0 => Self::Texture0,
+            ctru_sys::GPU_TEXTURE1 => Self::Texture1,
+            ctru_sys::GPU_TEXTURE2 => Self::Texture2,
+            ctru_sys::GPU_TEXTURE3 => Self::Texture3,
+            ctru_sys::GPU_PREVIOUS_BUFFER => Self::PreviousBuffer,
+            ctru_sys::GPU_CONSTANT => Self::Constant,
+            ctru_sys::GPU_PREVIOUS => Self::Previous,
+            _ => return None,
+        })
+    }
+}
+
+impl CombineFunc {
+    fn from_raw(raw: u16) -> Option<Self> {
+        Some(match raw as u8 {
+            ctru_sys::GPU_REPLACE => Self::Replace,
+            ctru_sys::GPU_MODULATE => Self::Modulate,
+            ctru_sys::GPU_ADD => Self::Add,
+            ctru_sys::GPU_ADD_SIGNED => Self::AddSigned,
+            ctru_sys::GPU_INTERPOLATE => Self::Interpolate,
+            ctru_sys::GPU_SUBTRACT => Self::Subtract,
+            ctru_sys::GPU_DOT3_RGB => Self::Dot3Rgb,
+            _ => return None,
+        })
+    }
+}
+
+/// The configuration of a [`TexEnv`], as returned by [`TexEnv::describe`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TexEnvDesc {
+    /// The configuration for the color channels.
+    pub rgb: ChannelDesc,
+    /// The configuration for the alpha channel.
+    pub alpha: ChannelDesc,
+    /// The constant color used by [`Source::Constant`].
+    pub color: Color,
+}
+
+/// The configuration of one [`Mode`] of a [`TexEnv`], see [`TexEnvDesc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelDesc {
+    /// The three source operands, or `None` for values this crate doesn't know.
+    pub sources: [Option<Source>; 3],
+    /// The raw operand of each source, i.e. which channels of the source are
+    /// used. See `GPU_TEVOP_RGB` and `GPU_TEVOP_A` in `<3ds/gpu/enums.h>`.
+    pub operands: [u8; 3],
+    /// The combination function, or `None` for values this crate doesn't know.
+    pub func: Option<CombineFunc>,
+    /// The factor the result is multiplied with: 1, 2 or 4.
+    pub scale: u8,
+}
+
+impl TexEnvDesc {
+    fn from_raw(raw: &citro3d_sys::C3D_TexEnv) -> Self {
+        // SAFETY: both union fields are plain integers covering the same bits
+        let ops = unsafe { raw.__bindgen_anon_1.opAll };
+        let channel = |sources: u16, ops: u32, func: u16, scale: u16| ChannelDesc {
+            sources: std::array::from_fn(|i| Source::from_raw((sources >> (i * 4)) & 0xF)),
+            operands: std::array::from_fn(|i| ((ops >> (i * 4)) & 0xF) as u8),
+            func: CombineFunc::from_raw(func),
+            // See GPU_TEVSCALE in <3ds/gpu/enums.h>
+            scale: 1 << (scale & 0x3).min(2),
+        };
+        let [r, g, b, a] = raw.color.to_le_bytes();
+
+        Self {
+            rgb: channel(raw.srcRgb, ops, raw.funcRgb, raw.scaleRgb),
+            alpha: channel(raw.srcAlpha, ops >> 12, raw.funcAlpha, raw.scaleAlpha),
+            color: Color::from_rgba8(r, g, b, a),
+        }
+    }
+}
+
 /// A texture combination stage identifier. This index doubles as the order
 /// in which texture combinations will be applied.
 // (I think?)
@@ -140,6 +377,47 @@ pub struct Stage(pub(crate) usize);
 impl Stage {
     /// Get a stage index. Valid indices range from 0 to 5.
     pub fn new(index: usize) -> Option<Self> {
-        (index < 6).then_some(Self(index))
+        (index < MAX_STAGES).then_some(Self(index))
+    }
+
+    /// Iterate over all stages, in the order they are applied.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..MAX_STAGES).map(Self)
+    }
+
+    /// Get the index of this stage.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_raw_texenv() {
+        let mut raw: citro3d_sys::C3D_TexEnv = unsafe { std::mem::zeroed() };
+        raw.srcRgb = ((Source::Previous as u16) << 4) | Source::Texture0 as u16;
+        raw.srcAlpha = Source::Constant as u16;
+        raw.funcRgb = CombineFunc::Modulate as u16;
+        raw.funcAlpha = CombineFunc::Replace as u16;
+        raw.scaleRgb = 1;
+        raw.color = 0x8040_20FF;
+
+        let desc = TexEnvDesc::from_raw(&raw);
+        assert_eq!(
+            desc.rgb.sources,
+            [
+                Some(Source::Texture0),
+                Some(Source::Previous),
+                Some(Source::PrimaryColor)
+            ]
+        );
+        assert_eq!(desc.rgb.func, Some(CombineFunc::Modulate));
+        assert_eq!(desc.rgb.scale, 2);
+        assert_eq!(desc.alpha.sources[0], Some(Source::Constant));
+        assert_eq!(desc.alpha.scale, 1);
+        assert_eq!(desc.color, Color::from_rgba8(0xFF, 0x20, 0x40, 0x80));
     }
 }
@@ -1,7 +1,17 @@
 use std::ptr::NonNull;
+use std::sync::OnceLock;
 
 use citro3d_sys::C3D_TexCube;
 
+#[cfg(feature = "image")]
+use self::format::swizzle;
+pub(crate) use self::format::tiled_index;
+use self::format::write_tiled;
+pub use self::format::{Quality, TexFormat};
+use crate::color::Color;
+
+mod format;
+
 #[doc(alias = "GPU_TEXTURE_MODE_PARAM")]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u8)]
@@ -10,7 +20,10 @@ pub enum TexKind {
     Tex2d = ctru_sys::GPU_TEX_2D,
     /// Cube map texture
     CubeMap = ctru_sys::GPU_TEX_CUBE_MAP,
+    /// 2D shadow map, sampled with a depth comparison. See
+    /// [`Tex::set_shadow_comparison`].
     Shadow2d = ctru_sys::GPU_TEX_SHADOW_2D,
+    /// Cube shadow map, sampled with a depth comparison.
     ShadowCube = ctru_sys::GPU_TEX_SHADOW_CUBE,
 }
 
@@ -31,82 +44,6 @@ impl From<ctru_sys::GPU_TEXTURE_MODE_PARAM> for TexKind {
     }
 }
 
-/// Format of the texture bytes
-#[doc(alias = "GPU_TEXCOLOR")]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-#[repr(u8)]
-pub enum TexFormat {
-    /// 8-bit Red + 8-bit Green + 8-bit Blue + 8-bit Alpha
-    Rgba8 = ctru_sys::GPU_RGBA8,
-    /// 8-bit Red + 8-bit Green + 8-bit Blue
-    Rgb8 = ctru_sys::GPU_RGB8,
-    /// 5-bit Red + 5-bit Green + 5-bit Blue + 1-bit Alpha
-    Rgba5551 = ctru_sys::GPU_RGBA5551,
-    /// 5-bit Red + 6-bit Green + 5-bit Blue
-    Rgb565 = ctru_sys::GPU_RGB565,
-    /// 4-bit Red + 4-bit Green + 4-bit Blue + 4-bit Alpha
-    Rgba4 = ctru_sys::GPU_RGBA4,
-    /// 8-bit Luminance + 8-bit Alpha
-    La8 = ctru_sys::GPU_LA8,
-    /// 8-bit Hi + 8-bit Lo
-    HiLo8 = ctru_sys::GPU_HILO8,
-    /// 8-bit Luminance
-    L8 = ctru_sys::GPU_L8,
-    /// 8-bit Alpha
-    A8 = ctru_sys::GPU_A8,
-    /// 4-bit Luminance + 4-bit Alpha
-    La4 = ctru_sys::GPU_LA4,
-    /// 4-bit Luminance
-    L4 = ctru_sys::GPU_L4,
-    /// 4-bit Alpha
-    A4 = ctru_sys::GPU_A4,
-    /// ETC1 texture compression
-    Etc1 = ctru_sys::GPU_ETC1,
-    /// ETC1 texture compression + 4-bit Alpha
-    Etc1A4 = ctru_sys::GPU_ETC1A4,
-}
-
-impl TexFormat {
-    /// Bits needed to store each pixel
-    pub fn bits_per_pixel(&self) -> usize {
-        match self {
-            TexFormat::Rgba8 => 32,
-            TexFormat::Rgb8 => 24,
-            TexFormat::Rgba5551
-            | TexFormat::Rgb565
-            | TexFormat::Rgba4
-            | TexFormat::La8
-            | TexFormat::HiLo8 => 16,
-            TexFormat::L8 | TexFormat::A8 | TexFormat::La4 | TexFormat::Etc1A4 => 8,
-            TexFormat::L4 | TexFormat::A4 | TexFormat::Etc1 => 4,
-        }
-    }
-}
-
-impl TryFrom<ctru_sys::GPU_TEXCOLOR> for TexFormat {
-    type Error = super::Error;
-
-    fn try_from(value: ctru_sys::GPU_TEXCOLOR) -> Result<Self, Self::Error> {
-        match value {
-            ctru_sys::GPU_RGBA8 => Ok(Self::Rgba8),
-            ctru_sys::GPU_RGB8 => Ok(Self::Rgb8),
-            ctru_sys::GPU_RGBA5551 => Ok(Self::Rgba5551),
-            ctru_sys::GPU_RGB565 => Ok(Self::Rgb565),
-            ctru_sys::GPU_RGBA4 => Ok(Self::Rgba4),
-            ctru_sys::GPU_LA8 => Ok(Self::La8),
-            ctru_sys::GPU_HILO8 => Ok(Self::HiLo8),
-            ctru_sys::GPU_L8 => Ok(Self::L8),
-            ctru_sys::GPU_A8 => Ok(Self::A8),
-            ctru_sys::GPU_LA4 => Ok(Self::La4),
-            ctru_sys::GPU_L4 => Ok(Self::L4),
-            ctru_sys::GPU_A4 => Ok(Self::A4),
-            ctru_sys::GPU_ETC1 => Ok(Self::Etc1),
-            ctru_sys::GPU_ETC1A4 => Ok(Self::Etc1A4),
-            _ => Err(super::Error::NotFound),
-        }
-    }
-}
-
 #[doc(alias = "GPU_TEXTURE_FILTER_PARAM")]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u8)]
@@ -117,6 +54,16 @@ pub enum TextureFilterParam {
     Linear = ctru_sys::GPU_LINEAR,
 }
 
+impl TextureFilterParam {
+    fn from_bit(bit: u32) -> Self {
+        if bit & 0x1 == 0 {
+            Self::Nearest
+        } else {
+            Self::Linear
+        }
+    }
+}
+
 #[doc(alias = "GPU_TEXTURE_WRAP_PARAM")]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u8)]
@@ -131,6 +78,17 @@ pub enum TextureWrapParam {
     MirroredRepeat = ctru_sys::GPU_MIRRORED_REPEAT,
 }
 
+impl TextureWrapParam {
+    fn from_bits(bits: u32) -> Self {
+        match bits & 0x3 {
+            0 => Self::ClampToEdge,
+            1 => Self::ClampToBorder,
+            2 => Self::Repeat,
+            _ => Self::MirroredRepeat,
+        }
+    }
+}
+
 #[doc(alias = "C3D_TexInitParams")]
 pub struct TexParams {
     use_vram: bool,
@@ -139,6 +97,22 @@ pub struct TexParams {
     format: TexFormat,
     kind: TexKind,
     cube: Option<C3D_TexCube>,
+    vram_bank: Option<VramBank>,
+}
+
+/// One of the two 3MB halves of VRAM. The GPU can read from one bank while
+/// writing to the other, so e.g. keeping textures and the render targets they
+/// are drawn to in different banks can improve fill rate.
+#[doc(alias = "vramAllocPos")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VramBank {
+    /// The first 3MB of VRAM.
+    A = ctru_sys::VRAM_ALLOC_A,
+    /// The last 3MB of VRAM.
+    B = ctru_sys::VRAM_ALLOC_B,
+    /// Whichever bank has space, which is what [`TexParams::use_vram`] uses.
+    Any = ctru_sys::VRAM_ALLOC_ANY,
 }
 
 impl TexParams {
@@ -151,6 +125,7 @@ impl TexParams {
             format: TexFormat::Rgba8,
             kind: TexKind::Tex2d,
             cube: None,
+            vram_bank: None,
         }
     }
 
@@ -160,6 +135,18 @@ impl TexParams {
         self
     }
 
+    /// Store the pixels in the given VRAM bank. This implies
+    /// [`use_vram(true)`](TexParams::use_vram).
+    ///
+    /// Only 2D textures can be placed in a specific bank; cube maps are always
+    /// allocated in whichever bank has space.
+    #[doc(alias = "vramAllocAt")]
+    pub fn vram_bank(mut self, bank: VramBank) -> Self {
+        self.use_vram = true;
+        self.vram_bank = Some(bank);
+        self
+    }
+
     pub fn width(mut self, v: u16) -> Self {
         self.width = v;
         self
@@ -175,16 +162,147 @@ impl TexParams {
         self.kind = TexKind::Tex2d;
         self
     }
+    /// Set to a 2D shadow map, see [`Tex::set_shadow_comparison`]. Shadow maps
+    /// store a 24-bit depth and 8-bit intensity per texel, so the format should
+    /// be [`TexFormat::Rgba8`].
+    pub fn make_shadow_2d(mut self) -> Self {
+        self.cube.take();
+        self.kind = TexKind::Shadow2d;
+        self
+    }
     /// Set texture format
     pub fn format(mut self, fmt: TexFormat) -> Self {
         self.format = fmt;
         self
     }
+
+    /// Whether `size` is a valid texture width or height. The PICA200 only
+    /// supports power-of-two texture sizes from 8 to 1024 (inclusive), for all
+    /// formats. Textures don't need to be square, e.g. 512x256 is fine.
+    ///
+    /// Note that this doesn't apply to render targets, which can have any size
+    /// that is a multiple of 8 (e.g. the 400x240 top screen).
+    pub fn is_valid_size(size: u16) -> bool {
+        (8..=1024).contains(&size) && size.is_power_of_two()
+    }
+
+    /// The number of bytes needed to store the texture data.
+    fn data_size(&self) -> usize {
+        let faces = match self.kind {
+            TexKind::CubeMap | TexKind::ShadowCube => 6,
+            TexKind::Tex2d | TexKind::Shadow2d => 1,
+        };
+        faces * self.width as usize * self.height as usize * self.format.bits_per_pixel() / 8
+    }
+}
+
+#[cfg(feature = "image")]
+impl TexParams {
+    /// Create a cube map texture (e.g. for a skybox) from six square images of
+    /// the same size. The faces are given in the same order as OpenGL:
+    /// +X, -X, +Y, -Y, +Z, -Z.
+    ///
+    /// The texture uses [`TexFormat::Rgba8`] if any of the images has an alpha
+    /// channel, and [`TexFormat::Rgb8`] otherwise.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if the images are not
+    ///   all square with the same [valid size](TexParams::is_valid_size)
+    /// * Any error returned by [`Tex::new`]
+    #[doc(alias = "C3D_TexLoadImage")]
+    pub fn cube_from_images(images: [&image::DynamicImage; 6]) -> crate::Result<Tex> {
+        let size = images[0].width();
+        if images
+            .iter()
+            .any(|img| img.width() != size || img.height() != size)
+        {
+            return Err(crate::Error::InvalidSize);
+        }
+        let size: u16 = size.try_into()?;
+
+        let has_alpha = images.iter().any(|img| img.color().has_alpha());
+        let format = TexFormat::best_for(has_alpha, false, Quality::High);
+        let tex = Tex::new(Self {
+            use_vram: false,
+            width: size,
+            height: size,
+            format,
+            kind: TexKind::CubeMap,
+            // Filled in with the face pointers by `C3D_TexInitWithParams`
+            cube: Some(unsafe { core::mem::zeroed() }),
+            vram_bank: None,
+        })?;
+
+        for (face, img) in images.into_iter().enumerate() {
+            // The GPU stores colour components in reverse order
+            let pixels: Vec<u8> = if has_alpha {
+                img.to_rgba8()
+                    .pixels()
+                    .flat_map(|p| {
+                        let [r, g, b, a] = p.0;
+                        [a, b, g, r]
+                    })
+                    .collect()
+            } else {
+                img.to_rgb8()
+                    .pixels()
+                    .flat_map(|p| {
+                        let [r, g, b] = p.0;
+                        [b, g, r]
+                    })
+                    .collect()
+            };
+            let data = swizzle(
+                &pixels,
+                size.into(),
+                size.into(),
+                format.bits_per_pixel() / 8,
+            );
+
+            unsafe {
+                citro3d_sys::C3D_TexLoadImage(
+                    tex.as_raw().cast_mut(),
+                    data.as_ptr().cast(),
+                    face as ctru_sys::GPU_TEXFACE,
+                    0,
+                );
+            }
+        }
+
+        Ok(tex)
+    }
+}
+
+/// Get the number of bytes of VRAM currently free for allocation, e.g. by
+/// textures created with [`TexParams::use_vram`] or render targets.
+///
+/// Note that free VRAM may be fragmented, so an allocation smaller than this
+/// can still fail.
+#[doc(alias = "vramSpaceFree")]
+pub fn vram_free() -> usize {
+    unsafe { ctru_sys::vramSpaceFree() as usize }
 }
 
+/// Get the number of bytes of VRAM currently allocated.
+#[doc(alias = "vramSpaceFree")]
+pub fn vram_used() -> usize {
+    ctru_sys::OS_VRAM_SIZE as usize - vram_free()
+}
+
+// See GPU_TEXTURE_SHADOW_PARAM in <3ds/gpu/enums.h>
+const SHADOW_PARAM: u32 = 1 << 20;
+
+/// The size of the linear buffer [`Tex::upload_from_reader`] streams VRAM
+/// textures through, one DMA transfer at a time.
+const READER_CHUNK_SIZE: usize = 0x8000;
+
 #[doc(alias = "C3D_Tex")]
-#[derive(Debug)]
-pub struct Tex(NonNull<citro3d_sys::C3D_Tex>);
+pub struct Tex {
+    raw: NonNull<citro3d_sys::C3D_Tex>,
+    /// Whether the texture data should be freed on drop
+    owned: bool,
+}
 
 unsafe impl Send for Tex {}
 unsafe impl Sync for Tex {}
@@ -195,10 +313,35 @@ impl Tex {
     /// ```
     /// # let _runner = test_runner::GdbRunner::default();
     /// # use citro3d::texture::{Tex, TexParams};
-    /// let tex = Tex::new(TexParams::new_2d(480, 320).use_vram(true));
+    /// # let _instance = citro3d::Instance::new().unwrap();
+    /// let tex = Tex::new(TexParams::new_2d(512, 256).use_vram(true));
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if the width or height
+    ///   is not a [valid texture size](TexParams::is_valid_size)
+    /// * [`Error::VramExhausted`](crate::Error::VramExhausted) if the texture
+    ///   should use VRAM but there isn't enough free (see [`vram_free`])
+    /// * [`Error::FailedToInitialize`](crate::Error::FailedToInitialize) if no
+    ///   [`Instance`](crate::Instance) exists, or the texture could not be
+    ///   allocated
     #[doc(alias = "C3D_TexInitWithParams")]
     pub fn new(params: TexParams) -> super::Result<Self> {
+        crate::ensure_initialized()?;
+        if !TexParams::is_valid_size(params.width) || !TexParams::is_valid_size(params.height) {
+            return Err(super::Error::InvalidSize);
+        }
+        if params.use_vram && params.data_size() > vram_free() {
+            return Err(super::Error::VramExhausted);
+        }
+        // citro3d can only allocate VRAM in any bank, so for a specific bank the
+        // texture is created in linear memory and its data moved afterwards
+        let vram_bank = params
+            .vram_bank
+            .filter(|&bank| bank != VramBank::Any)
+            .filter(|_| matches!(params.kind, TexKind::Tex2d | TexKind::Shadow2d));
+
         let raw = unsafe {
             let mut raw = Box::<citro3d_sys::C3D_Tex>::new_uninit();
             assert!(
@@ -216,39 +359,212 @@ impl Tex {
                 _bitfield_1: Default::default(),
                 __bindgen_padding_0: Default::default(),
             };
-            cparams.set_onVram(params.use_vram);
+            cparams.set_onVram(params.use_vram && vram_bank.is_none());
             cparams.set_format(params.format as _);
             cparams.set_type(params.kind as _);
             cparams.set_maxLevel(0);
             if !citro3d_sys::C3D_TexInitWithParams(raw.as_mut_ptr(), cube, cparams) {
+                // VRAM may still be too fragmented even if there was enough free
+                return Err(if params.use_vram {
+                    super::Error::VramExhausted
+                } else {
+                    super::Error::FailedToInitialize
+                });
+            }
+            let mut raw = raw.assume_init();
+
+            if let Some(bank) = vram_bank {
+                let data = ctru_sys::vramAllocAt(params.data_size(), bank as _);
+                if data.is_null() {
+                    citro3d_sys::C3D_TexDelete(&mut *raw);
+                    return Err(super::Error::VramExhausted);
+                }
+                // `C3D_TexDelete` frees the data with `vramFree` since it is in VRAM
+                ctru_sys::linearFree(raw.__bindgen_anon_1.data);
+                raw.__bindgen_anon_1.data = data;
+            }
+            raw
+        };
+        let raw = NonNull::new(Box::into_raw(raw)).ok_or(super::Error::FailedToInitialize)?;
+        Ok(Self { raw, owned: true })
+    }
+
+    /// Create a new 2D texture in linear memory, without mipmaps. This is a
+    /// shorthand for [`Tex::new`] with [`TexParams::new_2d`] for the common case.
+    ///
+    /// ```
+    /// # let _runner = test_runner::GdbRunner::default();
+    /// # use citro3d::texture::{Tex, TexFormat};
+    /// # let _instance = citro3d::Instance::new().unwrap();
+    /// let tex = Tex::new_simple(64, 64, TexFormat::Rgba8).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if the width or height
+    ///   is not a [valid texture size](TexParams::is_valid_size)
+    /// * [`Error::FailedToInitialize`](crate::Error::FailedToInitialize) if no
+    ///   [`Instance`](crate::Instance) exists, or the texture could not be
+    ///   allocated
+    #[doc(alias = "C3D_TexInit")]
+    pub fn new_simple(width: u16, height: u16, format: TexFormat) -> super::Result<Self> {
+        crate::ensure_initialized()?;
+        if !TexParams::is_valid_size(width) || !TexParams::is_valid_size(height) {
+            return Err(super::Error::InvalidSize);
+        }
+
+        let raw = unsafe {
+            let mut raw = Box::<citro3d_sys::C3D_Tex>::new_uninit();
+            if !citro3d_sys::C3D_TexInit(raw.as_mut_ptr(), width, height, format as _) {
                 return Err(super::Error::FailedToInitialize);
             }
             raw.assume_init()
         };
         let raw = NonNull::new(Box::into_raw(raw)).ok_or(super::Error::FailedToInitialize)?;
-        Ok(Self(raw))
+        Ok(Self { raw, owned: true })
+    }
+
+    /// Create a texture filled with a single color, e.g. as a placeholder for
+    /// untextured geometry drawn with a shader or combiner setup which samples
+    /// a texture. The texture is 8x8 [`Rgba8`](TexFormat::Rgba8), the smallest
+    /// size the GPU supports, in linear memory.
+    ///
+    /// ```
+    /// # let _runner = test_runner::GdbRunner::default();
+    /// # use citro3d::color::Color;
+    /// # use citro3d::texture::Tex;
+    /// # let _instance = citro3d::Instance::new().unwrap();
+    /// let red = Tex::solid(Color::new(1.0, 0.0, 0.0)).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FailedToInitialize`](crate::Error::FailedToInitialize)
+    /// if no [`Instance`](crate::Instance) exists, or the texture could not be
+    /// allocated.
+    pub fn solid(color: Color) -> super::Result<Self> {
+        const SIZE: u16 = 8;
+
+        let tex = Self::new_simple(SIZE, SIZE, TexFormat::Rgba8)?;
+        let pixels = color.to_rgba8().repeat(SIZE as usize * SIZE as usize);
+        tex.upload_linear(&pixels)?;
+        Ok(tex)
+    }
+
+    /// Get a shared opaque white [solid](Tex::solid) texture. Binding this to
+    /// otherwise unused texture units makes sampling them well defined, and
+    /// leaves colors unchanged when combined with
+    /// [`Modulate`](crate::texenv::CombineFunc::Modulate).
+    ///
+    /// The texture is created on first use and lives for the rest of the
+    /// program, so calling this repeatedly doesn't allocate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FailedToInitialize`](crate::Error::FailedToInitialize)
+    /// if no [`Instance`](crate::Instance) exists, or the texture could not be
+    /// allocated the first time.
+    pub fn white() -> super::Result<&'static Self> {
+        static WHITE: OnceLock<Tex> = OnceLock::new();
+
+        if let Some(tex) = WHITE.get() {
+            return Ok(tex);
+        }
+        let tex = Self::solid(Color::new(1.0, 1.0, 1.0))?;
+        // If another thread got there first, our texture is dropped instead
+        Ok(WHITE.get_or_init(|| tex))
+    }
+
+    /// Wrap a texture that was created elsewhere, e.g. by `citro2d` or other C code.
+    ///
+    /// If `owned` is `true`, the texture data will be freed with `C3D_TexDelete`
+    /// when the returned [`Tex`] is dropped. The `C3D_Tex` struct pointed to by
+    /// `ptr` is never freed by this type either way.
+    ///
+    /// # Safety
+    ///
+    /// * `ptr` must point to a valid, initialized `C3D_Tex` which stays at the
+    ///   same address for the lifetime of the returned [`Tex`].
+    /// * No other code may mutate the texture while the returned [`Tex`] is alive.
+    /// * If `owned` is `true`, nothing else may free the texture data, and it must
+    ///   not be used after the returned [`Tex`] is dropped.
+    /// * If `owned` is `false`, the caller must keep the texture data alive until
+    ///   the returned [`Tex`] is dropped.
+    pub unsafe fn from_raw(ptr: NonNull<citro3d_sys::C3D_Tex>, owned: bool) -> Self {
+        Self { raw: ptr, owned }
     }
 
     pub fn kind(&self) -> TexKind {
-        unsafe { citro3d_sys::C3D_TexGetType(self.0.as_ptr()) }.into()
+        unsafe { citro3d_sys::C3D_TexGetType(self.raw.as_ptr()) }.into()
     }
 
     pub fn width(&self) -> u16 {
-        unsafe { self.0.as_ref().__bindgen_anon_2.__bindgen_anon_1.width }
+        unsafe { self.raw.as_ref().__bindgen_anon_2.__bindgen_anon_1.width }
     }
     pub fn height(&self) -> u16 {
-        unsafe { self.0.as_ref().__bindgen_anon_2.__bindgen_anon_1.height }
+        unsafe { self.raw.as_ref().__bindgen_anon_2.__bindgen_anon_1.height }
     }
 
     pub fn format(&self) -> TexFormat {
-        TexFormat::try_from(unsafe { self.0.as_ref().fmt() }).expect("unknown texture colour type")
+        TexFormat::try_from(unsafe { self.raw.as_ref().fmt() })
+            .expect("unknown texture colour type")
     }
 
+    /// Bind the texture to texture unit `unit_id` for following draw calls.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::FailedToInitialize`](crate::Error::FailedToInitialize) if no
+    ///   [`Instance`](crate::Instance) exists.
+    /// * [`Error::SamplingRenderTarget`](crate::Error::SamplingRenderTarget) if
+    ///   this is the texture of the currently selected
+    ///   [render target](crate::render::Target::from_texture), since the GPU
+    ///   can't sample a texture while drawing to it. Nothing is bound in that
+    ///   case.
     #[doc(alias = "C3D_TexBind")]
-    pub fn bind(&self, unit_id: i32) {
-        unsafe { citro3d_sys::C3D_TexBind(unit_id, self.as_raw().cast_mut()) }
+    pub fn bind(&self, unit_id: i32) -> crate::Result<()> {
+        crate::ensure_initialized()?;
+        if crate::render::is_drawing_to(self) {
+            return Err(crate::Error::SamplingRenderTarget);
+        }
+        unsafe { citro3d_sys::C3D_TexBind(unit_id, self.as_raw().cast_mut()) };
+        crate::render::set_bound(unit_id, self);
+        Ok(())
     }
 
+    /// Set the `(mag_filter, min_filter)` and `(wrap_s, wrap_t)` sampler state,
+    /// then bind the texture to `unit_id`.
+    ///
+    /// The PICA200 has no separate sampler objects like OpenGL: the sampling
+    /// parameters are registers of each texture unit, which `citro3d` fills in
+    /// from the bound texture's own parameters. So this changes the parameters
+    /// stored in the texture, and they stay in effect for later binds of it
+    /// until changed again. To sample the same texture differently in two
+    /// draws, call this before each draw. Binding the same texture to two units
+    /// at once with different sampler state is not possible.
+    ///
+    /// # Errors
+    ///
+    /// See [`Tex::bind`].
+    #[doc(alias = "C3D_TexBind")]
+    pub fn bind_with_sampler(
+        &self,
+        unit_id: i32,
+        (mag_filter, min_filter): (TextureFilterParam, TextureFilterParam),
+        (wrap_s, wrap_t): (TextureWrapParam, TextureWrapParam),
+    ) -> crate::Result<()> {
+        self.set_filter(mag_filter, min_filter);
+        self.set_wrap(wrap_s, wrap_t);
+        self.bind(unit_id)
+    }
+
+    /// Upload already tiled pixel data in the texture's format. See
+    /// [`Tex::upload_checked`] for a variant which checks the format of the
+    /// data, and [`Tex::upload_linear`] for untiled data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is too short for the texture.
     #[doc(alias = "C3D_TexUpload")]
     pub fn upload<T: AsRef<[u8]>>(&self, data: T) {
         let buf = data.as_ref();
@@ -260,6 +576,36 @@ impl Tex {
         unsafe { citro3d_sys::C3D_TexUpload(self.as_raw().cast_mut(), buf.as_ptr().cast()) }
     }
 
+    /// Like [`Tex::upload`], but check that `data` is in `declared_format`,
+    /// the texture's format, first. This catches mistakes like uploading
+    /// [`TexFormat::Rgb8`] data into a [`TexFormat::Rgba8`] texture, which
+    /// would otherwise produce garbled colors.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidFormat`](crate::Error::InvalidFormat) if
+    ///   `declared_format` is not the texture's format.
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if `data` is too
+    ///   short for the texture.
+    #[doc(alias = "C3D_TexUpload")]
+    pub fn upload_checked<T: AsRef<[u8]>>(
+        &self,
+        data: T,
+        declared_format: TexFormat,
+    ) -> crate::Result<()> {
+        if declared_format != self.format() {
+            return Err(crate::Error::InvalidFormat);
+        }
+        if data.as_ref().len() < self.data_len() {
+            return Err(crate::Error::InvalidSize);
+        }
+
+        self.upload(data);
+        Ok(())
+    }
+
+    /// Set the magnification and minification filters. For cube maps, this
+    /// applies to all six faces.
     #[doc(alias = "C3D_TexSetFilter")]
     pub fn set_filter(&self, mag_filter: TextureFilterParam, min_filter: TextureFilterParam) {
         unsafe {
@@ -271,45 +617,544 @@ impl Tex {
         }
     }
 
+    /// Set the wrapping mode for texture coordinates outside `0.0..=1.0`. For
+    /// cube maps, this applies to all six faces. Textures are created with
+    /// [`TextureWrapParam::ClampToEdge`] in both directions.
     #[doc(alias = "C3D_TexSetWrap")]
     pub fn set_wrap(&self, wrap_s: TextureWrapParam, wrap_t: TextureWrapParam) {
         unsafe { citro3d_sys::C3D_TexSetWrap(self.as_raw().cast_mut(), wrap_s as u8, wrap_t as u8) }
     }
+
+    /// Set whether sampling this texture compares the texture coordinate's
+    /// depth against the depth stored in the texture, instead of returning the
+    /// texel color. This is enabled for [shadow textures](TexKind::Shadow2d)
+    /// when they are created.
+    ///
+    /// With comparison enabled, sampling returns the shadow factor: the texel's
+    /// intensity where the fragment is lit (its depth is less than or equal to
+    /// the stored depth, minus the [bias](Tex::set_shadow_params)), and zero
+    /// where it is in shadow. The comparison function itself is fixed in the
+    /// hardware and, unlike the [`TestFunc`](crate::render::TestFunc) of the
+    /// depth test, can't be changed. Only texture unit 0 can sample shadow
+    /// textures, with the depth taken from the W coordinate of texture
+    /// coordinate 0.
+    pub fn set_shadow_comparison(&self, enabled: bool) {
+        // SAFETY: we only change the texture's sampling parameters, which are
+        // read by `C3D_TexBind`
+        unsafe {
+            let raw = &mut *self.as_raw().cast_mut();
+            if enabled {
+                raw.param |= SHADOW_PARAM;
+            } else {
+                raw.param &= !SHADOW_PARAM;
+            }
+        }
+    }
+
+    /// Whether sampling this texture does a depth comparison, see
+    /// [`Tex::set_shadow_comparison`].
+    pub fn shadow_comparison(&self) -> bool {
+        self.param() & SHADOW_PARAM != 0
+    }
+
+    /// Set the parameters of the depth comparison done when sampling shadow
+    /// textures: whether the depth is divided by the texture coordinate's W
+    /// (for perspective projected shadow maps), and the `bias` subtracted from
+    /// it to avoid self-shadowing ("shadow acne").
+    ///
+    /// These parameters are shared by all textures, since only texture unit 0
+    /// can sample shadow textures.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FailedToInitialize`](crate::Error::FailedToInitialize)
+    /// if no [`Instance`](crate::Instance) exists.
+    #[doc(alias = "C3D_TexShadowParams")]
+    pub fn set_shadow_params(perspective: bool, bias: f32) -> crate::Result<()> {
+        crate::ensure_initialized()?;
+        unsafe { citro3d_sys::C3D_TexShadowParams(perspective, bias) };
+        Ok(())
+    }
+
+    /// Set up a cube map for sampling with as few visible seams between faces
+    /// as possible, by clamping both texture coordinates to the edge of each
+    /// face. This is the default for new textures, so it is only needed if the
+    /// wrapping mode was changed, but it is the most common cause of skybox
+    /// seams: with [`TextureWrapParam::Repeat`], samples at the edge of a face
+    /// blend in texels from its opposite edge.
+    ///
+    /// Note that the PICA200 does not do seamless cube map filtering in
+    /// hardware: linear filtering never samples across faces. With clamping, the
+    /// edge texels of neighbouring faces meet without blending, which is
+    /// invisible as long as the face images match up at their edges.
+    pub fn set_seamless_cube(&self) {
+        self.set_wrap(TextureWrapParam::ClampToEdge, TextureWrapParam::ClampToEdge);
+    }
+
+    /// Set the magnification, minification and (optionally) mipmap filters.
+    /// Linear `min_filter` and `mip_filter` gives trilinear filtering.
+    ///
+    /// If `mip_filter` is `None`, the mipmap filter is left unchanged. Note that
+    /// the mipmap filter only has an effect on textures with mipmap levels.
+    #[doc(alias = "C3D_TexSetFilterMipmap")]
+    pub fn set_filter_full(
+        &self,
+        mag_filter: TextureFilterParam,
+        min_filter: TextureFilterParam,
+        mip_filter: Option<TextureFilterParam>,
+    ) {
+        self.set_filter(mag_filter, min_filter);
+        if let Some(mip_filter) = mip_filter {
+            unsafe {
+                citro3d_sys::C3D_TexSetFilterMipmap(self.as_raw().cast_mut(), mip_filter as u8);
+            }
+        }
+    }
+
+    /// Get the mipmap filter currently set on the texture.
+    pub fn mip_filter(&self) -> TextureFilterParam {
+        // See GPU_TEXTURE_MIP_FILTER in <3ds/gpu/enums.h>
+        TextureFilterParam::from_bit(self.param() >> 24)
+    }
+
+    /// Get the `(mag_filter, min_filter)` currently set on the texture.
+    pub fn filter(&self) -> (TextureFilterParam, TextureFilterParam) {
+        // See GPU_TEXTURE_MAG_FILTER and GPU_TEXTURE_MIN_FILTER in <3ds/gpu/enums.h>
+        let param = self.param();
+        (
+            TextureFilterParam::from_bit(param >> 1),
+            TextureFilterParam::from_bit(param >> 2),
+        )
+    }
+
+    /// Get the `(wrap_s, wrap_t)` currently set on the texture.
+    pub fn wrap(&self) -> (TextureWrapParam, TextureWrapParam) {
+        // See GPU_TEXTURE_WRAP_S and GPU_TEXTURE_WRAP_T in <3ds/gpu/enums.h>
+        let param = self.param();
+        (
+            TextureWrapParam::from_bits(param >> 12),
+            TextureWrapParam::from_bits(param >> 8),
+        )
+    }
+
+    fn param(&self) -> u32 {
+        unsafe { self.raw.as_ref().param }
+    }
     // we are not a container it doesn't make sense to have is_empty
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.width() as usize * self.height() as usize
     }
 
-    fn data_ptr(&self) -> NonNull<u8> {
+    /// Upload texture data read from `reader`, which should contain data in the
+    /// same format as [`Tex::upload`]. Exactly as many bytes as the texture
+    /// needs are read; for cube maps that is [`Tex::data_len`] bytes for each
+    /// face, one after another in the order +X, -X, +Y, -Y, +Z, -Z.
+    ///
+    /// For textures in linear memory the data is read directly into the texture,
+    /// so the whole image never needs to be in memory at once. Textures in VRAM
+    /// can only be written by the GPU, so their data is read in chunks of 32 KiB
+    /// into a linear buffer and copied with one DMA transfer per chunk.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `reader`, an error of kind
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) if it ends before
+    /// all the texture data was read, or an error if a DMA transfer to VRAM
+    /// could not be started. The texture data is left partially written in
+    /// these cases.
+    #[cfg(feature = "std")]
+    #[doc(alias = "GX_RequestDma")]
+    #[doc(alias = "C3D_TexFlush")]
+    pub fn upload_from_reader(&mut self, mut reader: impl std::io::Read) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+
+        let size = self.data_len();
+        let too_short = |err: Error| match err.kind() {
+            ErrorKind::UnexpectedEof => {
+                Error::new(ErrorKind::UnexpectedEof, "texture data too short")
+            }
+            _ => err,
+        };
+
+        if !self.is_vram() {
+            for &face in self.face_ptrs() {
+                // SAFETY: each face is `size` bytes long, and we have unique
+                // access to them through `&mut self`
+                let data = unsafe { core::slice::from_raw_parts_mut(face.cast::<u8>(), size) };
+                reader.read_exact(data).map_err(too_short)?;
+            }
+            // Make sure the GPU sees the data written by the CPU
+            self.flush();
+            return Ok(());
+        }
+
+        let chunk_size = READER_CHUNK_SIZE.min(size);
+        let mut chunk = Vec::with_capacity_in(chunk_size, ctru::linear::LinearAllocator);
+        chunk.resize(chunk_size, 0u8);
+        for &face in self.face_ptrs() {
+            for offset in (0..size).step_by(chunk_size) {
+                let buf = &mut chunk[..chunk_size.min(size - offset)];
+                reader.read_exact(buf).map_err(too_short)?;
+
+                // SAFETY: the destination is within the face, which we have
+                // unique access to, and we wait for the transfer to finish
+                // before the buffer is reused or dropped
+                let res = unsafe {
+                    let _ = ctru_sys::GSPGPU_FlushDataCache(buf.as_ptr().cast(), buf.len() as u32);
+                    ctru_sys::GX_RequestDma(
+                        buf.as_mut_ptr().cast(),
+                        face.cast::<u8>().add(offset).cast(),
+                        buf.len() as u32,
+                    )
+                };
+                if res < 0 {
+                    return Err(Error::other(format!(
+                        "texture DMA transfer failed with code {res:#x}"
+                    )));
+                }
+                unsafe { ctru_sys::gspWaitForEvent(ctru_sys::GSPGPU_EVENT_DMA, false) };
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload row-major pixel data, converting it to the layout the GPU uses.
+    /// Unlike [`Tex::upload`], `data` doesn't need to be tiled or have its
+    /// components reversed, and is laid out as follows for each format:
+    ///
+    /// * [`Rgba8`](TexFormat::Rgba8), [`Rgb8`](TexFormat::Rgb8),
+    ///   [`La8`](TexFormat::La8) and [`HiLo8`](TexFormat::HiLo8): one byte per
+    ///   component, in the order of the format's name (e.g. `[l, a]`).
+    /// * [`Rgba5551`](TexFormat::Rgba5551), [`Rgb565`](TexFormat::Rgb565) and
+    ///   [`Rgba4`](TexFormat::Rgba4): a little-endian `u16` per pixel, with the
+    ///   first component in the most significant bits.
+    /// * [`L8`](TexFormat::L8) and [`A8`](TexFormat::A8): one byte per pixel.
+    /// * [`La4`](TexFormat::La4): one byte per pixel, luminance in the high
+    ///   nibble.
+    /// * [`L4`](TexFormat::L4) and [`A4`](TexFormat::A4): two pixels per byte,
+    ///   the first one in the low nibble.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidFormat`](crate::Error::InvalidFormat) if the texture has
+    ///   a compressed (ETC1) format.
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if `data` is too short.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture is a cube map.
+    #[doc(alias = "C3D_TexUpload")]
+    pub fn upload_linear(&self, data: &[u8]) -> crate::Result<()> {
+        assert!(
+            matches!(self.kind(), TexKind::Tex2d | TexKind::Shadow2d),
+            "linear uploads are not supported for cube maps"
+        );
+
+        let format = self.format();
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let mut tiled = vec![0; width * height * format.bits_per_pixel() / 8];
+        write_tiled(&mut tiled, width, format, (0, 0), (width, height), data)?;
+        self.upload(&tiled);
+        Ok(())
+    }
+
+    /// Upload a `width` by `height` rectangle of pixels at `(x, y)`, leaving the
+    /// rest of the texture untouched. `data` is laid out as described for
+    /// [`Tex::upload_linear`], and is tiled while it is copied, so the rectangle
+    /// doesn't need to line up with the GPU's 8x8 tiles.
+    ///
+    /// The texture must be in linear memory, since it is written by the CPU.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidFormat`](crate::Error::InvalidFormat) if the texture has
+    ///   a compressed (ETC1) format.
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if the rectangle
+    ///   doesn't fit inside the texture or `data` is too short for it.
+    /// * [`Error::InvalidMemoryLocation`](crate::Error::InvalidMemoryLocation)
+    ///   if the texture is in VRAM.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture is a cube map.
+    pub fn upload_sub(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        data: &[u8],
+    ) -> crate::Result<()> {
+        assert!(
+            matches!(self.kind(), TexKind::Tex2d | TexKind::Shadow2d),
+            "partial uploads are not supported for cube maps"
+        );
+
+        let (x, y, width, height) = (x as usize, y as usize, width as usize, height as usize);
+        let (tex_width, tex_height) = (self.width() as usize, self.height() as usize);
+        if x + width > tex_width || y + height > tex_height {
+            return Err(crate::Error::InvalidSize);
+        }
+        if self.is_vram() {
+            return Err(crate::Error::InvalidMemoryLocation {
+                address: Some(self.data_non_null().as_ptr() as usize),
+            });
+        }
+
+        let format = self.format();
+        let size = tex_width * tex_height * format.bits_per_pixel() / 8;
+        // SAFETY: the texture data is `size` bytes long, and we have unique
+        // access to it through `&mut self`
+        let tex_data =
+            unsafe { core::slice::from_raw_parts_mut(self.data_non_null().as_ptr(), size) };
+        write_tiled(tex_data, tex_width, format, (x, y), (width, height), data)?;
+
+        self.flush();
+        Ok(())
+    }
+
+    /// Write back any texture data in the CPU's data cache to memory. This is
+    /// needed after writing the texture bytes directly on the CPU (e.g. through
+    /// [`Tex::data_mut`]) and before the GPU next reads the texture, otherwise the
+    /// GPU may see stale data. [`Tex::upload`] already does this.
+    ///
+    /// Textures in VRAM are not cached, so this does nothing for them.
+    #[doc(alias = "GSPGPU_FlushDataCache")]
+    #[doc(alias = "C3D_TexFlush")]
+    pub fn flush(&self) {
+        self.for_each_face(|data, size| unsafe {
+            let _ = ctru_sys::GSPGPU_FlushDataCache(data, size);
+        });
+    }
+
+    /// Discard any texture data in the CPU's data cache, so the next CPU read
+    /// comes from memory. This is needed after the GPU writes to the texture
+    /// (e.g. when it was used as a render target or the destination of a
+    /// transfer) and before reading the texture bytes on the CPU (e.g. through
+    /// [`Tex::data`]), otherwise the CPU may see stale data.
+    ///
+    /// Textures in VRAM are not cached, so this does nothing for them.
+    #[doc(alias = "GSPGPU_InvalidateDataCache")]
+    pub fn invalidate(&self) {
+        self.for_each_face(|data, size| unsafe {
+            let _ = ctru_sys::GSPGPU_InvalidateDataCache(data, size);
+        });
+    }
+
+    /// Call `f` with the data pointer and size in bytes of each face of the
+    /// texture (1 for 2D textures, 6 for cube maps), unless it is in VRAM.
+    fn for_each_face(&self, mut f: impl FnMut(*const libc::c_void, u32)) {
+        if self.is_vram() {
+            return;
+        }
+
+        let size = self.data_len();
+        for &face in self.face_ptrs() {
+            f(face.cast_const(), size as u32);
+        }
+    }
+
+    /// Get the data pointer of each face of the texture (1 for 2D textures, 6
+    /// for cube maps). Each face is [`Tex::data_len`] bytes long.
+    fn face_ptrs(&self) -> &[*mut libc::c_void] {
+        match self.kind() {
+            // SAFETY: cube textures always have cube data, which was filled
+            // in when the texture was initialized
+            TexKind::CubeMap | TexKind::ShadowCube => unsafe {
+                &(*(*self.as_raw()).__bindgen_anon_1.cube).data
+            },
+            // SAFETY: 2D textures store their data pointer in the union directly
+            TexKind::Tex2d | TexKind::Shadow2d => unsafe {
+                std::slice::from_ref(&(*self.as_raw()).__bindgen_anon_1.data)
+            },
+        }
+    }
+
+    /// Whether the texture data is stored in VRAM, rather than linear memory.
+    /// Data in VRAM can only be written by the GPU, e.g. with DMA transfers,
+    /// and needs no cache maintenance.
+    pub fn is_vram(&self) -> bool {
+        let addr = self.data_ptr() as usize;
+        let vram = ctru_sys::OS_VRAM_VADDR as usize;
+        (vram..vram + ctru_sys::OS_VRAM_SIZE as usize).contains(&addr)
+    }
+
+    /// Get the virtual address of the texture data, e.g. for DMA transfers or
+    /// manual cache operations. For cube maps, this is the data of the first
+    /// (+X) face; each face is [`Tex::data_len`] bytes long. Use
+    /// `osConvertVirtToPhys` to get the physical address the GPU uses.
+    ///
+    /// The data is tiled in the GPU's layout, see [`Tex::upload`].
+    ///
+    /// Getting the pointer is safe, but using it is subject to the usual rules:
+    ///
+    /// * It is only valid until the texture is dropped.
+    /// * Writing through it while the texture is borrowed elsewhere, or while
+    ///   the GPU may be reading it during a frame, is a data race.
+    /// * VRAM can't be written by the CPU, see [`Tex::is_vram`].
+    /// * After writing linear memory, the data must be flushed from the CPU
+    ///   cache (see [`Tex::flush`]) before the GPU reads it.
+    pub fn data_ptr(&self) -> *const u8 {
+        match self.kind() {
+            // SAFETY: see `for_each_face`
+            TexKind::CubeMap | TexKind::ShadowCube => unsafe {
+                (*(*self.as_raw()).__bindgen_anon_1.cube).data[0]
+                    .cast_const()
+                    .cast()
+            },
+            TexKind::Tex2d | TexKind::Shadow2d => self.data_non_null().as_ptr().cast_const(),
+        }
+    }
+
+    /// Get the size of the texture data in bytes, for each face of cube maps.
+    pub fn data_len(&self) -> usize {
+        self.width() as usize * self.height() as usize * self.format().bits_per_pixel() / 8
+    }
+
+    pub(crate) fn data_non_null(&self) -> NonNull<u8> {
         NonNull::new(unsafe { (*self.as_raw()).__bindgen_anon_1.data }.cast())
             .expect("data pointer for texture cannot be null")
     }
 
     /// Get a reference to the underlying bytes
     pub fn data(&self) -> &[u8] {
-        let ptr = self.data_ptr();
+        let ptr = self.data_non_null();
         unsafe { core::slice::from_raw_parts(ptr.as_ref(), self.len()) }
     }
 
     /// Get a mutable reference to the underlying bytes
     pub fn data_mut(&mut self) -> &mut [u8] {
-        let mut ptr = self.data_ptr();
+        let mut ptr = self.data_non_null();
         unsafe { core::slice::from_raw_parts_mut(ptr.as_mut(), self.len()) }
     }
 
     pub fn as_raw(&self) -> *const citro3d_sys::C3D_Tex {
-        self.0.as_ptr() as *const _
+        self.raw.as_ptr() as *const _
     }
 
     pub fn as_raw_mut(&mut self) -> *mut citro3d_sys::C3D_Tex {
-        self.0.as_ptr()
+        self.raw.as_ptr()
+    }
+}
+
+impl std::fmt::Debug for Tex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tex")
+            .field("kind", &self.kind())
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("format", &self.format())
+            .field("filter", &self.filter())
+            .field("mip_filter", &self.mip_filter())
+            .field("wrap", &self.wrap())
+            .finish()
     }
 }
 
 impl Drop for Tex {
     #[doc(alias = "C3D_TexDelete")]
     fn drop(&mut self) {
-        unsafe { citro3d_sys::C3D_TexDelete(self.as_raw().cast_mut()) }
+        crate::render::clear_bound(self);
+        if self.owned {
+            unsafe { citro3d_sys::C3D_TexDelete(self.as_raw().cast_mut()) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upload_sub_rect() {
+        let _instance = crate::Instance::new().unwrap();
+        let mut tex = Tex::new(TexParams::new_2d(16, 16).format(TexFormat::L8)).unwrap();
+        tex.data_mut().fill(0);
+
+        // 2x2 rectangle straddling the first two tiles
+        tex.upload_sub(7, 0, 2, 2, &[1, 2, 3, 4]).unwrap();
+        let data = tex.data();
+        assert_eq!(data[tiled_index(7, 0, 16)], 1);
+        assert_eq!(data[tiled_index(8, 0, 16)], 2);
+        assert_eq!(data[tiled_index(7, 1, 16)], 3);
+        assert_eq!(data[tiled_index(8, 1, 16)], 4);
+        assert_eq!(data.iter().filter(|&&b| b != 0).count(), 4);
+
+        assert!(matches!(
+            tex.upload_sub(15, 0, 2, 1, &[0, 0]),
+            Err(crate::Error::InvalidSize)
+        ));
+        assert!(matches!(
+            tex.upload_sub(0, 0, 2, 2, &[0; 3]),
+            Err(crate::Error::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn upload_checked() {
+        let _instance = crate::Instance::new().unwrap();
+        let tex = Tex::new_simple(8, 8, TexFormat::Rgba8).unwrap();
+        assert!(matches!(
+            tex.upload_checked([0u8; 8 * 8 * 3], TexFormat::Rgb8),
+            Err(crate::Error::InvalidFormat)
+        ));
+        assert!(matches!(
+            tex.upload_checked([0u8; 8 * 8 * 3], TexFormat::Rgba8),
+            Err(crate::Error::InvalidSize)
+        ));
+        tex.upload_checked([0u8; 8 * 8 * 4], TexFormat::Rgba8)
+            .unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn upload_from_reader() {
+        use std::io::ErrorKind;
+
+        let _instance = crate::Instance::new().unwrap();
+        let is_too_short =
+            |res: std::io::Result<()>| res.is_err_and(|e| e.kind() == ErrorKind::UnexpectedEof);
+
+        let mut tex = Tex::new_simple(8, 8, TexFormat::L8).unwrap();
+        assert!(is_too_short(tex.upload_from_reader(&[1u8; 63][..])));
+        let data: Vec<u8> = (0..64).collect();
+        tex.upload_from_reader(&data[..]).unwrap();
+        assert_eq!(tex.data(), &data[..]);
+
+        let mut cube = Tex::new(TexParams {
+            kind: TexKind::CubeMap,
+            cube: Some(unsafe { core::mem::zeroed() }),
+            ..TexParams::new_2d(8, 8).format(TexFormat::L8)
+        })
+        .unwrap();
+        // Enough for some faces, but not all of them
+        assert!(is_too_short(cube.upload_from_reader(&[1u8; 64 * 5][..])));
+        let data: Vec<u8> = (0..64 * 6).map(|i| (i / 64) as u8).collect();
+        cube.upload_from_reader(&data[..]).unwrap();
+        for (face, &ptr) in cube.face_ptrs().iter().enumerate() {
+            let face_data = unsafe { core::slice::from_raw_parts(ptr.cast::<u8>(), 64) };
+            assert!(face_data.iter().all(|&b| b == face as u8));
+        }
+
+        // Larger than one chunk, so it takes several DMA transfers
+        let mut vram = Tex::new(TexParams::new_2d(128, 128).use_vram(true)).unwrap();
+        let size = vram.data_len();
+        assert!(size > READER_CHUNK_SIZE);
+        assert!(is_too_short(
+            vram.upload_from_reader(&vec![0; size - 1][..])
+        ));
+        vram.upload_from_reader(&vec![0; size][..]).unwrap();
+    }
+
+    #[test]
+    fn valid_sizes() {
+        assert!(TexParams::is_valid_size(8));
+        assert!(TexParams::is_valid_size(256));
+        assert!(TexParams::is_valid_size(1024));
+        assert!(!TexParams::is_valid_size(0));
+        assert!(!TexParams::is_valid_size(4));
+        assert!(!TexParams::is_valid_size(240));
+        assert!(!TexParams::is_valid_size(2048));
     }
 }
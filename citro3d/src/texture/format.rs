@@ -0,0 +1,512 @@
+//! Texture formats and the layout of texture data, which don't need the GPU.
+
+#[cfg(feature = "mock")]
+use crate::mock::ctru_sys;
+
+/// Format of the texture bytes
+#[doc(alias = "GPU_TEXCOLOR")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TexFormat {
+    /// 8-bit Red + 8-bit Green + 8-bit Blue + 8-bit Alpha
+    Rgba8 = ctru_sys::GPU_RGBA8,
+    /// 8-bit Red + 8-bit Green + 8-bit Blue
+    Rgb8 = ctru_sys::GPU_RGB8,
+    /// 5-bit Red + 5-bit Green + 5-bit Blue + 1-bit Alpha
+    Rgba5551 = ctru_sys::GPU_RGBA5551,
+    /// 5-bit Red + 6-bit Green + 5-bit Blue
+    Rgb565 = ctru_sys::GPU_RGB565,
+    /// 4-bit Red + 4-bit Green + 4-bit Blue + 4-bit Alpha
+    Rgba4 = ctru_sys::GPU_RGBA4,
+    /// 8-bit Luminance + 8-bit Alpha
+    La8 = ctru_sys::GPU_LA8,
+    /// 8-bit Hi + 8-bit Lo
+    HiLo8 = ctru_sys::GPU_HILO8,
+    /// 8-bit Luminance
+    L8 = ctru_sys::GPU_L8,
+    /// 8-bit Alpha
+    A8 = ctru_sys::GPU_A8,
+    /// 4-bit Luminance + 4-bit Alpha
+    La4 = ctru_sys::GPU_LA4,
+    /// 4-bit Luminance
+    L4 = ctru_sys::GPU_L4,
+    /// 4-bit Alpha
+    A4 = ctru_sys::GPU_A4,
+    /// ETC1 texture compression
+    Etc1 = ctru_sys::GPU_ETC1,
+    /// ETC1 texture compression + 4-bit Alpha
+    Etc1A4 = ctru_sys::GPU_ETC1A4,
+}
+
+impl TexFormat {
+    /// Bits needed to store each pixel
+    pub fn bits_per_pixel(&self) -> usize {
+        match self {
+            TexFormat::Rgba8 => 32,
+            TexFormat::Rgb8 => 24,
+            TexFormat::Rgba5551
+            | TexFormat::Rgb565
+            | TexFormat::Rgba4
+            | TexFormat::La8
+            | TexFormat::HiLo8 => 16,
+            TexFormat::L8 | TexFormat::A8 | TexFormat::La4 | TexFormat::Etc1A4 => 8,
+            TexFormat::L4 | TexFormat::A4 | TexFormat::Etc1 => 4,
+        }
+    }
+}
+
+/// The quality/memory tradeoff to make when picking a [`TexFormat`] with
+/// [`TexFormat::best_for`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Quality {
+    /// 8 bits per channel. Uses the most memory but has no loss of precision
+    /// for typical 8-bit images.
+    High,
+    /// 4 to 6 bits per channel, using half (or less) of the memory of [`Quality::High`].
+    Low,
+}
+
+impl TexFormat {
+    /// Recommend an uncompressed format for image data with the given properties.
+    ///
+    /// | `has_alpha` | `grayscale` | [`High`](Quality::High) | [`Low`](Quality::Low) |
+    /// |-------------|-------------|-------------------------|-----------------------|
+    /// | no          | no          | `Rgb8` (24 bpp)         | `Rgb565` (16 bpp)     |
+    /// | yes         | no          | `Rgba8` (32 bpp)        | `Rgba4` (16 bpp)      |
+    /// | no          | yes         | `L8` (8 bpp)            | `L4` (4 bpp)          |
+    /// | yes         | yes         | `La8` (16 bpp)          | `La4` (8 bpp)         |
+    ///
+    /// For images with only fully opaque or fully transparent pixels,
+    /// [`TexFormat::Rgba5551`] is usually a better choice than `Rgba4`. The
+    /// compressed [`TexFormat::Etc1`] formats are never recommended since they
+    /// need the data to be encoded ahead of time.
+    pub fn best_for(has_alpha: bool, grayscale: bool, quality: Quality) -> Self {
+        match (has_alpha, grayscale, quality) {
+            (false, false, Quality::High) => Self::Rgb8,
+            (false, false, Quality::Low) => Self::Rgb565,
+            (true, false, Quality::High) => Self::Rgba8,
+            (true, false, Quality::Low) => Self::Rgba4,
+            (false, true, Quality::High) => Self::L8,
+            (false, true, Quality::Low) => Self::L4,
+            (true, true, Quality::High) => Self::La8,
+            (true, true, Quality::Low) => Self::La4,
+        }
+    }
+
+    /// Get the 8 bits per channel format for image data with the given number
+    /// of channels (1 = luminance, 2 = luminance + alpha, 3 = RGB, 4 = RGBA).
+    ///
+    /// Returns `None` for any other number of channels.
+    pub fn from_channels(channels: u8) -> Option<Self> {
+        match channels {
+            1 => Some(Self::L8),
+            2 => Some(Self::La8),
+            3 => Some(Self::Rgb8),
+            4 => Some(Self::Rgba8),
+            _ => None,
+        }
+    }
+
+    /// Encode a normal map as [`TexFormat::HiLo8`] data, e.g. for use with
+    /// [`LightEnv::set_normal_map`](crate::light::LightEnv::set_normal_map).
+    ///
+    /// Each normal is normalized and its X and Y components are mapped from
+    /// `-1.0..=1.0` to `0..=255`, stored as the Hi and Lo channels respectively.
+    /// The Z component is not stored, since the GPU reconstructs it from X and
+    /// Y. Zero-length normals are encoded as pointing straight out (`+Z`).
+    ///
+    /// Pixels are returned in the same order as `normals`, so they should
+    /// already be in the tiled layout the GPU uses before uploading.
+    pub fn encode_normal_map(normals: &[[f32; 3]]) -> Vec<u8> {
+        let encode = |v: f32| ((v.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0).round() as u8;
+
+        normals
+            .iter()
+            .flat_map(|&[x, y, z]| {
+                let len = (x * x + y * y + z * z).sqrt();
+                let (x, y) = if len > 0.0 {
+                    (x / len, y / len)
+                } else {
+                    (0.0, 0.0)
+                };
+                // The GPU stores channels in reverse order, i.e. Lo before Hi
+                [encode(y), encode(x)]
+            })
+            .collect()
+    }
+
+    /// Decode [`Etc1`](Self::Etc1) or [`Etc1A4`](Self::Etc1A4) texture data into
+    /// RGBA8 pixels (4 bytes per pixel, `r, g, b, a` order). This is implemented
+    /// in pure Rust, so it can be used for previewing or verifying compressed
+    /// textures off-device.
+    ///
+    /// `data` is expected to be in the layout the GPU uses, i.e. 8x8 tiles each
+    /// containing four 4x4 ETC1 blocks stored as little-endian 64-bit words (with
+    /// a 64-bit block of 4-bit alpha values before each block for `Etc1A4`).
+    /// Pixels are returned row by row in the same order as the tiles.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidFormat`](crate::Error::InvalidFormat) if `self` is not an ETC1 format
+    /// * [`Error::InvalidSize`](crate::Error::InvalidSize) if `width` or `height`
+    ///   are not multiples of 8, or `data` is too short for the given size
+    pub fn decode_etc1(self, data: &[u8], width: usize, height: usize) -> crate::Result<Vec<u8>> {
+        let has_alpha = match self {
+            Self::Etc1 => false,
+            Self::Etc1A4 => true,
+            _ => return Err(crate::Error::InvalidFormat),
+        };
+        if width % 8 != 0
+            || height % 8 != 0
+            || data.len() < width * height * self.bits_per_pixel() / 8
+        {
+            return Err(crate::Error::InvalidSize);
+        }
+
+        let block_size = if has_alpha { 16 } else { 8 };
+        let read_u64 =
+            |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+        let mut out = vec![0; width * height * 4];
+        for (tile_idx, tile_offset) in (0..width * height / 64).map(|i| (i, i * block_size * 4)) {
+            let tile_x = (tile_idx % (width / 8)) * 8;
+            let tile_y = (tile_idx / (width / 8)) * 8;
+
+            for block_idx in 0..4 {
+                let block_offset = tile_offset + block_idx * block_size;
+                let (alpha, color) = if has_alpha {
+                    (Some(read_u64(block_offset)), read_u64(block_offset + 8))
+                } else {
+                    (None, read_u64(block_offset))
+                };
+
+                for x in 0..4 {
+                    for y in 0..4 {
+                        let [r, g, b] = etc1_sample(color, x, y);
+                        let a =
+                            alpha.map_or(0xFF, |a| ((a >> (4 * (x * 4 + y))) & 0xF) as u8 * 0x11);
+
+                        let px = tile_x + (block_idx % 2) * 4 + x;
+                        let py = tile_y + (block_idx / 2) * 4 + y;
+                        let i = (py * width + px) * 4;
+                        out[i..i + 4].copy_from_slice(&[r, g, b, a]);
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Decode a single pixel at `(x, y)` from a 4x4 ETC1 block.
+fn etc1_sample(block: u64, x: usize, y: usize) -> [u8; 3] {
+    const MODIFIERS: [[i32; 2]; 8] = [
+        [2, 8],
+        [5, 17],
+        [9, 29],
+        [13, 42],
+        [18, 60],
+        [24, 80],
+        [33, 106],
+        [47, 183],
+    ];
+
+    let bits = |offset: u32, len: u32| ((block >> offset) & ((1 << len) - 1)) as i32;
+    let flip = bits(32, 1) != 0;
+    let differential = bits(33, 1) != 0;
+
+    // The block is split into two halves, either side by side or top and bottom
+    let second_half = if flip { y >= 2 } else { x >= 2 };
+
+    let base = if differential {
+        let expand = |v: i32| (v << 3) | (v >> 2);
+        let sign_extend = |v: i32| (v << 29) >> 29;
+        [59, 51, 43].map(|offset| {
+            let mut v = bits(offset, 5);
+            if second_half {
+                v += sign_extend(bits(offset - 3, 3));
+            }
+            expand(v)
+        })
+    } else {
+        let offsets = if second_half {
+            [56, 48, 40]
+        } else {
+            [60, 52, 44]
+        };
+        offsets.map(|offset| bits(offset, 4) * 0x11)
+    };
+
+    let table = if second_half {
+        bits(34, 3)
+    } else {
+        bits(37, 3)
+    };
+    let texel = (x * 4 + y) as u32;
+    let mut modifier = MODIFIERS[table as usize][bits(texel, 1) as usize];
+    if bits(texel + 16, 1) != 0 {
+        modifier = -modifier;
+    }
+
+    base.map(|c| (c + modifier).clamp(0, 255) as u8)
+}
+
+impl TryFrom<ctru_sys::GPU_TEXCOLOR> for TexFormat {
+    type Error = crate::Error;
+
+    fn try_from(value: ctru_sys::GPU_TEXCOLOR) -> Result<Self, Self::Error> {
+        match value {
+            ctru_sys::GPU_RGBA8 => Ok(Self::Rgba8),
+            ctru_sys::GPU_RGB8 => Ok(Self::Rgb8),
+            ctru_sys::GPU_RGBA5551 => Ok(Self::Rgba5551),
+            ctru_sys::GPU_RGB565 => Ok(Self::Rgb565),
+            ctru_sys::GPU_RGBA4 => Ok(Self::Rgba4),
+            ctru_sys::GPU_LA8 => Ok(Self::La8),
+            ctru_sys::GPU_HILO8 => Ok(Self::HiLo8),
+            ctru_sys::GPU_L8 => Ok(Self::L8),
+            ctru_sys::GPU_A8 => Ok(Self::A8),
+            ctru_sys::GPU_LA4 => Ok(Self::La4),
+            ctru_sys::GPU_L4 => Ok(Self::L4),
+            ctru_sys::GPU_A4 => Ok(Self::A4),
+            ctru_sys::GPU_ETC1 => Ok(Self::Etc1),
+            ctru_sys::GPU_ETC1A4 => Ok(Self::Etc1A4),
+            _ => Err(crate::Error::NotFound),
+        }
+    }
+}
+
+/// Rearrange row-major pixels into the layout the GPU uses, see [`tiled_index`].
+#[cfg_attr(any(feature = "mock", not(feature = "image")), allow(dead_code))]
+pub(super) fn swizzle(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+) -> Vec<u8> {
+    let mut out = vec![0; width * height * bytes_per_pixel];
+    for y in 0..height {
+        for x in 0..width {
+            let dst = tiled_index(x, y, width) * bytes_per_pixel;
+            let src = (y * width + x) * bytes_per_pixel;
+            out[dst..dst + bytes_per_pixel].copy_from_slice(&pixels[src..src + bytes_per_pixel]);
+        }
+    }
+    out
+}
+
+/// Get the index of the pixel at `(x, y)` in the layout the GPU uses for a
+/// texture `width` pixels wide, i.e. 8x8 tiles stored row by row, with the
+/// pixels of each tile in Morton (Z-order) order.
+pub(crate) fn tiled_index(x: usize, y: usize, width: usize) -> usize {
+    let tile = (y / 8) * (width / 8) + x / 8;
+    let (tx, ty) = (x % 8, y % 8);
+    let morton = (tx & 1)
+        | ((ty & 1) << 1)
+        | ((tx & 2) << 1)
+        | ((ty & 2) << 2)
+        | ((tx & 4) << 2)
+        | ((ty & 4) << 3);
+    tile * 64 + morton
+}
+
+/// Copy a `size` rectangle of row-major `pixels` (laid out as described for
+/// [`Tex::upload_linear`](super::Tex::upload_linear)) to `pos` in `tiled`, a
+/// texture `width` pixels wide in the layout the GPU uses.
+#[cfg_attr(feature = "mock", allow(dead_code))]
+pub(super) fn write_tiled(
+    tiled: &mut [u8],
+    width: usize,
+    format: TexFormat,
+    (x, y): (usize, usize),
+    (rect_width, rect_height): (usize, usize),
+    pixels: &[u8],
+) -> crate::Result<()> {
+    if matches!(format, TexFormat::Etc1 | TexFormat::Etc1A4) {
+        return Err(crate::Error::InvalidFormat);
+    }
+    let bits = format.bits_per_pixel();
+    if pixels.len() < (rect_width * rect_height * bits).div_ceil(8) {
+        return Err(crate::Error::InvalidSize);
+    }
+
+    for row in 0..rect_height {
+        for col in 0..rect_width {
+            let src = row * rect_width + col;
+            let dst = tiled_index(x + col, y + row, width);
+            if bits == 4 {
+                let shift = |i: usize| (i % 2) * 4;
+                let nibble = (pixels[src / 2] >> shift(src)) & 0xF;
+                let byte = &mut tiled[dst / 2];
+                *byte = (*byte & !(0xF << shift(dst))) | (nibble << shift(dst));
+                continue;
+            }
+
+            let bytes = bits / 8;
+            let (src, dst) = (
+                &pixels[src * bytes..][..bytes],
+                &mut tiled[dst * bytes..][..bytes],
+            );
+            dst.copy_from_slice(src);
+            // The GPU stores multi-byte components in reverse order
+            if matches!(
+                format,
+                TexFormat::Rgba8 | TexFormat::Rgb8 | TexFormat::La8 | TexFormat::HiLo8
+            ) {
+                dst.reverse();
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn etc1_tile(block: u64) -> Vec<u8> {
+        std::iter::repeat(block.to_le_bytes())
+            .take(4)
+            .flatten()
+            .collect()
+    }
+
+    #[test]
+    fn format_selection() {
+        assert_eq!(
+            TexFormat::best_for(true, false, Quality::High),
+            TexFormat::Rgba8
+        );
+        assert_eq!(
+            TexFormat::best_for(false, true, Quality::Low),
+            TexFormat::L4
+        );
+        assert_eq!(TexFormat::from_channels(2), Some(TexFormat::La8));
+        assert_eq!(TexFormat::from_channels(0), None);
+        assert_eq!(TexFormat::from_channels(5), None);
+    }
+
+    #[test]
+    fn encode_normal_map() {
+        let data = TexFormat::encode_normal_map(&[
+            [0.0, 0.0, 1.0],
+            [2.0, 0.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 0.0],
+        ]);
+        assert_eq!(data.len(), 4 * TexFormat::HiLo8.bits_per_pixel() / 8);
+        assert_eq!(data, [128, 128, 128, 255, 0, 128, 128, 128]);
+    }
+
+    #[test]
+    fn swizzle_tiles() {
+        let pixels: Vec<u8> = (0..16 * 8).map(|i| i as u8).collect();
+        let out = swizzle(&pixels, 16, 8, 1);
+        assert_eq!(&out[..8], &[0, 1, 16, 17, 2, 3, 18, 19]);
+        // The second tile starts at pixel (8, 0)
+        assert_eq!(out[64], 8);
+        assert_eq!(out[63], 7 * 16 + 7);
+    }
+
+    #[test]
+    fn write_tiled_formats() {
+        // A 2x2 rectangle in the corner of an 8x8 texture, so the pixels end
+        // up at tiled indices 0, 1, 2 and 3
+        let tiled = |format: TexFormat, pixels: &[u8]| {
+            let mut out = vec![0; 64 * format.bits_per_pixel() / 8];
+            write_tiled(&mut out, 8, format, (0, 0), (2, 2), pixels).unwrap();
+            out
+        };
+
+        let l8 = tiled(TexFormat::L8, &[1, 2, 3, 4]);
+        assert_eq!(l8[..4], [1, 2, 3, 4]);
+        assert_eq!(tiled(TexFormat::A8, &[1, 2, 3, 4]), l8);
+        assert_eq!(
+            tiled(TexFormat::La4, &[0x12, 0x34, 0x56, 0x78])[..4],
+            [0x12, 0x34, 0x56, 0x78]
+        );
+
+        // [l, a] is stored as [a, l]
+        let la8 = tiled(TexFormat::La8, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(la8[..8], [2, 1, 4, 3, 6, 5, 8, 7]);
+        let hilo8 = tiled(TexFormat::HiLo8, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(hilo8, la8);
+
+        let rgba8 = tiled(
+            TexFormat::Rgba8,
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
+        );
+        assert_eq!(rgba8[..8], [4, 3, 2, 1, 8, 7, 6, 5]);
+        let rgb8 = tiled(TexFormat::Rgb8, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(rgb8[..6], [3, 2, 1, 6, 5, 4]);
+
+        // Packed 16-bit formats are copied as-is
+        let rgb565 = tiled(TexFormat::Rgb565, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(rgb565[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // Two pixels per byte, first pixel in the low nibble
+        let l4 = tiled(TexFormat::L4, &[0x21, 0x43]);
+        assert_eq!(l4[..2], [0x21, 0x43]);
+
+        let mut out = vec![0; 64];
+        assert!(matches!(
+            write_tiled(&mut out, 8, TexFormat::Etc1A4, (0, 0), (2, 2), &[0; 4]),
+            Err(crate::Error::InvalidFormat)
+        ));
+        assert!(matches!(
+            write_tiled(&mut out, 8, TexFormat::L8, (0, 0), (2, 2), &[0; 3]),
+            Err(crate::Error::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn decode_etc1_individual() {
+        // R1 = G1 = B1 = R2 = G2 = B2 = 0x8, table 0, all modifiers +2
+        let block = 0x8888_8800_0000_0000;
+        let out = TexFormat::Etc1
+            .decode_etc1(&etc1_tile(block), 8, 8)
+            .unwrap();
+        for px in out.chunks(4) {
+            assert_eq!(px, [0x8a, 0x8a, 0x8a, 0xFF]);
+        }
+    }
+
+    #[test]
+    fn decode_etc1_differential_negated() {
+        // R = G = B = 16, dR = dG = dB = -1, table 0, all modifiers -2
+        let block = 0x8787_8702_FFFF_0000;
+        let out = TexFormat::Etc1
+            .decode_etc1(&etc1_tile(block), 8, 8)
+            .unwrap();
+        for (i, px) in out.chunks(4).enumerate() {
+            let x = i % 8;
+            let expected = if x % 4 < 2 { 132 - 2 } else { 123 - 2 };
+            assert_eq!(px, [expected, expected, expected, 0xFF]);
+        }
+    }
+
+    #[test]
+    fn decode_etc1a4_alpha() {
+        let block = 0x8888_8800_0000_0000u64;
+        // alpha 0x0 for the first column of each block, 0xF otherwise
+        let alpha = 0xFFFF_FFFF_FFFF_0000u64;
+        let data: Vec<u8> = std::iter::repeat([alpha.to_le_bytes(), block.to_le_bytes()])
+            .take(4)
+            .flatten()
+            .flatten()
+            .collect();
+
+        let out = TexFormat::Etc1A4.decode_etc1(&data, 8, 8).unwrap();
+        for (i, px) in out.chunks(4).enumerate() {
+            let expected = if i % 4 == 0 { 0 } else { 0xFF };
+            assert_eq!(px[3], expected);
+        }
+    }
+
+    #[test]
+    fn decode_etc1_rejects_bad_input() {
+        assert!(TexFormat::Rgba8.decode_etc1(&[0; 32], 8, 8).is_err());
+        assert!(TexFormat::Etc1.decode_etc1(&[0; 16], 8, 8).is_err());
+        assert!(TexFormat::Etc1.decode_etc1(&[0; 32], 4, 16).is_err());
+    }
+}
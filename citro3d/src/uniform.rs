@@ -3,26 +3,30 @@
 
 use std::ops::Range;
 
+pub use self::index::{Index, Kind};
 use crate::math::{FVec4, IVec, Matrix4};
 use crate::{shader, Instance};
 
-/// The index of a uniform within a [`shader::Program`].
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Index(u8);
+mod index;
 
-impl From<u8> for Index {
-    fn from(value: u8) -> Self {
-        Self(value)
-    }
-}
+/// The number of float (`vec4`) uniform registers available to a shader.
+pub(crate) const FLOAT_REGISTER_COUNT: usize = 0x60;
 
-impl From<Index> for i32 {
-    fn from(value: Index) -> Self {
-        value.0.into()
+impl Index {
+    /// Get the register number within this index's register file, as expected
+    /// by the `C3D_*UnifSet` functions.
+    fn register(self) -> i32 {
+        let start = match self.kind() {
+            Some(Kind::Int) => 0x60,
+            Some(Kind::Bool) => 0x68,
+            _ => 0,
+        };
+        (self.index - start).into()
     }
 }
 
 /// A uniform which may be bound as input to a shader program
+#[derive(Clone, Copy, PartialEq)]
 pub enum Uniform {
     /// Single float uniform (`.fvec name`)
     Float(FVec4),
@@ -44,11 +48,22 @@ impl Uniform {
         // the input registers then are excluded by libctru, see: https://github.com/devkitPro/libctru/blob/0da8705527f03b4b08ff7fee4dd1b7f28df37905/libctru/source/gpu/shbin.c#L93
         match self {
             Uniform::Float(_) | Uniform::Float2(_) | Uniform::Float3(_) | Uniform::Float4(_) => {
-                Index(0)..Index(0x60)
+                Index::from(0)..Index::from(0x60)
             }
-            Uniform::Int(_) => Index(0x60)..Index(0x64),
+            Uniform::Int(_) => Index::from(0x60)..Index::from(0x64),
             // this gap is intentional
-            Uniform::Bool(_) => Index(0x68)..Index(0x79),
+            Uniform::Bool(_) => Index::from(0x68)..Index::from(0x78),
+        }
+    }
+
+    /// Get the register file this uniform must be bound to.
+    pub fn kind(&self) -> Kind {
+        match self {
+            Uniform::Float(_) | Uniform::Float2(_) | Uniform::Float3(_) | Uniform::Float4(_) => {
+                Kind::Float
+            }
+            Uniform::Int(_) => Kind::Int,
+            Uniform::Bool(_) => Kind::Bool,
         }
     }
     /// Get length of uniform, i.e. how many registers it will write to
@@ -63,25 +78,45 @@ impl Uniform {
         }
     }
 
+    /// Check that this uniform can be bound to `index`, see [`Uniform::bind`].
+    pub(crate) fn check_index(self, index: Index) -> crate::Result<()> {
+        if index.kind() != Some(self.kind()) {
+            return Err(crate::Error::UniformKindMismatch {
+                uniform: self.kind(),
+                index: index.kind(),
+            });
+        }
+        if self.kind() == Kind::Float {
+            check_float_range(index, self.len())?;
+        }
+        Ok(())
+    }
+
     /// Bind a uniform
     ///
     /// Note: `_instance` is here to ensure unique access to the global uniform buffers
     /// otherwise we could race and/or violate aliasing
-    pub(crate) fn bind(self, _instance: &mut Instance, ty: shader::Type, index: Index) {
-        assert!(
-            self.index_range().contains(&index),
-            "tried to bind uniform to an invalid index (index: {}, valid range: {}..{})",
-            index.0,
-            self.index_range().start.0,
-            self.index_range().end.0
-        );
-        assert!(self.index_range().end.0 as usize >= self.len() + index.0 as usize, "tried to bind a uniform that would overflow the uniform buffer. index was {}, size was {} max is {}", index.0, self.len(), self.index_range().end.0);
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::UniformKindMismatch`](crate::Error::UniformKindMismatch) if
+    ///   `index` is not of the same [`Kind`] as this uniform.
+    /// * [`Error::UniformOverflow`](crate::Error::UniformOverflow) if the
+    ///   uniform would write past the last float register.
+    pub(crate) fn bind(
+        self,
+        _instance: &mut Instance,
+        ty: shader::Type,
+        index: Index,
+    ) -> crate::Result<()> {
+        self.check_index(index)?;
+
         let set_fvs = |fs: &[FVec4]| {
             for (off, f) in fs.iter().enumerate() {
                 unsafe {
                     citro3d_sys::C3D_FVUnifSet(
                         ty.into(),
-                        (index.0 as usize + off) as i32,
+                        (index.index as usize + off) as i32,
                         f.x(),
                         f.y(),
                         f.z(),
@@ -92,12 +127,12 @@ impl Uniform {
         };
         match self {
             Uniform::Bool(b) => unsafe {
-                citro3d_sys::C3D_BoolUnifSet(ty.into(), index.into(), b);
+                citro3d_sys::C3D_BoolUnifSet(ty.into(), index.register(), b);
             },
             Uniform::Int(i) => unsafe {
                 citro3d_sys::C3D_IVUnifSet(
                     ty.into(),
-                    index.into(),
+                    index.register(),
                     i.x() as i32,
                     i.y() as i32,
                     i.z() as i32,
@@ -113,9 +148,126 @@ impl Uniform {
                 set_fvs(&m.rows_wzyx());
             }
         }
+        Ok(())
     }
 }
 
+/// Remembers the last value written to each uniform register, so that uploading
+/// an unchanged value can be skipped. This saves GPU command buffer space in
+/// scenes that set many uniforms every frame.
+///
+/// The cache only knows about uniforms set through it, so values written by
+/// other means (e.g. [`Instance::bind_vertex_uniform`]) or lost due to a context
+/// reset will not be noticed. Call [`UniformCache::force_flush`] in that case.
+#[derive(Default)]
+pub struct UniformCache {
+    bound: Vec<(shader::Type, Index, Uniform)>,
+}
+
+impl UniformCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a uniform to the given `index` in the given shader, unless the same
+    /// value was already bound there through this cache.
+    ///
+    /// Returns whether the uniform was actually uploaded.
+    ///
+    /// # Errors
+    ///
+    /// See [`Instance::bind_vertex_uniform`]. Nothing is uploaded or cached in
+    /// that case.
+    pub fn set_uniform_cached(
+        &mut self,
+        instance: &mut Instance,
+        ty: shader::Type,
+        index: Index,
+        uniform: impl Into<Uniform>,
+    ) -> crate::Result<bool> {
+        let uniform = uniform.into();
+        if self.bound.contains(&(ty, index, uniform)) {
+            return Ok(false);
+        }
+        uniform.bind(instance, ty, index)?;
+
+        // Forget about anything this uniform overwrote
+        let range = index.index as usize..index.index as usize + uniform.len();
+        self.bound.retain(|(t, i, u)| {
+            let start = i.index as usize;
+            *t != ty || start + u.len() <= range.start || range.end <= start
+        });
+        self.bound.push((ty, index, uniform));
+        Ok(true)
+    }
+
+    /// Forget all cached values, so that the next value set for every register
+    /// is uploaded.
+    pub fn force_flush(&mut self) {
+        self.bound.clear();
+    }
+}
+
+/// Get the number of float (`vec4`) uniform registers available to a shader,
+/// i.e. the maximum number of vectors in all of its `.fvec` uniforms combined.
+/// Each [`Matrix4`] takes up four registers.
+pub fn float_register_capacity() -> usize {
+    FLOAT_REGISTER_COUNT
+}
+
+/// Check that `len` float registers starting at `start` fit in the register file.
+fn check_float_range(start: Index, len: usize) -> crate::Result<()> {
+    if start.index as usize + len > FLOAT_REGISTER_COUNT {
+        Err(crate::Error::UniformOverflow {
+            start: start.index,
+            len,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Upload consecutive matrices to the float uniform registers, starting at `start`.
+/// Each matrix takes up four registers.
+pub(crate) fn bind_matrices(
+    instance: &mut Instance,
+    ty: shader::Type,
+    start: Index,
+    mats: &[Matrix4],
+) -> crate::Result<()> {
+    check_float_range(start, mats.len() * 4)?;
+    let rows: Vec<_> = mats.iter().flat_map(|m| m.rows_wzyx()).collect();
+    bind_vectors(instance, ty, start, &rows)
+}
+
+/// Upload consecutive vectors to the float uniform registers, starting at `start`.
+///
+/// Note: `_instance` is here for the same reason as in [`Uniform::bind`].
+pub(crate) fn bind_vectors(
+    _instance: &mut Instance,
+    ty: shader::Type,
+    start: Index,
+    vectors: &[FVec4],
+) -> crate::Result<()> {
+    check_float_range(start, vectors.len())?;
+
+    for (off, v) in vectors.iter().enumerate() {
+        unsafe {
+            citro3d_sys::C3D_FVUnifSet(
+                ty.into(),
+                (start.index as usize + off) as i32,
+                v.x(),
+                v.y(),
+                v.z(),
+                v.w(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
 impl From<Matrix4> for Uniform {
     fn from(value: Matrix4) -> Self {
         Self::Float4(value)
@@ -166,3 +318,53 @@ impl From<glam::Mat4> for Uniform {
         Self::Float4(value.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_register_overflow() {
+        assert_eq!(float_register_capacity(), 96);
+        assert!(check_float_range(Index::from(0), 96).is_ok());
+        assert!(check_float_range(Index::from(92), 4).is_ok());
+        assert!(matches!(
+            check_float_range(Index::from(93), 4),
+            Err(crate::Error::UniformOverflow { start: 93, len: 4 })
+        ));
+    }
+
+    #[test]
+    fn index_register() {
+        assert_eq!(Index::from(0x10).register(), 0x10);
+        assert_eq!(Index::from(0x62).register(), 2);
+        assert_eq!(Index::from(0x6F).register(), 7);
+    }
+
+    #[test]
+    fn bind_rejects_mismatched_kind() {
+        let bool_idx = Index::from(0x68);
+        assert!(Uniform::Bool(true).check_index(bool_idx).is_ok());
+        assert!(matches!(
+            Uniform::Float4(Matrix4::identity()).check_index(bool_idx),
+            Err(crate::Error::UniformKindMismatch {
+                uniform: Kind::Float,
+                index: Some(Kind::Bool),
+            })
+        ));
+        assert!(matches!(
+            Uniform::Int(IVec::new(0, 0, 0, 0)).check_index(Index::from(0x64)),
+            Err(crate::Error::UniformKindMismatch {
+                uniform: Kind::Int,
+                index: None,
+            })
+        ));
+        assert!(matches!(
+            Uniform::Float4(Matrix4::identity()).check_index(Index::from(0x5D)),
+            Err(crate::Error::UniformOverflow {
+                start: 0x5D,
+                len: 4
+            })
+        ));
+    }
+}
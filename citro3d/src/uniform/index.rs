@@ -0,0 +1,73 @@
+//! Uniform indices, and the register files they refer to.
+
+/// The index of a uniform within a [`shader::Program`](crate::shader::Program),
+/// tagged with the register file ([`Kind`]) it belongs to. Binding a
+/// [`Uniform`](super::Uniform) of a different kind to it fails with
+/// [`Error::UniformKindMismatch`](crate::Error::UniformKindMismatch), instead
+/// of corrupting other uniforms.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Index {
+    pub(super) index: u8,
+    kind: Option<Kind>,
+}
+
+impl From<u8> for Index {
+    /// Create an index from its position in the shader's uniform table. The
+    /// kind is determined by which register file the position falls in.
+    fn from(value: u8) -> Self {
+        // See https://www.3dbrew.org/wiki/SHBIN#Uniform_Table_Entry, offset by
+        // the 0x10 input registers which libctru excludes
+        let kind = match value {
+            0x00..=0x5F => Some(Kind::Float),
+            0x60..=0x63 => Some(Kind::Int),
+            0x68..=0x77 => Some(Kind::Bool),
+            _ => None,
+        };
+        Self { index: value, kind }
+    }
+}
+
+impl Index {
+    /// Get which uniform register file this index belongs to, or `None` if the
+    /// index does not correspond to any uniform register.
+    ///
+    /// Indices returned by
+    /// [`Program::get_uniform`](crate::shader::Program::get_uniform) are taken
+    /// from the shader's uniform table, so their kind always matches how the
+    /// uniform was declared in the shader source.
+    pub fn kind(self) -> Option<Kind> {
+        self.kind
+    }
+}
+
+/// The register files of the PICA200 that uniforms can be stored in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// Float vector registers (`.fvec`), 96 available.
+    Float,
+    /// Integer vector registers (`.ivec`), 4 available.
+    Int,
+    /// Boolean registers (`.bool`), 16 available.
+    Bool,
+}
+
+impl From<Index> for i32 {
+    fn from(value: Index) -> Self {
+        value.index.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_kind() {
+        assert_eq!(Index::from(0x00).kind(), Some(Kind::Float));
+        assert_eq!(Index::from(0x5F).kind(), Some(Kind::Float));
+        assert_eq!(Index::from(0x60).kind(), Some(Kind::Int));
+        assert_eq!(Index::from(0x64).kind(), None);
+        assert_eq!(Index::from(0x68).kind(), Some(Kind::Bool));
+        assert_eq!(Index::from(0x78).kind(), None);
+    }
+}